@@ -1,35 +1,182 @@
-use futures::StreamExt;
+use futures::{SinkExt, Stream, StreamExt};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::atomic::{AtomicU64, Ordering},
+    sync::Arc,
+};
 use tokio::{
-    io::AsyncWriteExt,
-    net::TcpListener,
-    sync::{mpsc, RwLock},
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{tcp::OwnedReadHalf, TcpListener},
+    sync::{mpsc, oneshot, watch, RwLock},
     time::{Duration, Instant},
 };
-use tokio_util::codec::{FramedRead, LinesCodec};
-use uno::game::events::GameEvent as GE;
-use uno::game::UnoGame;
-use uno::protocol::{Client2Server, Server2Client};
+use tokio_tungstenite::tungstenite::Message;
+use uno::bot::{new_strategy, BotAction, BotDifficulty, BotView, Strategy};
+use uno::game::events::{redact_events, GameEvent as GE};
+use uno::game::{Accepted, Match, PlayerId, RuleError, RuleSet, UnoGame};
+use uno::ports::accounts::{Account, AccountError, AccountStore};
+use uno::ports::replay::{ReplayHeader, ReplayRecorder};
+use uno::ports::stats::StatsStore;
+use uno::protocol::chat::sanitize_chat;
+use uno::protocol::client2server::MAX_PLAYERS;
+use uno::protocol::handshake::MIN_PROTOCOL_VERSION;
+use uno::protocol::codec::{JsonLines, MsgPack};
+use uno::protocol::{
+    Capability, Client2Server, Codec, ErrorCode, Frame, RoomInfo, Server2Client, WireFormat, PROTOCOL_VERSION,
+};
 
 // ===== 房间与命令定义 =====
 type RoomId = String;
 type ConnId = u64;
 
+// 断线后保留座位的时长, 期间可凭 session_id 重连
+const RECONNECT_GRACE: Duration = Duration::from_secs(60);
+// 机器人使用的虚拟连接 ID, 不会与真实连接冲突
+const BOT_CONN_BASE: ConnId = 1 << 63;
+// 从快照恢复、还没重连的玩家使用的虚拟连接 ID
+const RESTORED_CONN_BASE: ConnId = 1 << 62;
+// 机器人每步之间的停顿, 让真人看得清
+const BOT_THINK_TIME: Duration = Duration::from_millis(500);
+// 大厅查询单个房间的最长等待时间
+const ROOM_INFO_TIMEOUT: Duration = Duration::from_secs(1);
+// 连续超时这么多次后判定挂机, 交给电脑代打
+const AFK_TIMEOUTS: u32 = 3;
+// 聊天限流: 每个连接在 CHAT_WINDOW 内最多发 CHAT_BURST 条
+const CHAT_BURST: usize = 5;
+const CHAT_WINDOW: Duration = Duration::from_secs(10);
+
+// 创建房间时确定, 之后不再改变
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RoomSettings {
+    rules: RuleSet,
+    max_players: usize,
+    password: Option<RoomPassword>,
+}
+impl Default for RoomSettings {
+    fn default() -> Self {
+        Self {
+            rules: RuleSet::default(),
+            max_players: MAX_PLAYERS,
+            password: None,
+        }
+    }
+}
+
+// 房间密码只保存加盐的摘要, 快照文件里不出现明文
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RoomPassword {
+    salt: String,
+    hash: String,
+}
+impl RoomPassword {
+    fn new(password: &str) -> Self {
+        let salt: String = rand::thread_rng().sample_iter(&Alphanumeric).take(16).map(char::from).collect();
+        let hash = Self::digest(&salt, password);
+        Self { salt, hash }
+    }
+
+    fn digest(salt: &str, password: &str) -> String {
+        Sha256::new()
+            .chain_update(salt)
+            .chain_update(password)
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    fn matches(&self, password: Option<&str>) -> bool {
+        password.is_some_and(|p| Self::digest(&self.salt, p) == self.hash)
+    }
+}
+
 #[derive(Debug)]
 enum RoomCmd {
+    // reply 告诉连接是否进入了房间, 房间满了或密码错误时为 false
     Join {
         conn_id: ConnId,
         name: String,
+        account: bool, // name 是登录的账号名, 战绩记到账号下
+        password: Option<String>,
+        tx_client: mpsc::Sender<Server2Client>,
+        detach: mpsc::Sender<()>,
+        reply: oneshot::Sender<bool>,
+    },
+    Spectate {
+        conn_id: ConnId,
+        name: String,
+        account: bool,  // name 是登录的账号名
+        god_view: bool, // 请求上帝视角, 只有房主和裁判能拿到
+        referee: bool,  // 已经校验过裁判口令
+        password: Option<String>,
         tx_client: mpsc::Sender<Server2Client>,
+        reply: oneshot::Sender<bool>,
+    },
+    // 大厅查询房间概况
+    Info {
+        reply: oneshot::Sender<RoomInfo>,
     },
     Leave {
         conn_id: ConnId,
     },
+    Resume {
+        conn_id: ConnId,
+        session_id: String,
+        tx_client: mpsc::Sender<Server2Client>,
+        detach: mpsc::Sender<()>,
+        reply: oneshot::Sender<bool>,
+    },
     GameMsg {
         conn_id: ConnId,
         msg: Client2Server,
     },
+    // 服务器关闭前写最后一次快照, 写完后房间任务结束
+    Shutdown {
+        reply: oneshot::Sender<()>,
+    },
+}
+
+// 房间快照: 重启后恢复房间和进行中的比赛, 真人玩家凭原来的 session_id 重连
+#[derive(Serialize, Deserialize)]
+struct RoomSnapshot {
+    room_id: RoomId,
+    settings: RoomSettings,
+    uno_match: Match,
+    seats: Vec<SeatSnapshot>,
+    next_pid: usize,
+    host: Option<PlayerId>,
+    locked: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SeatSnapshot {
+    pid: PlayerId,
+    name: String,
+    session_id: String,
+    bot: Option<BotDifficulty>,
+    #[serde(default)]
+    account: bool,
+}
+
+impl RoomSnapshot {
+    // 新建的空房间
+    fn new(room_id: &str, settings: RoomSettings) -> Self {
+        Self {
+            room_id: room_id.to_string(),
+            uno_match: Match::new(settings.rules.clone()),
+            settings,
+            seats: Vec::new(),
+            next_pid: 0,
+            host: None,
+            locked: false,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -40,37 +187,118 @@ struct RoomHandle {
 #[derive(Clone)]
 struct Rooms {
     inner: Arc<RwLock<HashMap<RoomId, RoomHandle>>>,
+    sessions: Arc<RwLock<HashMap<String, RoomId>>>, // session_id -> 房间
+    store: Option<SnapshotStore>,
 }
 impl Rooms {
-    fn new() -> Self {
+    fn new(store: Option<SnapshotStore>) -> Self {
         Self {
             inner: Arc::new(RwLock::new(HashMap::new())),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            store,
+        }
+    }
+    // 启动时恢复上次保存的房间, 返回恢复的房间数
+    async fn restore(&self) -> usize {
+        let Some(store) = &self.store else { return 0 };
+        let snapshots = store.load_all();
+        let count = snapshots.len();
+        for snapshot in snapshots {
+            let room_id = snapshot.room_id.clone();
+            for seat in snapshot.seats.iter().filter(|s| s.bot.is_none()) {
+                self.add_session(&seat.session_id, &room_id).await;
+            }
+            let handle = self.spawn_room(snapshot);
+            self.inner.write().await.insert(room_id, handle);
         }
+        count
     }
-    async fn get_or_create(&self, id: &str) -> RoomHandle {
-        if let Some(h) = self.inner.read().await.get(id).cloned() {
-            return h;
+    // 让每个房间写最后一次快照, 等文件都写完再返回
+    async fn shutdown(&self) {
+        for handle in self.list().await {
+            let (reply, done) = oneshot::channel();
+            if handle.tx.send(RoomCmd::Shutdown { reply }).await.is_ok() {
+                let _ = done.await;
+            }
         }
-        let (tx, rx) = mpsc::channel(256);
-        let handle = RoomHandle { tx: tx.clone() };
-        self.inner
+        if let Some(store) = &self.store {
+            store.flush().await;
+        }
+    }
+    async fn find_session(&self, session_id: &str) -> Option<RoomHandle> {
+        let room_id = self.sessions.read().await.get(session_id).cloned()?;
+        self.inner.read().await.get(&room_id).cloned()
+    }
+    async fn add_session(&self, session_id: &str, room_id: &str) {
+        self.sessions
             .write()
             .await
-            .insert(id.to_string(), handle.clone());
-        tokio::spawn(room_task(id.to_string(), rx, self.clone()));
-        handle
+            .insert(session_id.to_string(), room_id.to_string());
+    }
+    async fn remove_session(&self, session_id: &str) {
+        self.sessions.write().await.remove(session_id);
+    }
+    async fn get(&self, id: &str) -> Option<RoomHandle> {
+        self.inner.read().await.get(id).cloned()
+    }
+    // 只新建, 房间已存在时返回 None
+    async fn create(&self, id: &str, settings: RoomSettings) -> Option<RoomHandle> {
+        let mut inner = self.inner.write().await;
+        if inner.contains_key(id) {
+            return None;
+        }
+        let handle = self.spawn_room(RoomSnapshot::new(id, settings));
+        inner.insert(id.to_string(), handle.clone());
+        Some(handle)
+    }
+    fn spawn_room(&self, snapshot: RoomSnapshot) -> RoomHandle {
+        let (tx, rx) = mpsc::channel(256);
+        tokio::spawn(room_task(rx, self.clone(), snapshot));
+        RoomHandle { tx }
+    }
+    async fn list(&self) -> Vec<RoomHandle> {
+        self.inner.read().await.values().cloned().collect()
     }
     async fn remove(&self, id: &str) {
         self.inner.write().await.remove(id);
+        self.sessions.write().await.retain(|_, room| room != id);
+        if let Some(store) = &self.store {
+            store.remove(id);
+        }
     }
 }
 
 #[derive(Clone)]
 struct PlayerSlot {
     conn_id: ConnId,
-    pid: usize,
+    pid: PlayerId,
+    name: String,
+    session_id: String,
+    tx: mpsc::Sender<Server2Client>,
+    detach: mpsc::Sender<()>, // 被踢出时通知连接离开房间
+    disconnected_at: Option<Instant>, // 断线时间, None 表示在线
+    bot: Option<BotDifficulty>, // 电脑玩家的难度, 真人为 None
+    account: bool, // 登录了账号的真人, 记录战绩
+}
+
+// 房主和房间锁, 房主命令在 handle_game_msg 中校验
+#[derive(Default)]
+struct HostControl {
+    host: Option<PlayerId>, // 房主的 pid, 第一个加入的真人
+    locked: bool,
+    kicked: Option<PlayerId>, // 刚被踢出, 等 room_task 腾出座位
+    // 开房后只观战不入座的房主账号, 可以开上帝视角; 入座后失效
+    watching_host: Option<String>,
+}
+
+// 只读观战者, 不占座位
+#[derive(Clone)]
+struct Spectator {
+    conn_id: ConnId,
     name: String,
+    account: bool,
     tx: mpsc::Sender<Server2Client>,
+    god_view: bool,
 }
 
 macro_rules! log_ts { ($($arg:tt)*) => {{
@@ -86,40 +314,512 @@ fn room_log(room: &str, msg: &str) {
     log_ts!("room={} {}", room, msg);
 }
 
-async fn room_task(room_id: RoomId, mut rx: mpsc::Receiver<RoomCmd>, rooms: Rooms) {
-    let mut game = UnoGame::new();
-    let mut players: Vec<PlayerSlot> = Vec::new();
-    let mut started = false;
+// 设置了 UNO_DATA_DIR 时每个房间写一个快照文件, 启动时全部恢复
+// 文件由后台线程按提交顺序写, 房间任务不会被磁盘阻塞
+#[derive(Clone)]
+struct SnapshotStore {
+    dir: PathBuf,
+    writer: std::sync::mpsc::Sender<SnapshotJob>,
+}
+
+enum SnapshotJob {
+    Save(RoomId, Vec<u8>),
+    Remove(RoomId),
+    Flush(oneshot::Sender<()>),
+}
+
+impl SnapshotStore {
+    fn from_env() -> Option<Self> {
+        let dir = PathBuf::from(std::env::var("UNO_DATA_DIR").ok()?).join("rooms");
+        match std::fs::create_dir_all(&dir) {
+            Ok(()) => {
+                let (writer, rx) = std::sync::mpsc::channel();
+                let thread_dir = dir.clone();
+                std::thread::spawn(move || snapshot_write_loop(&thread_dir, rx));
+                Some(Self { dir, writer })
+            }
+            Err(e) => {
+                log_ts!("cannot keep snapshots in {}: {}", dir.display(), e);
+                None
+            }
+        }
+    }
+    // 序列化在调用方完成, 写文件交给后台线程
+    fn save(&self, snapshot: &RoomSnapshot) -> anyhow::Result<()> {
+        let data = serde_json::to_vec(snapshot)?;
+        let _ = self.writer.send(SnapshotJob::Save(snapshot.room_id.clone(), data));
+        Ok(())
+    }
+    fn remove(&self, room_id: &str) {
+        let _ = self.writer.send(SnapshotJob::Remove(room_id.to_string()));
+    }
+    // 等后台线程写完之前交给它的快照
+    async fn flush(&self) {
+        let (done, wait) = oneshot::channel();
+        if self.writer.send(SnapshotJob::Flush(done)).is_ok() {
+            let _ = wait.await;
+        }
+    }
+    // 读不了的快照跳过, 不影响其他房间
+    fn load_all(&self) -> Vec<RoomSnapshot> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut snapshots = Vec::new();
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let res = std::fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|data| Ok(serde_json::from_slice::<RoomSnapshot>(&data)?));
+            match res {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(e) => log_ts!("skip snapshot {}: {}", path.display(), e),
+            }
+        }
+        snapshots
+    }
+}
+
+// 房间名由客户端决定, 转成十六进制再做文件名
+fn snapshot_path(dir: &Path, room_id: &str) -> PathBuf {
+    let hex: String = room_id.bytes().map(|b| format!("{:02x}", b)).collect();
+    dir.join(format!("room-{}.json", hex))
+}
+
+// 先写临时文件再改名, 写到一半崩溃也不会留下半个快照; 写失败只记日志
+fn snapshot_write_loop(dir: &Path, rx: std::sync::mpsc::Receiver<SnapshotJob>) {
+    for job in rx {
+        match job {
+            SnapshotJob::Save(room_id, data) => {
+                let path = snapshot_path(dir, &room_id);
+                let tmp = path.with_extension("json.tmp");
+                match std::fs::write(&tmp, data).and_then(|_| std::fs::rename(&tmp, &path)) {
+                    Ok(()) => room_log(&room_id, "snapshot saved"),
+                    Err(e) => room_log(&room_id, &format!("snapshot failed: {}", e)),
+                }
+            }
+            SnapshotJob::Remove(room_id) => {
+                let _ = std::fs::remove_file(snapshot_path(dir, &room_id));
+            }
+            SnapshotJob::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+// 注册账号和战绩, 设置了 UNO_DATA_DIR 时存成文件, 否则只保存在内存里
+struct Profiles {
+    accounts: Mutex<AccountStore>,
+    stats: Mutex<StatsStore>,
+}
+
+static PROFILES: OnceLock<Profiles> = OnceLock::new();
+
+fn profiles() -> &'static Profiles {
+    PROFILES.get_or_init(|| {
+        let dir = std::env::var("UNO_DATA_DIR").ok().map(PathBuf::from);
+        // 文件读不了时退回内存, 不覆盖原来的文件
+        let accounts = dir
+            .as_ref()
+            .and_then(|d| AccountStore::open(d.join("accounts.json")).map_err(|e| log_ts!("cannot load accounts: {}", e)).ok())
+            .unwrap_or_else(AccountStore::in_memory);
+        let stats = dir
+            .as_ref()
+            .and_then(|d| StatsStore::open(d.join("stats.json")).map_err(|e| log_ts!("cannot load stats: {}", e)).ok())
+            .unwrap_or_else(StatsStore::in_memory);
+        Profiles {
+            accounts: Mutex::new(accounts),
+            stats: Mutex::new(stats),
+        }
+    })
+}
+
+// 算摘要很慢, 在阻塞线程里调用; 返回账号名
+fn login(name: &str, password: &str, register: bool) -> Result<String, AccountError> {
+    let accounts = &profiles().accounts;
+    if register {
+        if accounts.lock().unwrap().is_registered(name.trim()) {
+            return Err(AccountError::NameTaken);
+        }
+        let account = Account::new(name, password)?;
+        let name = account.name.clone();
+        accounts.lock().unwrap().insert(account)?;
+        return Ok(name);
+    }
+    let account = accounts.lock().unwrap().get(name.trim()).cloned();
+    match account {
+        Some(account) if account.verify(password) => Ok(account.name),
+        _ => Err(AccountError::WrongPassword),
+    }
+}
+
+// 登录后一律用账号名入座; 游客不能用已注册的名字
+fn seat_name(account: &Option<String>, name: &str) -> Result<String, Server2Client> {
+    if let Some(account) = account {
+        return Ok(account.clone());
+    }
+    if profiles().accounts.lock().unwrap().is_registered(name.trim()) {
+        return Err(Server2Client::ServerError {
+            code: ErrorCode::NameReserved,
+            message: format!("{} is a registered account, log in first", name.trim()),
+        });
+    }
+    Ok(name.to_string())
+}
+
+async fn room_task(mut rx: mpsc::Receiver<RoomCmd>, rooms: Rooms, snapshot: RoomSnapshot) {
+    let RoomSnapshot { room_id, settings, mut uno_match, seats, mut next_pid, host, locked } = snapshot;
+    let replay = replay_recorder();
+    let restored = !seats.is_empty();
+    let mut bots: HashMap<PlayerId, Box<dyn Strategy>> = HashMap::new(); // pid -> 策略
+    // 恢复的真人都算断线, 在重连宽限期内凭 session_id 回到座位
+    let mut players: Vec<PlayerSlot> = seats
+        .into_iter()
+        .map(|seat| match seat.bot {
+            Some(difficulty) => {
+                bots.insert(seat.pid, new_strategy(difficulty));
+                bot_slot(seat.pid, seat.name, difficulty)
+            }
+            None => restored_slot(seat),
+        })
+        .collect();
+    let mut spectators: Vec<Spectator> = Vec::new();
+    let mut afk: HashSet<PlayerId> = HashSet::new(); // 挂机后由电脑代打的真人
+    let mut timeouts: HashMap<PlayerId, u32> = HashMap::new(); // pid -> 连续超时次数
+    let mut turn_deadline: Option<(PlayerId, Instant)> = None;
+    let mut bot_turn: Option<Instant> = None; // 电脑下一步行动的时间
+    let mut chat_times: HashMap<ConnId, VecDeque<Instant>> = HashMap::new(); // 最近的发言时间
+    let mut ctl = HostControl { host, locked, kicked: None, watching_host: None };
     let mut last_active = Instant::now();
+    let mut dirty = false; // 上次快照之后房间有变化
     let mut ticker = tokio::time::interval(Duration::from_secs(15));
-    room_log(&room_id, "task started");
+    room_log(
+        &room_id,
+        &format!(
+            "task started seed={} max_players={} password={} restored={} rules={:?}",
+            uno_match.game().seed(),
+            settings.max_players,
+            settings.password.is_some(),
+            restored,
+            uno_match.rules()
+        ),
+    );
+    if restored {
+        // 轮到真人时照常倒计时, 轮到电脑时由下面的定时器让它们打
+        let _ = refresh_deadline(&uno_match, &bots, &mut turn_deadline, false);
+    }
     loop {
+        schedule_bot(&uno_match, &bots, &mut bot_turn);
         tokio::select! {
+            _ = tokio::time::sleep_until(bot_turn.unwrap_or_else(Instant::now)), if bot_turn.is_some() => {
+                bot_turn = None;
+                dirty = true;
+                let new_turn = bot_step(&mut uno_match, replay.as_ref(), &mut players, &spectators, &mut ctl, &mut bots).await;
+                if let Some(msg) = refresh_deadline(&uno_match, &bots, &mut turn_deadline, new_turn) {
+                    send_all(&players, &spectators, msg).await;
+                }
+            }
             _ = ticker.tick() => {
-                if players.is_empty() && last_active.elapsed() > Duration::from_secs(30) {
+                if dirty {
+                    save_snapshot(&rooms, &room_id, &settings, &uno_match, &players, next_pid, &ctl);
+                    dirty = false;
+                }
+                // 超过重连宽限期的断线玩家才真正移除
+                let expired: Vec<PlayerSlot> = players
+                    .iter()
+                    .filter(|p| p.disconnected_at.is_some_and(|t| t.elapsed() > RECONNECT_GRACE))
+                    .cloned()
+                    .collect();
+                if !expired.is_empty() {
+                    let mut new_turn = false;
+                    for p in expired {
+                        room_log(&room_id, &format!("reconnect grace expired pid={} name={}", p.pid, p.name));
+                        rooms.remove_session(&p.session_id).await;
+                        new_turn |= vacate_seat(&mut uno_match, replay.as_ref(), &mut players, &spectators, &mut ctl, p.pid).await;
+                        bots.remove(&p.pid);
+                        afk.remove(&p.pid);
+                        timeouts.remove(&p.pid);
+                    }
+                    if let Some(msg) = refresh_deadline(&uno_match, &bots, &mut turn_deadline, new_turn) {
+                        send_all(&players, &spectators, msg).await;
+                    }
+                }
+                if players.iter().all(|p| p.bot.is_some()) && last_active.elapsed() > Duration::from_secs(30) {
                    room_log(&room_id, "idle timeout -> removing room");
                    rooms.remove(&room_id).await;
                     break;
                 }
-                let _ = rx.try_recv(); // 触发下轮select
+            }
+            _ = tokio::time::sleep_until(turn_deadline.map_or_else(Instant::now, |(_, at)| at)), if turn_deadline.is_some() => {
+                let Some((pid, _)) = turn_deadline.take() else { continue };
+                dirty = true;
+                let count = timeouts.entry(pid).or_insert(0);
+                *count += 1;
+                room_log(&room_id, &format!("turn timeout pid={} count={}", pid, count));
+                let new_turn = auto_play(&mut uno_match, replay.as_ref(), &mut players, &spectators, &mut ctl, pid).await;
+                if *count >= AFK_TIMEOUTS && !bots.contains_key(&pid) {
+                    room_log(&room_id, &format!("pid={} is afk -> bot takes over", pid));
+                    afk.insert(pid);
+                    bots.insert(pid, new_strategy(BotDifficulty::Easy));
+                    broadcast_events(&players, &spectators, vec![GE::PlayerAfk { player_id: pid }]).await;
+                }
+                if let Some(msg) = refresh_deadline(&uno_match, &bots, &mut turn_deadline, new_turn) {
+                    send_all(&players, &spectators, msg).await;
+                }
             }
             cmd = rx.recv() => {
-                let Some(cmd) = cmd else { break }; last_active = Instant::now();
+                let Some(cmd) = cmd else { break };
+                // 大厅查询不算房间活跃
+                if !matches!(cmd, RoomCmd::Info { .. }) {
+                    last_active = Instant::now();
+                    dirty = true;
+                }
                 match cmd {
-                    RoomCmd::Join { conn_id, name, tx_client } => {
+                    RoomCmd::Shutdown { reply } => {
+                        save_snapshot(&rooms, &room_id, &settings, &uno_match, &players, next_pid, &ctl);
+                        let _ = reply.send(());
+                        break;
+                    }
+                    RoomCmd::Join { conn_id, tx_client, reply, password, .. }
+                    | RoomCmd::Spectate { conn_id, tx_client, reply, password, .. }
+                        if settings.password.as_ref().is_some_and(|p| !p.matches(password.as_deref())) =>
+                    {
+                        room_log(&room_id, &format!("conn={} wrong password", conn_id));
+                        let _ = tx_client.send(Server2Client::ServerError { code: ErrorCode::WrongPassword, message: "Wrong room password".into() }).await;
+                        let _ = reply.send(false);
+                    }
+                    RoomCmd::Join { conn_id, name, account, tx_client, reply, .. } if uno_match.game().started => {
+                        // 牌局中途加入拿不到手牌, 改为观战
+                        room_log(&room_id, &format!("join conn={} name={} during game -> spectator", conn_id, name));
+                        let _ = reply.send(true);
+                        let _ = tx_client.send(Server2Client::ServerError {
+                            code: ErrorCode::GameAlreadyStarted,
+                            message: "Game already started, joined as spectator".into(),
+                        }).await;
+                        let _ = tx_client.send(Server2Client::Spectating { god_view: false }).await;
+                        spectators.push(Spectator { conn_id, name, account, tx: tx_client, god_view: false });
+                        sync_state(&players, &spectators, uno_match.game()).await;
+                    }
+                    RoomCmd::Join { conn_id, tx_client, reply, .. } if ctl.locked => {
+                        room_log(&room_id, &format!("join conn={} rejected, room is locked", conn_id));
+                        let _ = tx_client.send(Server2Client::ServerError { code: ErrorCode::RoomLocked, message: "Room is locked".into() }).await;
+                        let _ = reply.send(false);
+                    }
+                    RoomCmd::Join { conn_id, tx_client, reply, .. } if players.len() >= settings.max_players => {
+                        room_log(&room_id, &format!("join conn={} rejected, room is full", conn_id));
+                        let _ = tx_client.send(Server2Client::ServerError { code: ErrorCode::RoomFull, message: "Room is full".into() }).await;
+                        let _ = reply.send(false);
+                    }
+                    RoomCmd::Join { conn_id, name, account, tx_client, detach, reply, .. } => {
                        room_log(&room_id, &format!("join conn={} name={} (players before={})", conn_id, name, players.len()));
-                        let pid = players.len();
-                        players.push(PlayerSlot { conn_id, pid, name: name.clone(), tx: tx_client.clone() });
-                        let _ = tx_client.send(Server2Client::Welcome { player_id: pid, session_id: format!("{}-{}", room_id, pid) }).await;
-                        broadcast_events(&players, vec![GE::PlayerJoined { player_id: pid, name }]).await;
-                        sync_state(&players, &game).await;
+                        let _ = reply.send(true);
+                        let pid = PlayerId(next_pid);
+                        next_pid += 1;
+                        let session_id = gen_session_id();
+                        rooms.add_session(&session_id, &room_id).await;
+                        players.push(PlayerSlot {
+                            conn_id,
+                            pid,
+                            name: name.clone(),
+                            session_id: session_id.clone(),
+                            tx: tx_client.clone(),
+                            detach,
+                            disconnected_at: None,
+                            bot: None,
+                            account,
+                        });
+                        let _ = tx_client.send(Server2Client::Welcome { player_id: pid, session_id }).await;
+                        if account {
+                            revoke_god_view(&mut spectators, &mut ctl, &name).await;
+                        }
+                        broadcast_events(&players, &spectators, vec![GE::PlayerJoined { player_id: pid, name }]).await;
+                        match ctl.host {
+                            Some(host) => {
+                                let _ = tx_client.send(Server2Client::Events(vec![GE::HostChanged { player_id: host }])).await;
+                            }
+                            None => {
+                                ctl.host = Some(pid);
+                                broadcast_events(&players, &spectators, vec![GE::HostChanged { player_id: pid }]).await;
+                            }
+                        }
+                        sync_state(&players, &spectators, uno_match.game()).await;
+                    }
+                    RoomCmd::Spectate { conn_id, name, account, god_view, referee, tx_client, reply, .. } => {
+                        let _ = reply.send(true);
+                        // 登录的账号打开还没有房主的空房间时成为房主, 只要不入座就能开上帝视角
+                        if account && ctl.host.is_none() && ctl.watching_host.is_none() && players.is_empty() {
+                            ctl.watching_host = Some(name.clone());
+                        }
+                        let host = account && ctl.watching_host.as_ref() == Some(&name);
+                        let god_view = god_view && (host || referee);
+                        room_log(&room_id, &format!("spectate conn={} name={} god_view={}", conn_id, name, god_view));
+                        let _ = tx_client.send(Server2Client::Spectating { god_view }).await;
+                        spectators.push(Spectator { conn_id, name, account, tx: tx_client, god_view });
+                        sync_state(&players, &spectators, uno_match.game()).await;
+                    }
+                    RoomCmd::GameMsg { conn_id, msg: msg @ (Client2Server::Chat { .. } | Client2Server::SendEmote { .. }) } => {
+                        let sender = players.iter().find(|p| p.conn_id == conn_id).map(|p| (p.name.clone(), p.tx.clone()))
+                            .or_else(|| spectators.iter().find(|s| s.conn_id == conn_id).map(|s| (s.name.clone(), s.tx.clone())));
+                        let Some((from, tx)) = sender else { continue };
+                        let text = match msg {
+                            Client2Server::SendEmote { emote } => Ok(emote.text().to_string()),
+                            Client2Server::Chat { text } => sanitize_chat(&text),
+                            _ => unreachable!(),
+                        };
+                        let text = match text {
+                            Ok(text) if chat_allowed(chat_times.entry(conn_id).or_default()) => text,
+                            Ok(_) => {
+                                let _ = tx.send(Server2Client::ServerError { code: ErrorCode::RateLimited, message: "Sending messages too fast".into() }).await;
+                                continue;
+                            }
+                            Err(message) => {
+                                let _ = tx.send(Server2Client::ServerError { code: ErrorCode::InvalidChat, message }).await;
+                                continue;
+                            }
+                        };
+                        room_log(&room_id, &format!("chat conn={} from={} len={}", conn_id, from, text.chars().count()));
+                        send_all(&players, &spectators, Server2Client::ChatMessage { from, text, ts: now_ms() }).await;
+                    }
+                    RoomCmd::GameMsg { conn_id, .. } if spectators.iter().any(|s| s.conn_id == conn_id) => {
+                        if let Some(s) = spectators.iter().find(|s| s.conn_id == conn_id) {
+                            let _ = s.tx.send(Server2Client::ServerError {
+                                code: ErrorCode::SpectatorCannotPlay,
+                                message: "Spectators cannot play".into(),
+                            }).await;
+                        }
                     }
                     RoomCmd::Leave { conn_id } => {
                        room_log(&room_id, &format!("leave conn={}", conn_id));
-                        players.retain(|p| p.conn_id != conn_id);
+                        if let Some(s) = spectators.iter().find(|s| s.conn_id == conn_id) {
+                            room_log(&room_id, &format!("spectator left name={}", s.name));
+                        }
+                        spectators.retain(|s| s.conn_id != conn_id);
+                        chat_times.remove(&conn_id);
+                        let Some(pid) = players.iter().find(|p| p.conn_id == conn_id).map(|p| p.pid) else {
+                            continue;
+                        };
+                        if uno_match.in_progress() {
+                            // 比赛中断线: 保留座位和手牌, 等待重连
+                            if let Some(p) = players.iter_mut().find(|p| p.conn_id == conn_id) {
+                                p.disconnected_at = Some(Instant::now());
+                            }
+                            hand_over_host(&players, &spectators, &mut ctl, pid).await;
+                        } else {
+                            for p in players.iter().filter(|p| p.conn_id == conn_id) {
+                                rooms.remove_session(&p.session_id).await;
+                            }
+                            vacate_seat(&mut uno_match, replay.as_ref(), &mut players, &spectators, &mut ctl, pid).await;
+                        }
+                    }
+                    RoomCmd::Info { reply } => {
+                        let _ = reply.send(RoomInfo {
+                            room_id: room_id.clone(),
+                            players: players.len(),
+                            max_players: settings.max_players,
+                            started: uno_match.game().started,
+                            rules: uno_match.rules().clone(),
+                            has_password: settings.password.is_some(),
+                            locked: ctl.locked,
+                        });
+                    }
+                    RoomCmd::Resume { conn_id, session_id, tx_client, detach, reply } => {
+                        let Some(p) = players.iter_mut().find(|p| p.session_id == session_id) else {
+                            let _ = reply.send(false);
+                            continue;
+                        };
+                        room_log(&room_id, &format!("resume conn={} pid={} (old conn={})", conn_id, p.pid, p.conn_id));
+                        p.conn_id = conn_id;
+                        p.tx = tx_client.clone();
+                        p.detach = detach;
+                        p.disconnected_at = None;
+                        let _ = reply.send(true);
+                        let _ = tx_client.send(Server2Client::Welcome { player_id: p.pid, session_id }).await;
+                        sync_state(&players, &spectators, uno_match.game()).await;
+                        if let Some((pid, at)) = turn_deadline {
+                            let deadline_ms = at.saturating_duration_since(Instant::now()).as_millis() as u64;
+                            let _ = tx_client.send(Server2Client::TurnDeadline { player_id: pid, deadline_ms }).await;
+                        }
+                    }
+                    RoomCmd::GameMsg { conn_id, msg: Client2Server::SetRules { rules } } if rules.check(settings.max_players).is_err() => {
+                        if let Err(message) = rules.check(settings.max_players) {
+                            send_err(&players, conn_id, ErrorCode::InvalidRoomConfig, &message).await;
+                        }
+                    }
+                    RoomCmd::GameMsg { conn_id, msg: Client2Server::AddBot { difficulty } } => {
+                        if uno_match.game().started {
+                            send_err(&players, conn_id, ErrorCode::GameAlreadyStarted, "Game already started").await;
+                            continue;
+                        }
+                        if players.len() >= settings.max_players {
+                            send_err(&players, conn_id, ErrorCode::RoomFull, "Room is full").await;
+                            continue;
+                        }
+                        if players.iter().find(|p| p.conn_id == conn_id).map(|p| p.pid) != ctl.host {
+                            send_err(&players, conn_id, ErrorCode::NotHost, "Only the host can add bots").await;
+                            continue;
+                        }
+                        let pid = PlayerId(next_pid);
+                        next_pid += 1;
+                        let name = format!("Bot-{}", pid);
+                        room_log(&room_id, &format!("add bot pid={} difficulty={:?} by conn={}", pid, difficulty, conn_id));
+                        players.push(bot_slot(pid, name.clone(), difficulty));
+                        bots.insert(pid, new_strategy(difficulty));
+                        broadcast_events(&players, &spectators, vec![GE::PlayerJoined { player_id: pid, name }]).await;
+                        sync_state(&players, &spectators, uno_match.game()).await;
+                    }
+                    RoomCmd::GameMsg { conn_id, msg: Client2Server::LeaveGame { player_id } } => {
+                        let Some(seat) = players.iter().find(|p| p.conn_id == conn_id) else { continue };
+                        if seat.pid != player_id {
+                            send_err(&players, conn_id, ErrorCode::PlayerMismatch, "Player mismatch").await;
+                            continue;
+                        }
+                        room_log(&room_id, &format!("leave game pid={} name={}", player_id, seat.name));
+                        rooms.remove_session(&seat.session_id).await;
+                        // 主动离开, 连接回到大厅
+                        let _ = seat.detach.send(()).await;
+                        let new_turn = vacate_seat(&mut uno_match, replay.as_ref(), &mut players, &spectators, &mut ctl, player_id).await;
+                        bots.remove(&player_id);
+                        afk.remove(&player_id);
+                        timeouts.remove(&player_id);
+                        if let Some(msg) = refresh_deadline(&uno_match, &bots, &mut turn_deadline, new_turn) {
+                            send_all(&players, &spectators, msg).await;
+                        }
                     }
                     RoomCmd::GameMsg { conn_id, msg } => {
-                        handle_game_msg(&mut game, &mut started, &mut players, conn_id, msg).await;
+                        // 本人操作了就不算挂机, 收回电脑代打
+                        if let Some(pid) = players.iter().find(|p| p.conn_id == conn_id).map(|p| p.pid) {
+                            timeouts.remove(&pid);
+                            if afk.remove(&pid) {
+                                room_log(&room_id, &format!("pid={} is back from afk", pid));
+                                bots.remove(&pid);
+                                broadcast_events(&players, &spectators, vec![GE::PlayerReturned { player_id: pid }]).await;
+                            }
+                        }
+                        let mut new_turn = handle_game_msg(&mut uno_match, replay.as_ref(), &mut players, &spectators, &mut ctl, conn_id, msg).await;
+                        if let Some(pid) = ctl.kicked.take() {
+                            let Some(seat) = players.iter_mut().find(|p| p.pid == pid) else { continue };
+                            room_log(&room_id, &format!("kick pid={} name={}", pid, seat.name));
+                            rooms.remove_session(&seat.session_id).await;
+                            let _ = seat.detach.send(()).await;
+                            if uno_match.in_progress() {
+                                // 比赛中被踢, 座位交给电脑打完
+                                *seat = bot_slot(pid, seat.name.clone(), BotDifficulty::Easy);
+                                afk.remove(&pid);
+                                timeouts.remove(&pid);
+                                bots.insert(pid, new_strategy(BotDifficulty::Easy));
+                                new_turn = true;
+                            } else {
+                                players.retain(|p| p.pid != pid);
+                                bots.remove(&pid);
+                            }
+                            sync_state(&players, &spectators, uno_match.game()).await;
+                        }
+                        if let Some(msg) = refresh_deadline(&uno_match, &bots, &mut turn_deadline, new_turn) {
+                            send_all(&players, &spectators, msg).await;
+                        }
                     }
                 }
             }
@@ -129,65 +829,66 @@ async fn room_task(room_id: RoomId, mut rx: mpsc::Receiver<RoomCmd>, rooms: Room
 }
 
 async fn handle_game_msg(
-    game: &mut UnoGame,
-    started: &mut bool,
+    uno_match: &mut Match,
+    replay: Option<&ReplayRecorder>,
     players: &mut Vec<PlayerSlot>,
+    spectators: &[Spectator],
+    ctl: &mut HostControl,
     conn_id: ConnId,
     msg: Client2Server,
-) {
+) -> bool {
     use Client2Server::*;
     let find_pid =
         |v: &Vec<PlayerSlot>, cid: ConnId| v.iter().find(|p| p.conn_id == cid).map(|p| p.pid);
-    let mut need_reset_after_sync = false; // GameOver 后同步一次旧状态再重置
+    // 连接只能替自己的座位行动, 其余规则由 UnoGame 校验
+    let claimed = match &msg {
+        StartGame { player_id }
+        | PlayCard { player_id, .. }
+        | DrawCard { player_id, .. }
+        | PassTurn { player_id }
+        | ChooseColor { player_id, .. }
+        | AcceptWildDrawFour { player_id } => Some(*player_id),
+        ChallengeWildDrawFour { challenger_id, .. } => Some(*challenger_id),
+        _ => None,
+    };
+    if claimed.is_some() && find_pid(players, conn_id) != claimed {
+        send_err(players, conn_id, ErrorCode::PlayerMismatch, "Player mismatch").await;
+        return false;
+    }
+    let mut new_turn = false; // 是否轮到了新的回合, 用于重置回合倒计时
     match msg {
         StartGame { player_id } => {
-            if *started {
-                send_err(players, conn_id, "Game already started").await;
-                return;
+            if ctl.host != Some(player_id) {
+                send_err(players, conn_id, ErrorCode::NotHost, "Only the host can start the game").await;
+                return false;
             }
-            if find_pid(players, conn_id) != Some(player_id) {
-                send_err(players, conn_id, "Player mismatch").await;
-                return;
+            let roster = players.iter().map(|p| (p.pid, p.name.clone())).collect();
+            let res = uno_match.start_round(roster);
+            if let (Some(replay), Ok(ev)) = (replay, &res) {
+                if let Some(header) = ReplayHeader::from_events(uno_match.round(), ev) {
+                    replay.begin(header, ev);
+                }
             }
-            let names: Vec<String> = players.iter().map(|p| p.name.clone()).collect();
-            let ev = game.init_game(names);
-            *started = true;
-            if ev.iter().any(|e| matches!(e, GE::GameOver { .. })) {
-                *started = false;
-                need_reset_after_sync = true;
+            if res.is_ok() {
+                log_ts!(
+                    "start round={} players={} conn={} pid={}",
+                    uno_match.round(),
+                    players.len(),
+                    conn_id,
+                    player_id
+                );
             }
-            log_ts!(
-                "start game players={} conn={} pid={}",
-                players.len(),
-                conn_id,
-                player_id
-            );
-            broadcast_events(players, ev).await;
+            new_turn |= publish(players, spectators, conn_id, res).await;
         }
         PlayCard {
             player_id,
             card_index,
             color,
             call_uno,
+            swap_with,
         } => {
-            if !*started {
-                send_err(players, conn_id, "Game not started").await;
-                return;
-            }
-            if find_pid(players, conn_id) != Some(player_id) {
-                send_err(players, conn_id, "Player mismatch").await;
-                return;
-            }
-            if player_id != game.current_player {
-                send_err(players, conn_id, "Not your turn").await;
-                return;
-            }
-            let ev = game.play_card(player_id, card_index, call_uno, color);
-            if ev.iter().any(|e| matches!(e, GE::GameOver { .. })) {
-                *started = false;
-                need_reset_after_sync = true;
-            }
-            if ev.iter().any(|e| matches!(e, GE::CardPlayed { .. })) {
+            let res = accepted(replay, uno_match.play_card(player_id, card_index, call_uno, color, swap_with));
+            if res.is_ok() {
                 log_ts!(
                     "play conn={} pid={} card_index={} call_uno={}",
                     conn_id,
@@ -196,72 +897,491 @@ async fn handle_game_msg(
                     call_uno
                 );
             }
-            broadcast_events(players, ev).await;
+            new_turn |= publish(players, spectators, conn_id, res).await;
         }
         DrawCard { player_id, count } => {
-            if !*started {
-                send_err(players, conn_id, "Game not started").await;
-                return;
-            }
-            if find_pid(players, conn_id) != Some(player_id) {
-                send_err(players, conn_id, "Player mismatch").await;
-                return;
-            }
             for _ in 0..count.max(1) {
-                let ev = game.draw_card(player_id);
-                if ev.iter().any(|e| matches!(e, GE::GameOver { .. })) {
-                    *started = false;
-                    need_reset_after_sync = true;
+                let res = accepted(replay, uno_match.draw_card(player_id));
+                let ok = res.is_ok();
+                new_turn |= publish(players, spectators, conn_id, res).await;
+                if !ok {
+                    break;
                 }
-                broadcast_events(players, ev).await;
             }
         }
         PassTurn { player_id } => {
-            if find_pid(players, conn_id) != Some(player_id) {
-                send_err(players, conn_id, "Player mismatch").await;
-                return;
+            let res = accepted(replay, uno_match.player_pass(player_id));
+            log_ts!("pass conn={} pid={}", conn_id, player_id);
+            new_turn |= publish(players, spectators, conn_id, res).await;
+        }
+        ChooseColor { player_id, color } => {
+            let res = accepted(replay, uno_match.choose_color(player_id, color));
+            log_ts!("choose color conn={} pid={} color={:?}", conn_id, player_id, color);
+            new_turn |= publish(players, spectators, conn_id, res).await;
+        }
+        KickPlayer { .. } | TransferHost { .. } | LockRoom { .. } | SetRules { .. }
+            if find_pid(players, conn_id).is_none() || find_pid(players, conn_id) != ctl.host =>
+        {
+            send_err(players, conn_id, ErrorCode::NotHost, "Only the host can do this").await;
+            return false;
+        }
+        KickPlayer { player_id } => {
+            if ctl.host == Some(player_id) || !players.iter().any(|p| p.pid == player_id && p.bot.is_none()) {
+                send_err(players, conn_id, ErrorCode::InvalidPlayer, "Invalid player to kick").await;
+                return false;
             }
-            let ev = game.player_pass(player_id);
-            if ev.iter().any(|e| matches!(e, GE::GameOver { .. })) {
-                *started = false;
-                need_reset_after_sync = true;
+            log_ts!("kick conn={} pid={}", conn_id, player_id);
+            ctl.kicked = Some(player_id);
+            broadcast_events(players, spectators, vec![GE::PlayerKicked { player_id }]).await;
+        }
+        TransferHost { player_id } => {
+            if !players.iter().any(|p| p.pid == player_id && p.bot.is_none()) {
+                send_err(players, conn_id, ErrorCode::InvalidPlayer, "Host must be a human player").await;
+                return false;
             }
-            log_ts!("pass conn={} pid={}", conn_id, player_id);
-            broadcast_events(players, ev).await;
+            log_ts!("transfer host conn={} to pid={}", conn_id, player_id);
+            ctl.host = Some(player_id);
+            broadcast_events(players, spectators, vec![GE::HostChanged { player_id }]).await;
+        }
+        LockRoom { locked } => {
+            ctl.locked = locked;
+            broadcast_events(players, spectators, vec![GE::RoomLocked { locked }]).await;
+        }
+        SetRules { rules } => {
+            let res = uno_match.set_rules(rules);
+            publish(players, spectators, conn_id, res).await;
+        }
+        LeaveGame { .. } => {} // 在 room_task 中处理
+        JoinGame { .. } | CreateRoom { .. } | Spectate { .. } | Resume { .. } | Hello { .. } | Login { .. } => {
+            send_err(players, conn_id, ErrorCode::AlreadyJoined, "Already in room").await;
+        }
+        AddBot { .. } | Chat { .. } | SendEmote { .. } => {} // 在 room_task 中处理
+        ListRooms | GetStats { .. } => {} // 在 handle_connection 中处理
+        ChallengeWildDrawFour {
+            challenger_id,
+            challenged_id,
+        } => {
+            let res = accepted(replay, uno_match.challenge(challenger_id, challenged_id));
+            log_ts!(
+                "challenge conn={} challenger={} challenged={}",
+                conn_id,
+                challenger_id,
+                challenged_id
+            );
+            new_turn |= publish(players, spectators, conn_id, res).await;
+        }
+        AcceptWildDrawFour { player_id } => {
+            let res = accepted(replay, uno_match.accept_draw_four(player_id));
+            new_turn |= publish(players, spectators, conn_id, res).await;
+        }
+    }
+    // 一局结束后保留最终状态, 下一次 StartGame 开始新的一局
+    sync_state(players, spectators, uno_match.game()).await;
+    new_turn
+}
+
+// 被接受的操作连同它产生的事件交给回放记录器
+fn accepted(replay: Option<&ReplayRecorder>, res: Result<Accepted, RuleError>) -> Result<Vec<GE>, RuleError> {
+    res.map(|a| {
+        if let Some(replay) = replay {
+            replay.record(&a.command, &a.events);
         }
-        LeaveGame { player_id: _ } => {}
-        JoinGame { .. } => {
-            send_err(players, conn_id, "Already in room").await;
+        a.events
+    })
+}
+
+// 动作被规则拒绝时只告诉发起者, 否则把事件广播出去; 返回是否开始了新的回合
+async fn publish(
+    players: &[PlayerSlot],
+    spectators: &[Spectator],
+    conn_id: ConnId,
+    res: Result<Vec<GE>, RuleError>,
+) -> bool {
+    match res {
+        Ok(ev) => {
+            let new_turn = starts_turn(&ev);
+            broadcast_events(players, spectators, ev).await;
+            new_turn
         }
-        ChallengeWildDrawFour { .. } => {
-            send_err(players, conn_id, "Challenge not implemented").await
+        Err(e) => {
+            send_err(players, conn_id, e.into(), &e.to_string()).await;
+            false
         }
     }
-    sync_state(players, game).await;
-    if need_reset_after_sync {
-        log_ts!(
-            "game over -> reset pending new StartGame (players={})",
-            players.len()
-        );
-        *game = UnoGame::new(); // 清空牌局以便下一次 StartGame 重新 init
+}
+
+fn starts_turn(events: &[GE]) -> bool {
+    events.iter().any(|e| {
+        matches!(
+            e,
+            GE::PlayerTurn { .. } | GE::ChallengeWindow { .. } | GE::ColorChoiceRequired { .. }
+        )
+    })
+}
+
+// 当前等待谁行动: +4 质疑窗口中是被罚的玩家, 否则是当前玩家
+fn waiting_player(game: &UnoGame) -> Option<PlayerId> {
+    if !game.started {
+        return None;
+    }
+    Some(game.challenge_window().map_or(game.current_player(), |(challenger, _)| challenger))
+}
+
+
+// 轮到机器人时替它行动, 直到轮到真人或牌局结束
+// 轮到电脑时安排它在 BOT_THINK_TIME 后行动; 已经安排过的不推迟, 聊天等消息不会拖住电脑
+fn schedule_bot(uno_match: &Match, bots: &HashMap<PlayerId, Box<dyn Strategy>>, at: &mut Option<Instant>) {
+    let bot_waiting = waiting_player(uno_match.game()).is_some_and(|pid| bots.contains_key(&pid));
+    if !bot_waiting {
+        *at = None;
+    } else if at.is_none() {
+        *at = Some(Instant::now() + BOT_THINK_TIME);
+    }
+}
+
+// 电脑走一步, 每次只做一个动作, 房间任务在两步之间照常处理其它命令
+async fn bot_step(
+    uno_match: &mut Match,
+    replay: Option<&ReplayRecorder>,
+    players: &mut Vec<PlayerSlot>,
+    spectators: &[Spectator],
+    ctl: &mut HostControl,
+    bots: &mut HashMap<PlayerId, Box<dyn Strategy>>,
+) -> bool {
+    let game = uno_match.game();
+    let Some(pid) = waiting_player(game) else {
+        return false;
+    };
+    let challenged = game.challenge_window().map(|(_, challenged_id)| challenged_id);
+    let Some(strategy) = bots.get_mut(&pid) else {
+        return false;
+    };
+    let choosing_color = game.color_choice() == Some(pid);
+    let Some(conn_id) = players.iter().find(|p| p.pid == pid).map(|p| p.conn_id) else {
+        return false;
+    };
+    let view = BotView::from_game(game, pid);
+    let msg = match challenged {
+        None if choosing_color => Client2Server::ChooseColor {
+            player_id: pid,
+            color: strategy.choose_color(&view),
+        },
+        Some(challenged_id) if strategy.challenge_draw_four(&view) => {
+            Client2Server::ChallengeWildDrawFour {
+                challenger_id: pid,
+                challenged_id,
+            }
+        }
+        Some(_) => Client2Server::AcceptWildDrawFour { player_id: pid },
+        None => match strategy.decide(&view) {
+            BotAction::Play {
+                card_index,
+                color,
+                call_uno,
+            } => Client2Server::PlayCard {
+                player_id: pid,
+                card_index,
+                color: Some(color),
+                call_uno,
+                swap_with: None,
+            },
+            BotAction::Draw => Client2Server::DrawCard {
+                player_id: pid,
+                count: 1,
+            },
+            BotAction::Pass => Client2Server::PassTurn { player_id: pid },
+        },
+    };
+    let before = game.clone();
+    let mut new_turn = handle_game_msg(uno_match, replay, players, spectators, ctl, conn_id, msg).await;
+    // 策略给出的动作被规则拒绝时改用超时的保守操作, 免得一直卡在这个电脑上
+    if *uno_match.game() == before {
+        log_ts!("bot pid={} action rejected, falling back to auto play", pid);
+        new_turn |= auto_play(uno_match, replay, players, spectators, ctl, pid).await;
     }
+    new_turn
 }
 
-async fn broadcast_events(players: &Vec<PlayerSlot>, events: Vec<GE>) {
+// 回合超时, 替玩家做最保守的操作: 接受 +4, 选颜色, 否则摸一张牌再跳过
+async fn auto_play(
+    uno_match: &mut Match,
+    replay: Option<&ReplayRecorder>,
+    players: &mut Vec<PlayerSlot>,
+    spectators: &[Spectator],
+    ctl: &mut HostControl,
+    pid: PlayerId,
+) -> bool {
+    let Some(conn_id) = players.iter().find(|p| p.pid == pid).map(|p| p.conn_id) else {
+        return false;
+    };
+    let mut new_turn = false;
+    for _ in 0..3 {
+        let game = uno_match.game();
+        if waiting_player(game) != Some(pid) {
+            break;
+        }
+        let view = BotView::from_game(game, pid);
+        let msg = if game.challenge_window().is_some() {
+            Client2Server::AcceptWildDrawFour { player_id: pid }
+        } else if game.color_choice() == Some(pid) {
+            Client2Server::ChooseColor {
+                player_id: pid,
+                color: view.dominant_color(None),
+            }
+        } else if let Some(card_index) = game.drawn_card_playable() {
+            if game.rules().forced_play {
+                Client2Server::PlayCard {
+                    player_id: pid,
+                    card_index,
+                    color: Some(view.dominant_color(Some(card_index))),
+                    call_uno: view.hand.len() == 2,
+                    swap_with: None,
+                }
+            } else {
+                Client2Server::PassTurn { player_id: pid }
+            }
+        } else {
+            Client2Server::DrawCard {
+                player_id: pid,
+                count: 1,
+            }
+        };
+        new_turn |= handle_game_msg(uno_match, replay, players, spectators, ctl, conn_id, msg).await;
+    }
+    new_turn
+}
+
+// 轮到真人行动时开始倒计时, 新回合或换人时重新计时, 返回要通知所有人的 TurnDeadline
+fn refresh_deadline(
+    uno_match: &Match,
+    bots: &HashMap<PlayerId, Box<dyn Strategy>>,
+    deadline: &mut Option<(PlayerId, Instant)>,
+    new_turn: bool,
+) -> Option<Server2Client> {
+    let limit = uno_match.rules().turn_timeout_secs;
+    let waiting = waiting_player(uno_match.game()).filter(|pid| !bots.contains_key(pid));
+    let Some(pid) = waiting.filter(|_| limit > 0) else {
+        *deadline = None;
+        return None;
+    };
+    if !new_turn && deadline.is_some_and(|(p, _)| p == pid) {
+        return None;
+    }
+    let limit = Duration::from_secs(limit);
+    *deadline = Some((pid, Instant::now() + limit));
+    Some(Server2Client::TurnDeadline {
+        player_id: pid,
+        deadline_ms: limit.as_millis() as u64,
+    })
+}
+
+// 滑动窗口限流, 允许时记录本次发言
+fn chat_allowed(times: &mut VecDeque<Instant>) -> bool {
+    let now = Instant::now();
+    while times.front().is_some_and(|t| now.duration_since(*t) > CHAT_WINDOW) {
+        times.pop_front();
+    }
+    if times.len() >= CHAT_BURST {
+        return false;
+    }
+    times.push_back(now);
+    true
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+// 机器人不需要网络消息, 接收端直接丢弃
+fn bot_slot(pid: PlayerId, name: String, difficulty: BotDifficulty) -> PlayerSlot {
+    let (tx, _) = mpsc::channel(1);
+    let (detach, _) = mpsc::channel(1);
+    PlayerSlot {
+        conn_id: BOT_CONN_BASE + pid.index() as ConnId,
+        pid,
+        name,
+        session_id: String::new(),
+        tx,
+        detach,
+        disconnected_at: None,
+        bot: Some(difficulty),
+        account: false,
+    }
+}
+
+// 从快照恢复的真人, 重连前消息同样直接丢弃
+fn restored_slot(seat: SeatSnapshot) -> PlayerSlot {
+    let (tx, _) = mpsc::channel(1);
+    let (detach, _) = mpsc::channel(1);
+    PlayerSlot {
+        conn_id: RESTORED_CONN_BASE + seat.pid.index() as ConnId,
+        pid: seat.pid,
+        name: seat.name,
+        session_id: seat.session_id,
+        tx,
+        detach,
+        disconnected_at: Some(Instant::now()),
+        bot: None,
+        account: seat.account,
+    }
+}
+
+// 写失败只记日志, 不影响房间继续运行
+fn save_snapshot(
+    rooms: &Rooms,
+    room_id: &str,
+    settings: &RoomSettings,
+    uno_match: &Match,
+    players: &[PlayerSlot],
+    next_pid: usize,
+    ctl: &HostControl,
+) {
+    let Some(store) = &rooms.store else { return };
+    let snapshot = RoomSnapshot {
+        room_id: room_id.to_string(),
+        settings: settings.clone(),
+        uno_match: uno_match.clone(),
+        seats: players
+            .iter()
+            .map(|p| SeatSnapshot {
+                pid: p.pid,
+                name: p.name.clone(),
+                session_id: p.session_id.clone(),
+                bot: p.bot,
+                account: p.account,
+            })
+            .collect(),
+        next_pid,
+        host: ctl.host,
+        locked: ctl.locked,
+    };
+    if let Err(e) = store.save(&snapshot) {
+        room_log(room_id, &format!("snapshot failed: {}", e));
+    }
+}
+
+// 玩家彻底离开房间: 牌局进行中先从牌局里移除, 其他人的 pid 不受影响
+async fn vacate_seat(
+    uno_match: &mut Match,
+    replay: Option<&ReplayRecorder>,
+    players: &mut Vec<PlayerSlot>,
+    spectators: &[Spectator],
+    ctl: &mut HostControl,
+    pid: PlayerId,
+) -> bool {
+    let ev = if uno_match.game().started {
+        accepted(replay, uno_match.remove_player(pid)).unwrap_or_default()
+    } else {
+        vec![GE::PlayerLeft { player_id: pid }]
+    };
+    let new_turn = starts_turn(&ev);
+    broadcast_events(players, spectators, ev).await;
+    hand_over_host(players, spectators, ctl, pid).await;
+    players.retain(|p| p.pid != pid);
+    sync_state(players, spectators, uno_match.game()).await;
+    new_turn
+}
+
+// 账号入座后收回同一账号观战连接的上帝视角, 观战的房主也不再是房主
+async fn revoke_god_view(spectators: &mut [Spectator], ctl: &mut HostControl, account: &str) {
+    if ctl.watching_host.as_deref() == Some(account) {
+        ctl.watching_host = None;
+    }
+    for s in spectators.iter_mut().filter(|s| s.god_view && s.account && s.name == account) {
+        log_ts!("conn={} took a seat as {}, god view revoked", s.conn_id, account);
+        s.god_view = false;
+        let _ = s.tx.send(Server2Client::Spectating { god_view: false }).await;
+    }
+}
+
+// 房主离开后交给下一位在线的真人
+async fn hand_over_host(
+    players: &[PlayerSlot],
+    spectators: &[Spectator],
+    ctl: &mut HostControl,
+    leaving: PlayerId,
+) {
+    if ctl.host != Some(leaving) {
+        return;
+    }
+    ctl.host = players
+        .iter()
+        .find(|p| p.pid != leaving && p.bot.is_none() && p.disconnected_at.is_none())
+        .map(|p| p.pid);
+    log_ts!("host pid={} left, new host={:?}", leaving, ctl.host);
+    if let Some(host) = ctl.host {
+        broadcast_events(players, spectators, vec![GE::HostChanged { player_id: host }]).await;
+    }
+}
+
+fn gen_session_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect()
+}
+
+// 调试模式下房间可以用固定种子, 便于复现整场比赛
+// 加入或观战不存在的房间
+fn room_not_found(room_id: &str) -> Server2Client {
+    Server2Client::ServerError {
+        code: ErrorCode::RoomNotFound,
+        message: format!("Room {} does not exist", room_id),
+    }
+}
+
+// 设置了 UNO_REPLAY_DIR 时每一局都写一个回放文件, 用 replay 查看
+fn replay_recorder() -> Option<ReplayRecorder> {
+    let dir = std::env::var("UNO_REPLAY_DIR").ok()?;
+    ReplayRecorder::new(&dir)
+        .map_err(|e| log_ts!("cannot record replays to {}: {}", dir, e))
+        .ok()
+}
+
+async fn broadcast_events(players: &[PlayerSlot], spectators: &[Spectator], events: Vec<GE>) {
     if events.is_empty() {
         return;
     }
-    let msg = Server2Client::Events(events);
+    record_stats(players, &events);
+    // 每个玩家只能看到自己摸到的牌
     for p in players {
-        let _ = p.tx.send(msg.clone()).await;
+        let msg = Server2Client::Events(redact_events(&events, Some(p.pid)));
+        let _ = p.tx.send(msg).await;
+    }
+    // 观战者看不到任何人摸的牌, 上帝视角除外
+    for s in spectators {
+        let events = if s.god_view {
+            events.clone()
+        } else {
+            redact_events(&events, None)
+        };
+        let _ = s.tx.send(Server2Client::Events(events)).await;
+    }
+}
+// 登录了账号的玩家的战绩随牌局事件更新
+fn record_stats(players: &[PlayerSlot], events: &[GE]) {
+    let accounts: HashMap<PlayerId, String> =
+        players.iter().filter(|p| p.account).map(|p| (p.pid, p.name.clone())).collect();
+    if accounts.is_empty() {
+        return;
+    }
+    if let Err(e) = profiles().stats.lock().unwrap().record(events, &accounts) {
+        log_ts!("cannot save stats: {}", e);
+    }
+}
+async fn send_all(players: &[PlayerSlot], spectators: &[Spectator], msg: Server2Client) {
+    for tx in players.iter().map(|p| &p.tx).chain(spectators.iter().map(|s| &s.tx)) {
+        let _ = tx.send(msg.clone()).await;
     }
 }
-async fn sync_state(players: &Vec<PlayerSlot>, game: &UnoGame) {
+async fn sync_state(players: &[PlayerSlot], spectators: &[Spectator], game: &UnoGame) {
     let shared = Server2Client::SharedState {
         players_cards_count: game.get_players_cards_count(),
         top_card: game.top_card,
-        current_player: game.current_player,
+        current_player: game.current_player(),
         clockwise: game.direction,
+        rules: game.rules().clone(),
     };
     for p in players {
         let _ = p.tx.send(shared.clone()).await;
@@ -272,72 +1392,291 @@ async fn sync_state(players: &Vec<PlayerSlot>, game: &UnoGame) {
             })
             .await;
     }
+    // 观战者永远收不到 PlayerState, 上帝视角可以看到所有人的手牌
+    for s in spectators {
+        let _ = s.tx.send(shared.clone()).await;
+        if s.god_view {
+            for pid in game.seating() {
+                let _ =
+                    s.tx.send(Server2Client::PlayerState {
+                        player_id: pid,
+                        hand: game.get_player_hand(pid),
+                    })
+                    .await;
+            }
+        }
+    }
 }
-async fn send_err(players: &Vec<PlayerSlot>, conn_id: ConnId, msg: &str) {
+async fn send_err(players: &[PlayerSlot], conn_id: ConnId, code: ErrorCode, msg: &str) {
     if let Some(p) = players.iter().find(|p| p.conn_id == conn_id) {
         let _ =
             p.tx.send(Server2Client::ServerError {
+                code,
                 message: msg.to_string(),
             })
             .await;
     }
-    log_ts!("error conn={} msg={} ", conn_id, msg);
+    log_ts!("error conn={} code={:?} msg={} ", conn_id, code, msg);
 }
 
 // ===== 连接处理 =====
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let addr = "0.0.0.0:9000";
-    let listener = TcpListener::bind(addr).await?;
+    // 监听地址可用环境变量覆盖, 集成测试用随机端口启动
+    let addr = std::env::var("UNO_ADDR").unwrap_or_else(|_| "0.0.0.0:9000".into());
+    let listener = TcpListener::bind(&addr).await?;
     log_ts!("multi-room async UNO listening on {}", addr);
-    let rooms = Rooms::new();
-    let mut next_conn: ConnId = 0;
+    // WebSocket 网关只在设置了 UNO_WS_ADDR 时打开, 端口占用时只记日志, 不影响 TCP 玩家
+    let ws_listener = match std::env::var("UNO_WS_ADDR") {
+        Ok(ws_addr) => match TcpListener::bind(&ws_addr).await {
+            Ok(l) => {
+                log_ts!("websocket gateway listening on {}", ws_addr);
+                Some(l)
+            }
+            Err(e) => {
+                log_ts!("cannot bind websocket gateway on {}: {}", ws_addr, e);
+                None
+            }
+        },
+        Err(_) => None,
+    };
+    let rooms = Rooms::new(SnapshotStore::from_env());
+    let restored = rooms.restore().await;
+    if restored > 0 {
+        log_ts!("restored {} rooms from snapshots", restored);
+    }
+    // TCP 和 WebSocket 共用连接编号和房间表, 两边的玩家可以坐同一桌
+    let next_conn = Arc::new(AtomicU64::new(0));
+    if let Some(ws_listener) = ws_listener {
+        tokio::spawn(accept_loop(ws_listener, rooms.clone(), next_conn.clone(), true));
+    }
+    tokio::select! {
+        res = accept_loop(listener, rooms.clone(), next_conn, false) => res,
+        res = shutdown_signal() => {
+            log_ts!("shutting down, saving rooms");
+            rooms.shutdown().await;
+            tokio::task::spawn_blocking(|| profiles().stats.lock().unwrap().flush()).await?;
+            Ok(res?)
+        }
+    }
+}
+
+// Ctrl-C 或者 SIGTERM (systemd、docker stop) 都要先写完快照再退出
+#[cfg(unix)]
+async fn shutdown_signal() -> std::io::Result<()> {
+    let mut term = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    tokio::select! {
+        res = tokio::signal::ctrl_c() => res,
+        _ = term.recv() => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() -> std::io::Result<()> {
+    tokio::signal::ctrl_c().await
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    rooms: Rooms,
+    next_conn: Arc<AtomicU64>,
+    websocket: bool,
+) -> anyhow::Result<()> {
     loop {
         let (stream, peer) = listener.accept().await?;
-        next_conn += 1;
-        let conn_id = next_conn;
-        log_ts!("accept conn={} from {}", conn_id, peer);
+        let conn_id = next_conn.fetch_add(1, Ordering::Relaxed) + 1;
+        log_ts!("accept conn={} from {} websocket={}", conn_id, peer, websocket);
         let rooms_cl = rooms.clone();
         tokio::spawn(async move {
-            if let Err(e) = handle_connection(stream, rooms_cl, conn_id).await {
+            let res = if websocket {
+                handle_ws_connection(stream, rooms_cl, conn_id).await
+            } else {
+                handle_connection(stream, rooms_cl, conn_id).await
+            };
+            if let Err(e) = res {
                 eprintln!("conn {} error: {}", conn_id, e);
             }
         });
     }
 }
 
+// 客户端在 Hello 里没声明的功能不再推送
+fn client_accepts(caps: &watch::Receiver<Vec<Capability>>, msg: &Server2Client) -> bool {
+    !matches!(msg, Server2Client::ChatMessage { .. }) || caps.borrow().contains(&Capability::Chat)
+}
+
+// 连接的读端, TCP 和 WebSocket 各自实现
+trait Incoming {
+    // 读下一条消息, 连接断开返回 None; 必须可以被 select! 安全地取消
+    async fn next_frame(&mut self) -> Option<anyhow::Result<Frame<Client2Server>>>;
+    // 握手完成后切换到协商好的编码
+    fn switch_format(&mut self, format: WireFormat);
+}
+
+// 原始 TCP 连接: 默认每行一条 JSON, 握手后可以换成长度前缀的二进制帧
+struct TcpIncoming {
+    r: OwnedReadHalf,
+    buf: Vec<u8>,
+    format: WireFormat,
+}
+
+impl Incoming for TcpIncoming {
+    async fn next_frame(&mut self) -> Option<anyhow::Result<Frame<Client2Server>>> {
+        let mut chunk = [0u8; 4096];
+        loop {
+            match self.format.decode(&mut self.buf) {
+                Ok(Some(frame)) => return Some(Ok(frame)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e.into())),
+            }
+            match self.r.read(&mut chunk).await {
+                Ok(0) => return None,
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+
+    fn switch_format(&mut self, format: WireFormat) {
+        self.format = format;
+    }
+}
+
+// WebSocket 自带分帧: 文本帧是 JSON, 二进制帧是 MessagePack, 不需要切换
+struct WsIncoming<S> {
+    frames: S,
+}
+
+impl<S> Incoming for WsIncoming<S>
+where
+    S: Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    async fn next_frame(&mut self) -> Option<anyhow::Result<Frame<Client2Server>>> {
+        // ping/pong 直接忽略, 收到 Close 后流自然结束
+        loop {
+            match self.frames.next().await? {
+                Ok(Message::Text(text)) => return Some(Ok(JsonLines.decode_payload(text.as_bytes()))),
+                Ok(Message::Binary(data)) => return Some(Ok(MsgPack.decode_payload(&data))),
+                Ok(_) => {}
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+
+    fn switch_format(&mut self, _format: WireFormat) {}
+}
+
 async fn handle_connection(
     stream: tokio::net::TcpStream,
     rooms: Rooms,
     conn_id: ConnId,
 ) -> anyhow::Result<()> {
     let (r, w) = stream.into_split();
-    let mut lines = FramedRead::new(r, LinesCodec::new());
+    let incoming = TcpIncoming { r, buf: Vec::new(), format: WireFormat::Json };
     let (tx_client, mut rx_client) = mpsc::channel::<Server2Client>(256);
+    // 客户端在 Hello 里声明支持的功能, 没发 Hello 的老客户端当作全都支持
+    let (caps_tx, caps_rx) = watch::channel(Capability::ALL.to_vec());
     // 写任务
     tokio::spawn(async move {
         let mut writer = tokio::io::BufWriter::new(w);
+        let mut format = WireFormat::Json;
+        let mut buf = Vec::new();
         while let Some(msg) = rx_client.recv().await {
-            if let Ok(line) = serde_json::to_string(&msg) {
-                if writer.write_all(line.as_bytes()).await.is_err() {
-                    break;
-                }
-                if writer.write_all(b"\n").await.is_err() {
-                    break;
-                }
-                let _ = writer.flush().await;
+            if !client_accepts(&caps_rx, &msg) {
+                continue;
+            }
+            buf.clear();
+            if format.encode(&msg, &mut buf).is_err() {
+                continue;
+            }
+            if writer.write_all(&buf).await.is_err() {
+                break;
+            }
+            let _ = writer.flush().await;
+            // HelloAck 本身还是 JSON, 之后的消息换成协商好的编码
+            if let Server2Client::HelloAck { format: f, .. } = msg {
+                format = f;
+            }
+        }
+    });
+    serve_client(incoming, tx_client, caps_tx, rooms, conn_id).await
+}
+
+async fn handle_ws_connection(
+    stream: tokio::net::TcpStream,
+    rooms: Rooms,
+    conn_id: ConnId,
+) -> anyhow::Result<()> {
+    let ws = tokio_tungstenite::accept_async(stream).await?;
+    let (mut sink, frames) = ws.split();
+    let (tx_client, mut rx_client) = mpsc::channel::<Server2Client>(256);
+    let (caps_tx, caps_rx) = watch::channel(Capability::ALL.to_vec());
+    tokio::spawn(async move {
+        let mut format = WireFormat::Json;
+        while let Some(msg) = rx_client.recv().await {
+            if !client_accepts(&caps_rx, &msg) {
+                continue;
+            }
+            let Ok(payload) = format.encode_payload(&msg) else {
+                continue;
+            };
+            let frame = match format {
+                WireFormat::MsgPack => Message::binary(payload),
+                _ => Message::text(String::from_utf8(payload).unwrap_or_default()),
+            };
+            if sink.send(frame).await.is_err() {
+                break;
+            }
+            if let Server2Client::HelloAck { format: f, .. } = msg {
+                format = f;
             }
         }
+        let _ = sink.close().await;
     });
+    serve_client(WsIncoming { frames }, tx_client, caps_tx, rooms, conn_id).await
+}
+
+// 连接的读循环, 与传输方式无关: 处理握手和大厅消息, 入座后把其余消息转给房间
+async fn serve_client(
+    mut incoming: impl Incoming,
+    tx_client: mpsc::Sender<Server2Client>,
+    caps_tx: watch::Sender<Vec<Capability>>,
+    rooms: Rooms,
+    conn_id: ConnId,
+) -> anyhow::Result<()> {
     let mut room_tx: Option<mpsc::Sender<RoomCmd>> = None;
-    while let Some(line) = lines.next().await {
-        let line = line?;
-        let parsed: Result<Client2Server, _> = serde_json::from_str(&line);
-        let msg = match parsed {
-            Ok(m) => m,
-            Err(e) => {
+    let mut account: Option<String> = None; // 登录的账号名
+    // 被房主踢出后回到大厅, 可以重新加入别的房间
+    let (detach, mut detached) = mpsc::channel::<()>(1);
+    loop {
+        let frame = tokio::select! {
+            frame = incoming.next_frame() => frame,
+            Some(()) = detached.recv() => {
+                log_ts!("conn={} detached from room", conn_id);
+                room_tx = None;
+                continue;
+            }
+        };
+        // 读出错也要走到下面的 Leave, 让房间知道这个连接断了
+        let frame = match frame {
+            Some(Ok(frame)) => frame,
+            Some(Err(e)) => {
+                log_ts!("conn={} read error={}", conn_id, e);
+                break;
+            }
+            None => break,
+        };
+        let msg = match frame {
+            Frame::Msg(m) => m,
+            // 新版本客户端的消息, 跳过而不是报错
+            Frame::Unknown => {
+                log_ts!("conn={} skip unknown message", conn_id);
+                continue;
+            }
+            Frame::Invalid(e) => {
                 let _ = tx_client
                     .send(Server2Client::ServerError {
+                        code: ErrorCode::BadJson,
                         message: format!("bad json: {}", e),
                     })
                     .await;
@@ -346,34 +1685,271 @@ async fn handle_connection(
             }
         };
         match (&room_tx, &msg) {
-            (None, Client2Server::JoinGame { room_id, name }) => {
+            (_, Client2Server::ListRooms) => {
+                // 同时询问所有房间, 没及时回复的房间这次不列出
+                let queries = rooms.list().await.into_iter().map(|handle| async move {
+                    let query = async {
+                        let (reply, info) = oneshot::channel();
+                        handle.tx.send(RoomCmd::Info { reply }).await.ok()?;
+                        info.await.ok()
+                    };
+                    tokio::time::timeout(ROOM_INFO_TIMEOUT, query).await.ok().flatten()
+                });
+                let mut infos: Vec<RoomInfo> = futures::future::join_all(queries).await.into_iter().flatten().collect();
+                infos.sort_by(|a, b| a.room_id.cmp(&b.room_id));
+                let _ = tx_client.send(Server2Client::RoomList { rooms: infos }).await;
+            }
+            (_, Client2Server::GetStats { name }) => {
+                let name = name.trim().to_string();
+                let registered = profiles().accounts.lock().unwrap().is_registered(&name);
+                // 注册了但还没打完过一局的是全零战绩
+                let stats = registered.then(|| profiles().stats.lock().unwrap().get(&name).cloned().unwrap_or_default());
+                let _ = tx_client.send(Server2Client::Stats { name, stats }).await;
+            }
+            (None, Client2Server::Login { name, password, register }) => {
+                log_ts!("conn={} login request name={} register={}", conn_id, name, register);
+                let (name, password, register) = (name.clone(), password.clone(), *register);
+                let res = tokio::task::spawn_blocking(move || login(&name, &password, register)).await?;
+                match res {
+                    Ok(name) => {
+                        log_ts!("conn={} logged in as {}", conn_id, name);
+                        account = Some(name.clone());
+                        let _ = tx_client.send(Server2Client::LoggedIn { name }).await;
+                    }
+                    Err(e) => {
+                        log_ts!("conn={} login failed: {}", conn_id, e);
+                        let code = match e {
+                            AccountError::NameTaken => ErrorCode::NameTaken,
+                            AccountError::WrongPassword => ErrorCode::LoginFailed,
+                            AccountError::InvalidName | AccountError::WeakPassword | AccountError::Storage(_) => {
+                                ErrorCode::InvalidAccount
+                            }
+                        };
+                        let _ = tx_client.send(Server2Client::ServerError { code, message: e.to_string() }).await;
+                    }
+                }
+            }
+            (None, Client2Server::Hello { protocol_version, client_name, capabilities, formats }) => {
+                log_ts!(
+                    "conn={} hello client={} version={} caps={:?}",
+                    conn_id,
+                    client_name,
+                    protocol_version,
+                    capabilities
+                );
+                if *protocol_version < MIN_PROTOCOL_VERSION {
+                    let _ = tx_client
+                        .send(Server2Client::ServerError {
+                            code: ErrorCode::UnsupportedVersion,
+                            message: format!(
+                                "Protocol version {} is not supported, need at least {}",
+                                protocol_version, MIN_PROTOCOL_VERSION
+                            ),
+                        })
+                        .await;
+                    continue;
+                }
+                let _ = caps_tx.send(capabilities.clone());
+                // 取客户端最想用、服务器也认识的编码
+                let format = formats
+                    .iter()
+                    .copied()
+                    .find(|f| *f != WireFormat::Unknown)
+                    .unwrap_or_default();
+                let _ = tx_client
+                    .send(Server2Client::HelloAck {
+                        protocol_version: PROTOCOL_VERSION,
+                        server_name: format!("uno async_server {}", env!("CARGO_PKG_VERSION")),
+                        capabilities: Capability::ALL.to_vec(),
+                        format,
+                    })
+                    .await;
+                incoming.switch_format(format);
+            }
+            (None, Client2Server::CreateRoom { room_id, name, max_players, password, rules }) => {
+                log_ts!(
+                    "conn={} create request room={} name={} max_players={}",
+                    conn_id,
+                    room_id,
+                    name,
+                    max_players
+                );
+                let name = match seat_name(&account, name) {
+                    Ok(name) => name,
+                    Err(e) => {
+                        let _ = tx_client.send(e).await;
+                        continue;
+                    }
+                };
+                if !(2..=MAX_PLAYERS).contains(max_players) {
+                    let _ = tx_client
+                        .send(Server2Client::ServerError {
+                            code: ErrorCode::InvalidRoomConfig,
+                            message: format!("max_players must be between 2 and {}", MAX_PLAYERS),
+                        })
+                        .await;
+                    continue;
+                }
+                let rules = rules.clone().unwrap_or_default();
+                if let Err(message) = rules.check(*max_players) {
+                    let _ = tx_client
+                        .send(Server2Client::ServerError { code: ErrorCode::InvalidRoomConfig, message })
+                        .await;
+                    continue;
+                }
+                // 空密码视为不设密码
+                let password = password.clone().filter(|p| !p.is_empty());
+                let settings = RoomSettings {
+                    rules,
+                    max_players: *max_players,
+                    password: password.as_deref().map(RoomPassword::new),
+                };
+                let Some(handle) = rooms.create(room_id, settings).await else {
+                    let _ = tx_client
+                        .send(Server2Client::ServerError {
+                            code: ErrorCode::RoomExists,
+                            message: "Room already exists".into(),
+                        })
+                        .await;
+                    continue;
+                };
+                let (reply, joined) = oneshot::channel();
+                let _ = handle
+                    .tx
+                    .send(RoomCmd::Join {
+                        conn_id,
+                        name,
+                        account: account.is_some(),
+                        password,
+                        tx_client: tx_client.clone(),
+                        detach: detach.clone(),
+                        reply,
+                    })
+                    .await;
+                if joined.await.unwrap_or(false) {
+                    room_tx = Some(handle.tx);
+                }
+            }
+            (None, Client2Server::JoinGame { room_id, name, password, .. }) => {
                 log_ts!(
                     "conn={} join request room={} name={} ",
                     conn_id,
                     room_id,
                     name
                 );
-                let handle = rooms.get_or_create(room_id).await;
-                room_tx = Some(handle.tx.clone());
+                let name = match seat_name(&account, name) {
+                    Ok(name) => name,
+                    Err(e) => {
+                        let _ = tx_client.send(e).await;
+                        continue;
+                    }
+                };
+                // 房间只能由 CreateRoom 创建, 房规和种子在这里不生效
+                let Some(handle) = rooms.get(room_id).await else {
+                    let _ = tx_client.send(room_not_found(room_id)).await;
+                    continue;
+                };
+                let (reply, joined) = oneshot::channel();
                 let _ = handle
                     .tx
                     .send(RoomCmd::Join {
                         conn_id,
-                        name: name.clone(),
+                        name,
+                        account: account.is_some(),
+                        password: password.clone(),
+                        tx_client: tx_client.clone(),
+                        detach: detach.clone(),
+                        reply,
+                    })
+                    .await;
+                if joined.await.unwrap_or(false) {
+                    room_tx = Some(handle.tx);
+                }
+            }
+            (None, Client2Server::Spectate { room_id, name, god_view, referee_key, password }) => {
+                log_ts!(
+                    "conn={} spectate request room={} name={} god_view={}",
+                    conn_id,
+                    room_id,
+                    name,
+                    god_view
+                );
+                // 裁判口令由环境变量配置, 未配置时只有房主能开上帝视角
+                let referee = std::env::var("UNO_REFEREE_KEY")
+                    .ok()
+                    .is_some_and(|key| !key.is_empty() && referee_key.as_deref() == Some(key.as_str()));
+                let name = match seat_name(&account, name) {
+                    Ok(name) => name,
+                    Err(e) => {
+                        let _ = tx_client.send(e).await;
+                        continue;
+                    }
+                };
+                let Some(handle) = rooms.get(room_id).await else {
+                    let _ = tx_client.send(room_not_found(room_id)).await;
+                    continue;
+                };
+                let (reply, joined) = oneshot::channel();
+                let _ = handle
+                    .tx
+                    .send(RoomCmd::Spectate {
+                        conn_id,
+                        name,
+                        account: account.is_some(),
+                        god_view: *god_view,
+                        referee,
+                        password: password.clone(),
                         tx_client: tx_client.clone(),
+                        reply,
                     })
                     .await;
+                if joined.await.unwrap_or(false) {
+                    room_tx = Some(handle.tx);
+                }
+            }
+            (None, Client2Server::Resume { session_id }) => {
+                log_ts!("conn={} resume request", conn_id);
+                let resumed = match rooms.find_session(session_id).await {
+                    Some(handle) => {
+                        let (reply, resumed) = oneshot::channel();
+                        let _ = handle
+                            .tx
+                            .send(RoomCmd::Resume {
+                                conn_id,
+                                session_id: session_id.clone(),
+                                tx_client: tx_client.clone(),
+                                detach: detach.clone(),
+                                reply,
+                            })
+                            .await;
+                        resumed.await.unwrap_or(false).then_some(handle)
+                    }
+                    None => None,
+                };
+                match resumed {
+                    Some(handle) => room_tx = Some(handle.tx),
+                    None => {
+                        let _ = tx_client
+                            .send(Server2Client::ServerError {
+                                code: ErrorCode::SessionExpired,
+                                message: "Session expired or unknown".into(),
+                            })
+                            .await;
+                    }
+                }
             }
             (None, _) => {
                 let _ = tx_client
                     .send(Server2Client::ServerError {
-                        message: "First message must be JoinGame {room_id,name}".into(),
+                        code: ErrorCode::NotJoined,
+                        message: "First message must be JoinGame {room_id,name}, CreateRoom {room_id,name}, Spectate {room_id,name} or Resume {session_id}".into(),
                     })
                     .await;
             }
-            (Some(_), Client2Server::JoinGame { .. }) => {
+            (Some(_), Client2Server::JoinGame { .. } | Client2Server::CreateRoom { .. } | Client2Server::Spectate { .. } | Client2Server::Resume { .. } | Client2Server::Hello { .. } | Client2Server::Login { .. }) => {
                 let _ = tx_client
                     .send(Server2Client::ServerError {
+                        code: ErrorCode::AlreadyJoined,
                         message: "Already joined".into(),
                     })
                     .await;
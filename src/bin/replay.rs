@@ -0,0 +1,254 @@
+use std::io;
+use std::time::Duration;
+
+use crossterm::{
+    event::{self, Event as CEvent, KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color as TColor, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Paragraph},
+    Terminal,
+};
+
+use uno::game::cards::{Color as UColor, UnoCard};
+use uno::game::UnoGame;
+use uno::ports::replay::{Command, Replay};
+
+// ---------------- 状态定义 ----------------
+struct ViewState {
+    replay: Replay,
+    step: usize, // 已经执行了前 step 个操作
+    game: Option<UnoGame>,
+    error: Option<String>, // 操作重放失败, 文件和当前版本的规则不一致
+}
+
+impl ViewState {
+    fn goto(&mut self, step: usize) {
+        self.step = step.min(self.replay.steps.len());
+        match self.replay.game_at(self.step) {
+            Ok(game) => {
+                self.game = Some(game);
+                self.error = None;
+            }
+            Err(e) => {
+                self.game = None;
+                self.error = Some(format!("第 {} 步无法重放: {}", self.step, e));
+            }
+        }
+    }
+}
+
+// ---------------- 工具函数 ----------------
+fn map_color(c: UColor) -> TColor {
+    match c {
+        UColor::RED => TColor::Red,
+        UColor::GREEN => TColor::Green,
+        UColor::BLUE => TColor::Blue,
+        UColor::YELLOW => TColor::Yellow,
+    }
+}
+fn card_line(card: &UnoCard) -> Line<'static> {
+    let fg = match card {
+        UnoCard::NumberCard(c, _) | UnoCard::ActionCard(c, _) => map_color(*c),
+        UnoCard::WildCard(Some(c), _) => map_color(*c),
+        UnoCard::WildCard(None, _) => TColor::White,
+    };
+    Line::from(vec![
+        Span::raw("  "),
+        Span::styled(card.to_string(), Style::default().fg(fg).add_modifier(Modifier::BOLD)),
+    ])
+}
+fn command_text(c: &Command) -> String {
+    match c {
+        Command::PlayCard { player_id, card_index, call_uno, color, swap_with } => {
+            let mut s = format!("Player {} 出第 {} 张牌", player_id, card_index);
+            if let Some(color) = color {
+                s.push_str(&format!(" 选 {:?}", color));
+            }
+            if let Some(other) = swap_with {
+                s.push_str(&format!(" 与 Player {} 换牌", other));
+            }
+            if *call_uno {
+                s.push_str(" UNO!");
+            }
+            s
+        }
+        Command::DrawCard { player_id } => format!("Player {} 摸牌", player_id),
+        Command::PassTurn { player_id } => format!("Player {} 跳过", player_id),
+        Command::ChooseColor { player_id, color } => format!("Player {} 选色 {:?}", player_id, color),
+        Command::Challenge { challenger_id, challenged_id } => {
+            format!("Player {} 质疑 Player {} 的 +4", challenger_id, challenged_id)
+        }
+        Command::AcceptDrawFour { player_id } => format!("Player {} 接受 +4", player_id),
+        Command::Leave { player_id } => format!("Player {} 离开", player_id),
+    }
+}
+
+// ---------------- 主入口 ----------------
+fn main() -> io::Result<()> {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: replay <file.jsonl>");
+        std::process::exit(2);
+    };
+    let replay = Replay::load(&path)?;
+    let mut view = ViewState {
+        replay,
+        step: 0,
+        game: None,
+        error: None,
+    };
+    view.goto(0);
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+    loop {
+        terminal.draw(|f| ui(f, &view))?;
+        if !event::poll(Duration::from_millis(200))? {
+            continue;
+        }
+        let CEvent::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let last = view.replay.steps.len();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(' ') => view.goto(view.step + 1),
+            KeyCode::Left | KeyCode::Char('h') => view.goto(view.step.saturating_sub(1)),
+            KeyCode::PageDown => view.goto(view.step + 10),
+            KeyCode::PageUp => view.goto(view.step.saturating_sub(10)),
+            KeyCode::Home => view.goto(0),
+            KeyCode::End => view.goto(last),
+            _ => {}
+        }
+    }
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    Ok(())
+}
+
+// ---------------- 主 UI 绘制 ----------------
+fn ui(f: &mut ratatui::Frame<'_>, view: &ViewState) {
+    let v = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(8),
+        ])
+        .split(f.size());
+    draw_status(f, v[0], view);
+    draw_main(f, v[1], view);
+    draw_log(f, v[2], view);
+}
+
+fn draw_status(f: &mut ratatui::Frame<'_>, area: Rect, view: &ViewState) {
+    let header = &view.replay.header;
+    let (current, clockwise) = view
+        .game
+        .as_ref()
+        .map_or((String::from("-"), true), |g| (g.current_player().to_string(), g.direction));
+    let title = format!(
+        "UNO 回放 | 第 {} 局 | 种子:{} | 步骤:{}/{} | 当前:{} | 方向:{}",
+        header.round,
+        header.seed,
+        view.step,
+        view.replay.steps.len(),
+        current,
+        if clockwise { "顺时针" } else { "逆时针" },
+    );
+    let para = Paragraph::new(title).block(Block::default().borders(Borders::ALL).title("状态"));
+    f.render_widget(para, area);
+}
+
+fn draw_main(f: &mut ratatui::Frame<'_>, area: Rect, view: &ViewState) {
+    let cols = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(33),
+            Constraint::Percentage(34),
+            Constraint::Percentage(33),
+        ])
+        .split(area);
+    let Some(game) = &view.game else {
+        let msg = view.error.clone().unwrap_or_default();
+        let para = Paragraph::new(msg).block(Block::default().borders(Borders::ALL).title("错误"));
+        f.render_widget(para, area);
+        return;
+    };
+    // 左：玩家
+    let players_text: Vec<Line> = game
+        .get_players_cards_count()
+        .into_iter()
+        .map(|(pid, name, n)| {
+            let turn = if pid == game.current_player() { " ←" } else { "" };
+            let left = if game.has_left(pid) { " (离开)" } else { "" };
+            Line::from(format!("{}: {:>2}{}{}", name, n, left, turn))
+        })
+        .collect();
+    let players = Paragraph::new(Text::from(players_text))
+        .block(Block::default().borders(Borders::ALL).title("玩家"));
+    f.render_widget(players, cols[0]);
+    // 中：桌面和这一步的操作
+    let mut lines = vec![Line::from("顶部牌:")];
+    match &game.top_card {
+        None => lines.push(Line::from("无")),
+        Some(c) => lines.push(card_line(c)),
+    };
+    lines.push(Line::from(format!("牌堆剩余: {}", game.deck().len())));
+    lines.push(Line::from(""));
+    match view.step.checked_sub(1).and_then(|i| view.replay.steps.get(i)) {
+        Some(s) => lines.push(Line::from(command_text(&s.command))),
+        None => lines.push(Line::from("发牌")),
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from("←/→ 单步  PgUp/PgDn 10 步"));
+    lines.push(Line::from("Home/End 开头/结尾  Q 退出"));
+    let desk = Paragraph::new(Text::from(lines))
+        .block(Block::default().borders(Borders::ALL).title("桌面"));
+    f.render_widget(desk, cols[1]);
+    // 右：所有人的手牌
+    let mut lines: Vec<Line> = Vec::new();
+    for (pid, name, n) in game.get_players_cards_count() {
+        lines.push(Line::from(format!("{} ({})", name, n)));
+        lines.extend(game.get_player_hand(pid).iter().map(card_line));
+    }
+    let hands = Paragraph::new(Text::from(lines))
+        .block(Block::default().borders(Borders::ALL).title("所有手牌"));
+    f.render_widget(hands, cols[2]);
+}
+
+fn draw_log(f: &mut ratatui::Frame<'_>, area: Rect, view: &ViewState) {
+    let cols = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+    // 左: 这一步录下的事件, 放不下时只显示最后几条(发牌那一步有几十条)
+    let rows = area.height.saturating_sub(2) as usize;
+    let events = view.replay.events_at(view.step);
+    let lines: Vec<Line> = events[events.len().saturating_sub(rows)..]
+        .iter()
+        .map(|e| Line::from(e.to_string()))
+        .collect();
+    let para = Paragraph::new(Text::from(lines))
+        .block(Block::default().borders(Borders::ALL).title("本步事件"));
+    f.render_widget(para, cols[0]);
+    // 右: 最近的操作, 最后一行是当前这一步
+    let lines: Vec<Line> = view.replay.steps[..view.step]
+        .iter()
+        .enumerate()
+        .skip(view.step.saturating_sub(rows))
+        .map(|(i, s)| Line::from(format!("{:>3}. {}", i + 1, command_text(&s.command))))
+        .collect();
+    let para = Paragraph::new(Text::from(lines))
+        .block(Block::default().borders(Borders::ALL).title("操作记录"));
+    f.render_widget(para, cols[1]);
+}
@@ -1,22 +1,39 @@
 use rand::{distributions::Alphanumeric, Rng};
+use std::collections::{HashMap, HashSet};
 use std::io::{BufRead, BufReader, Write};
 use std::net::{TcpListener, TcpStream};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
-use uno::game::events::GameEvent as GE;
-use uno::game::UnoGame;
+use uno::game::events::{redact_events, GameEvent as GE};
+use uno::game::{Accepted, Match, PlayerId, RuleError, RuleSet};
 use uno::ports::bus::{ConsolerLogger, EventBus, EventHandler};
-use uno::protocol::{Client2Server, Server2Client};
+use uno::ports::replay::{ReplayHeader, ReplayRecorder};
+use uno::protocol::client2server::MAX_PLAYERS;
+use uno::protocol::handshake::MIN_PROTOCOL_VERSION;
+use uno::protocol::{decode, Capability, Client2Server, ErrorCode, Server2Client, WireFormat, PROTOCOL_VERSION};
 
 type ClientTx = mpsc::Sender<Server2Client>;
 type ClientRx = mpsc::Receiver<Server2Client>;
+type ConnId = u64;
+
+// 断线后保留座位的时长, 期间可凭 session_id 重连
+const RECONNECT_GRACE: Duration = Duration::from_secs(60);
 
 struct SharedState {
-    game: UnoGame,
-    players: Vec<String>,
+    uno_match: Match, // 房规和调试种子由第一个加入的玩家指定
+    players: Vec<(PlayerId, String)>, // 按加入顺序
+    next_id: usize, // 下一个加入的玩家的 id, 离开的玩家的 id 不会复用
+    left: HashSet<PlayerId>, // 牌局中离开的玩家, 下次开局前才从名单移除
+    // session_id -> player_id, 玩家离开或断线超过宽限期后失效
+    sessions: HashMap<String, PlayerId>,
+    disconnected: HashMap<PlayerId, Instant>, // 断线等待重连的玩家
     // game_id: String,
-    clients: Vec<(ClientTx, Option<usize>)>, // 广播通道
+    // 广播通道, 按连接 id 索引; 删除断开的连接不影响其他连接
+    clients: HashMap<ConnId, (ClientTx, Option<PlayerId>)>,
+    next_conn: ConnId,
+    replay: Option<ReplayRecorder>, // 设置了 UNO_REPLAY_DIR 时录制每一局
 }
 
 // 网络广播处理器：把每个 GameEvent 发送给所有客户端
@@ -25,29 +42,31 @@ struct BroadcastHandler {
 }
 impl EventHandler for BroadcastHandler {
     /// Handle a batch of game events and broadcast them to all clients.
+    /// Each client only sees its own drawn cards.
     fn handle_events(&self, events: &[GE]) {
-        let msg = Server2Client::Events(events.to_vec());
         let mut dead = Vec::new();
         let mut st = self.state.lock().unwrap();
-        for (i, (tx, _pid)) in st.clients.iter().enumerate() {
-            if tx.send(msg.clone()).is_err() {
-                dead.push(i);
+        for (conn_id, (tx, pid)) in st.clients.iter() {
+            let msg = Server2Client::Events(redact_events(events, *pid));
+            if tx.send(msg).is_err() {
+                dead.push(*conn_id);
             }
         }
-        for i in dead.into_iter().rev() {
-            st.clients.remove(i);
+        for conn_id in dead {
+            drop_client(&mut st, conn_id);
         }
         let shared_state = Server2Client::SharedState {
-            players_cards_count: st.game.get_players_cards_count(),
-            top_card: st.game.top_card,
-            current_player: st.game.current_player,
-            clockwise: st.game.direction,
+            players_cards_count: st.uno_match.game().get_players_cards_count(),
+            top_card: st.uno_match.game().top_card,
+            current_player: st.uno_match.game().current_player(),
+            clockwise: st.uno_match.game().direction,
+            rules: st.uno_match.rules().clone(),
         };
-        for (cl, pid_opt) in st.clients.iter() {
+        for (cl, pid_opt) in st.clients.values() {
             if let Some(pid) = pid_opt {
                 let _ = cl.send(Server2Client::PlayerState {
                     player_id: *pid,
-                    hand: st.game.get_player_hand(*pid),
+                    hand: st.uno_match.game().get_player_hand(*pid),
                 });
             }
             let _ = cl.send(shared_state.clone());
@@ -56,16 +75,23 @@ impl EventHandler for BroadcastHandler {
 }
 
 fn main() {
-    let addr = "127.0.0.1:9000";
-    let listener = TcpListener::bind(addr).expect("bind failed");
+    // 监听地址可用环境变量覆盖, 集成测试用随机端口启动
+    let addr = std::env::var("UNO_ADDR").unwrap_or_else(|_| "127.0.0.1:9000".into());
+    let listener = TcpListener::bind(&addr).expect("bind failed");
     println!("UNO server listening on {}", addr);
 
     // 共享状态
     let state = Arc::new(Mutex::new(SharedState {
-        game: UnoGame::new(),
+        uno_match: Match::new(RuleSet::default()),
         players: Vec::new(),
+        next_id: 0,
+        left: HashSet::new(),
+        sessions: HashMap::new(),
+        disconnected: HashMap::new(),
         // game_id: gen_id(10),
-        clients: Vec::new(),
+        clients: HashMap::new(),
+        next_conn: 0,
+        replay: replay_recorder(),
     }));
 
     // 事件总线：注册网络广播处理器
@@ -77,23 +103,36 @@ fn main() {
     bus.register_handler(Box::new(ConsolerLogger {}));
     let bus = Arc::new(bus); // 只读共享，后续不再注册新处理器
 
+    // 断线超过宽限期的玩家按离开处理, 免得牌局卡在他的回合
+    let grace = std::env::var("UNO_RECONNECT_GRACE_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .map_or(RECONNECT_GRACE, Duration::from_secs);
+    {
+        let state = state.clone();
+        let bus = bus.clone();
+        thread::spawn(move || expire_loop(state, bus, grace));
+    }
+
     for stream in listener.incoming() {
         match stream {
             Ok(stream) => {
                 let state_clone = state.clone();
                 let bus_clone = bus.clone();
                 let (tx, rx): (ClientTx, ClientRx) = mpsc::channel();
-                // 插入到clients并记录本连接索引
-                let conn_index = {
+                // 插入到clients并记录本连接 id
+                let conn_id = {
                     let mut st = state_clone.lock().unwrap();
-                    st.clients.push((tx.clone(), None));
-                    st.clients.len() - 1
+                    let conn_id = st.next_conn;
+                    st.next_conn += 1;
+                    st.clients.insert(conn_id, (tx.clone(), None));
+                    conn_id
                 };
                 // 写线程
                 let mut write_stream = stream.try_clone().expect("clone stream failed");
                 thread::spawn(move || writer_loop(&mut write_stream, rx));
-                // 读线程（带 conn_index）
-                thread::spawn(move || reader_loop(stream, state_clone, bus_clone, tx, conn_index));
+                // 读线程（带 conn_id）
+                thread::spawn(move || reader_loop(stream, state_clone, bus_clone, tx, conn_id));
             }
             Err(e) => eprintln!("accept error: {}", e),
         }
@@ -117,7 +156,7 @@ fn reader_loop(
     state: Arc<Mutex<SharedState>>,
     bus: Arc<EventBus>,
     my_tx: ClientTx,
-    conn_index: usize,
+    conn_id: ConnId,
 ) {
     let peer = stream.peer_addr().ok();
     let reader = BufReader::new(stream);
@@ -129,16 +168,56 @@ fn reader_loop(
                 break;
             }
         };
-        let msg: Result<Client2Server, _> = serde_json::from_str(&line);
-        match msg {
-            Ok(m) => handle_message(&state, &bus, &my_tx, m, conn_index),
+        match decode::<Client2Server>(&line) {
+            Ok(Some(m)) => handle_message(&state, &bus, &my_tx, m, conn_id),
+            // 新版本客户端的消息, 跳过而不是报错
+            Ok(None) => eprintln!("skip unknown message from {:?}: {}", peer, line),
             Err(e) => {
                 let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::BadJson,
                     message: format!("bad json: {}", e),
                 });
             }
         }
     }
+    // 连接断开, 玩家的座位和会话保留到宽限期结束, 等待 Resume
+    drop_client(&mut state.lock().unwrap(), conn_id);
+}
+
+// 移除断开的连接, 绑定的玩家开始计算重连宽限期
+fn drop_client(st: &mut SharedState, conn_id: ConnId) {
+    if let Some((_tx, Some(player_id))) = st.clients.remove(&conn_id) {
+        st.disconnected.insert(player_id, Instant::now());
+    }
+}
+
+fn expire_loop(state: Arc<Mutex<SharedState>>, bus: Arc<EventBus>, grace: Duration) {
+    loop {
+        thread::sleep(Duration::from_secs(1));
+        let batches: Vec<Vec<GE>> = {
+            let mut st = state.lock().unwrap();
+            let expired: Vec<PlayerId> = st
+                .disconnected
+                .iter()
+                .filter(|(_, at)| at.elapsed() > grace)
+                .map(|(pid, _)| *pid)
+                .collect();
+            expired
+                .into_iter()
+                .filter_map(|pid| {
+                    st.disconnected.remove(&pid);
+                    let seated = st.players.iter().any(|(id, _)| *id == pid) && !st.left.contains(&pid);
+                    seated.then(|| {
+                        eprintln!("reconnect grace expired pid={}", pid);
+                        vacate_seat(&mut st, pid)
+                    })
+                })
+                .collect()
+        };
+        for ev in batches {
+            bus.publish(ev);
+        }
+    }
 }
 
 fn handle_message(
@@ -146,15 +225,35 @@ fn handle_message(
     bus: &Arc<EventBus>,
     my_tx: &ClientTx,
     msg: Client2Server,
-    conn_index: usize,
+    conn_id: ConnId,
 ) {
     match msg {
-        Client2Server::JoinGame { room_id: _room_id,name } => {
+        Client2Server::Hello { protocol_version, .. } => {
+            if protocol_version < MIN_PROTOCOL_VERSION {
+                let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::UnsupportedVersion,
+                    message: format!(
+                        "Protocol version {} is not supported, need at least {}",
+                        protocol_version, MIN_PROTOCOL_VERSION
+                    ),
+                });
+                return;
+            }
+            // 单房间服务器没有大厅/聊天/机器人/房主/观战
+            let _ = my_tx.send(Server2Client::HelloAck {
+                protocol_version: PROTOCOL_VERSION,
+                server_name: format!("uno server {}", env!("CARGO_PKG_VERSION")),
+                capabilities: vec![Capability::Challenge, Capability::Reconnect],
+                format: WireFormat::Json, // 单房间服务器只说 JSON Lines
+            });
+        }
+        Client2Server::JoinGame { room_id: _room_id, name, seed, rules, .. } => {
             {
                 // 已开始校验
                 let st = state.lock().unwrap();
-                if st.game.started {
+                if st.uno_match.game().started {
                     let _ = my_tx.send(Server2Client::ServerError {
+                        code: ErrorCode::GameAlreadyStarted,
                         message: "Game already started".into(),
                     });
                     return;
@@ -163,18 +262,40 @@ fn handle_message(
             let welcome = {
                 let mut st = state.lock().unwrap();
                 // 已加入则拒绝
-                if let Some((_tx, Some(_))) = st.clients.get(conn_index) {
+                if let Some((_tx, Some(_))) = st.clients.get(&conn_id) {
                     let _ = my_tx.send(Server2Client::ServerError {
+                        code: ErrorCode::AlreadyJoined,
                         message: "Already joined".into(),
                     });
                     return;
                 }
-                let player_id = st.players.len();
-                st.players.push(name.clone());
-                if let Some((_tx, pid_slot)) = st.clients.get_mut(conn_index) {
+                if st.players.is_empty() {
+                    // 第一个加入的玩家决定房规
+                    let rules = rules.unwrap_or_default();
+                    if let Err(message) = rules.check(MAX_PLAYERS) {
+                        let _ = my_tx.send(Server2Client::ServerError { code: ErrorCode::InvalidRoomConfig, message });
+                        return;
+                    }
+                    st.uno_match = Match::new(rules.clone());
+                    if let Some(seed) = seed {
+                        if cfg!(debug_assertions) {
+                            st.uno_match = Match::with_seed(seed, rules);
+                        } else {
+                            let _ = my_tx.send(Server2Client::ServerError {
+                                code: ErrorCode::Notice,
+                                message: "Seed is only accepted in debug builds, ignored".into(),
+                            });
+                        }
+                    }
+                }
+                let player_id = PlayerId(st.next_id);
+                st.next_id += 1;
+                st.players.push((player_id, name.clone()));
+                if let Some((_tx, pid_slot)) = st.clients.get_mut(&conn_id) {
                     *pid_slot = Some(player_id);
                 }
                 let session_id = gen_id(12);
+                st.sessions.insert(session_id.clone(), player_id);
                 Server2Client::Welcome {
                     player_id,
                     session_id,
@@ -182,89 +303,142 @@ fn handle_message(
             };
             let _ = my_tx.send(welcome);
         }
+        Client2Server::Spectate { god_view, .. } => {
+            // 未加入的连接本来就只收 SharedState 和隐藏后的事件, 观战只需确认身份
+            let st = state.lock().unwrap();
+            if let Some((_tx, Some(_))) = st.clients.get(&conn_id) {
+                let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::AlreadyJoined,
+                    message: "Already joined".into(),
+                });
+                return;
+            }
+            if god_view {
+                let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::Unsupported,
+                    message: "God view is only supported by the multi-room server".into(),
+                });
+            }
+            let _ = my_tx.send(Server2Client::Spectating { god_view: false });
+            let _ = my_tx.send(Server2Client::SharedState {
+                players_cards_count: st.uno_match.game().get_players_cards_count(),
+                top_card: st.uno_match.game().top_card,
+                current_player: st.uno_match.game().current_player(),
+                clockwise: st.uno_match.game().direction,
+                rules: st.uno_match.rules().clone(),
+            });
+        }
+        Client2Server::Resume { session_id } => {
+            let mut st = state.lock().unwrap();
+            if let Some((_tx, Some(_))) = st.clients.get(&conn_id) {
+                let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::AlreadyJoined,
+                    message: "Already joined".into(),
+                });
+                return;
+            }
+            let Some(&player_id) = st.sessions.get(&session_id) else {
+                let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::SessionExpired,
+                    message: "Session expired or unknown".into(),
+                });
+                return;
+            };
+            // 旧连接若还活着, 解除它与该玩家的绑定
+            for (_tx, pid_slot) in st.clients.values_mut() {
+                if *pid_slot == Some(player_id) {
+                    *pid_slot = None;
+                }
+            }
+            if let Some((_tx, pid_slot)) = st.clients.get_mut(&conn_id) {
+                *pid_slot = Some(player_id);
+            }
+            st.disconnected.remove(&player_id);
+            let _ = my_tx.send(Server2Client::Welcome {
+                player_id,
+                session_id,
+            });
+            let _ = my_tx.send(Server2Client::SharedState {
+                players_cards_count: st.uno_match.game().get_players_cards_count(),
+                top_card: st.uno_match.game().top_card,
+                current_player: st.uno_match.game().current_player(),
+                clockwise: st.uno_match.game().direction,
+                rules: st.uno_match.rules().clone(),
+            });
+            let _ = my_tx.send(Server2Client::PlayerState {
+                player_id,
+                hand: st.uno_match.game().get_player_hand(player_id),
+            });
+        }
         Client2Server::StartGame { player_id } => {
-            if !connection_claim_matches(state, conn_index, player_id) {
+            if !connection_claim_matches(state, conn_id, player_id) {
                 let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::PlayerMismatch,
                     message: "Player ID mismatch or not joined".into(),
                 });
                 return;
             }
-            {
-                let st = state.lock().unwrap();
-                if st.game.started {
+            let ev = {
+                let mut st = state.lock().unwrap();
+                if st.uno_match.game().started {
                     let _ = my_tx.send(Server2Client::ServerError {
-                        message: "Game already started".into(),
+                        code: ErrorCode::GameAlreadyStarted,
+                        message: RuleError::GameAlreadyStarted.to_string(),
                     });
                     return;
                 }
-            }
-            let _ = my_tx.send(Server2Client::ServerError {
-                message: format!("You start the game!").into(),
-            });
-            let ev = {
-                let mut st = state.lock().unwrap();
+                // 上一局中途离开的玩家不再入座
+                let left = std::mem::take(&mut st.left);
+                st.players.retain(|(id, _)| !left.contains(id));
                 let players = st.players.clone();
-                st.game.init_game(players)
+                let res = st.uno_match.start_round(players);
+                if let (Some(replay), Ok(ev)) = (&st.replay, &res) {
+                    if let Some(header) = ReplayHeader::from_events(st.uno_match.round(), ev) {
+                        replay.begin(header, ev);
+                    }
+                }
+                res
             };
-            bus.publish(ev);
+            if publish(bus, my_tx, ev) {
+                let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::Notice,
+                    message: "You start the game!".to_string(),
+                });
+            }
         }
         Client2Server::PlayCard {
             player_id,
             card_index,
             color,
             call_uno,
+            swap_with,
         } => {
-            {
-                let st = state.lock().unwrap();
-                if !st.game.started {
-                    let _ = my_tx.send(Server2Client::ServerError {
-                        message: "Game not started yet".into(),
-                    });
-                    return;
-                }
-                if player_id != st.game.current_player {
-                    let _ = my_tx.send(Server2Client::ServerError {
-                        message: "Not your turn".into(),
-                    });
-                    return;
-                }
-            }
-            if !connection_claim_matches(state, conn_index, player_id) {
+            if !connection_claim_matches(state, conn_id, player_id) {
                 let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::PlayerMismatch,
                     message: "Player ID mismatch or not joined".into(),
                 });
                 return;
             }
             let events = {
                 let mut st = state.lock().unwrap();
-                st.game.play_card(player_id, card_index, call_uno, color)
+                let res = st.uno_match.play_card(player_id, card_index, call_uno, color, swap_with);
+                accepted(&st, res)
             };
             {
                 let st = state.lock().unwrap();
                 let _ = my_tx.send(Server2Client::PlayerState {
                     player_id,
-                    hand: st.game.get_player_hand(player_id),
+                    hand: st.uno_match.game().get_player_hand(player_id),
                 });
             }
-            let game_over = events.iter().any(|e| matches!(e, GE::GameOver { .. }));
-            bus.publish(events);
-            if game_over {
-                // 重置内部 UnoGame，只保留玩家名单，允许下一次 StartGame 重新开局
-                let mut st = state.lock().unwrap();
-                st.game = UnoGame::new();
-            }
+            // 一局结束后 Match 保留玩家名单和累计分数, 下一次 StartGame 开始新的一局
+            publish(bus, my_tx, events);
         }
         Client2Server::DrawCard { player_id, count } => {
-            {
-                let st = state.lock().unwrap();
-                if !st.game.started {
-                    let _ = my_tx.send(Server2Client::ServerError {
-                        message: "Game not started yet".into(),
-                    });
-                }
-            }
-            if !connection_claim_matches(state, conn_index, player_id) {
+            if !connection_claim_matches(state, conn_id, player_id) {
                 let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::PlayerMismatch,
                     message: "Player ID mismatch or not joined".into(),
                 });
                 return;
@@ -273,62 +447,189 @@ fn handle_message(
             for _ in 0..n {
                 let ev = {
                     let mut st = state.lock().unwrap();
-                    st.game.draw_card(player_id)
+                    let res = st.uno_match.draw_card(player_id);
+                    accepted(&st, res)
                 };
-                bus.publish(ev);
+                if !publish(bus, my_tx, ev) {
+                    break;
+                }
             }
         }
         Client2Server::PassTurn { player_id } => {
-            {
-                let st = state.lock().unwrap();
-                if !st.game.started {
-                    let _ = my_tx.send(Server2Client::ServerError {
-                        message: "Game not started yet".into(),
-                    });
-                }
-            }
-            if !connection_claim_matches(state, conn_index, player_id) {
+            if !connection_claim_matches(state, conn_id, player_id) {
                 let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::PlayerMismatch,
                     message: "Player ID mismatch or not joined".into(),
                 });
                 return;
             }
             let ev = {
                 let mut st = state.lock().unwrap();
-                st.game.player_pass(player_id)
+                let res = st.uno_match.player_pass(player_id);
+                accepted(&st, res)
             };
-            bus.publish(ev);
+            publish(bus, my_tx, ev);
         }
-        Client2Server::ChallengeWildDrawFour { .. } => {
-            let _ = my_tx.send(Server2Client::ServerError {
-                message: "Challenge (+4) 尚未实现".into(),
-            });
+        Client2Server::ChooseColor { player_id, color } => {
+            if !connection_claim_matches(state, conn_id, player_id) {
+                let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::PlayerMismatch,
+                    message: "Player ID mismatch or not joined".into(),
+                });
+                return;
+            }
+            let ev = {
+                let mut st = state.lock().unwrap();
+                let res = st.uno_match.choose_color(player_id, color);
+                accepted(&st, res)
+            };
+            publish(bus, my_tx, ev);
         }
-        Client2Server::LeaveGame { player_id } => {
-            if !connection_claim_matches(state, conn_index, player_id) {
+        Client2Server::ChallengeWildDrawFour {
+            challenger_id,
+            challenged_id,
+        } => {
+            if !connection_claim_matches(state, conn_id, challenger_id) {
                 let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::PlayerMismatch,
                     message: "Player ID mismatch or not joined".into(),
                 });
                 return;
             }
-            let mut st = state.lock().unwrap();
-            if player_id >= st.players.len() {
+            let ev = {
+                let mut st = state.lock().unwrap();
+                let res = st.uno_match.challenge(challenger_id, challenged_id);
+                accepted(&st, res)
+            };
+            publish(bus, my_tx, ev);
+        }
+        Client2Server::AcceptWildDrawFour { player_id } => {
+            if !connection_claim_matches(state, conn_id, player_id) {
                 let _ = my_tx.send(Server2Client::ServerError {
-                    message: "Invalid player ID".into(),
+                    code: ErrorCode::PlayerMismatch,
+                    message: "Player ID mismatch or not joined".into(),
                 });
                 return;
             }
-            st.players.remove(player_id);
-            // 不立即移除clients以免打乱索引，可标记None
-            if let Some((_tx, pid_slot)) = st.clients.get_mut(conn_index) {
-                *pid_slot = None;
+            let ev = {
+                let mut st = state.lock().unwrap();
+                let res = st.uno_match.accept_draw_four(player_id);
+                accepted(&st, res)
+            };
+            publish(bus, my_tx, ev);
+        }
+        Client2Server::AddBot { .. } => {
+            let _ = my_tx.send(Server2Client::ServerError {
+                code: ErrorCode::Unsupported,
+                message: "Bots are only supported by the multi-room server".into(),
+            });
+        }
+        Client2Server::ListRooms | Client2Server::CreateRoom { .. } => {
+            let _ = my_tx.send(Server2Client::ServerError {
+                code: ErrorCode::Unsupported,
+                message: "Lobby is only supported by the multi-room server".into(),
+            });
+        }
+        Client2Server::KickPlayer { .. }
+        | Client2Server::TransferHost { .. }
+        | Client2Server::LockRoom { .. }
+        | Client2Server::SetRules { .. } => {
+            let _ = my_tx.send(Server2Client::ServerError {
+                code: ErrorCode::Unsupported,
+                message: "Host controls are only supported by the multi-room server".into(),
+            });
+        }
+        Client2Server::Chat { .. } | Client2Server::SendEmote { .. } => {
+            let _ = my_tx.send(Server2Client::ServerError {
+                code: ErrorCode::Unsupported,
+                message: "Chat is only supported by the multi-room server".into(),
+            });
+        }
+        Client2Server::Login { .. } | Client2Server::GetStats { .. } => {
+            let _ = my_tx.send(Server2Client::ServerError {
+                code: ErrorCode::Unsupported,
+                message: "Accounts are only supported by the multi-room server".into(),
+            });
+        }
+        Client2Server::LeaveGame { player_id } => {
+            if !connection_claim_matches(state, conn_id, player_id) {
+                let _ = my_tx.send(Server2Client::ServerError {
+                    code: ErrorCode::PlayerMismatch,
+                    message: "Player ID mismatch or not joined".into(),
+                });
+                return;
             }
+            let ev = {
+                let mut st = state.lock().unwrap();
+                if !st.players.iter().any(|(id, _)| *id == player_id) || st.left.contains(&player_id) {
+                    let _ = my_tx.send(Server2Client::ServerError {
+                        code: ErrorCode::InvalidPlayer,
+                        message: "Invalid player ID".into(),
+                    });
+                    return;
+                }
+                // 连接还在, 只解除与玩家的绑定
+                if let Some((_tx, pid_slot)) = st.clients.get_mut(&conn_id) {
+                    *pid_slot = None;
+                }
+                vacate_seat(&mut st, player_id)
+            };
+            bus.publish(ev);
         }
     }
 }
 
+// 玩家离开或断线超时: 会话作废, 牌局中保留座位, 这一局结束后再移除
+fn vacate_seat(st: &mut SharedState, player_id: PlayerId) -> Vec<GE> {
+    st.sessions.retain(|_, pid| *pid != player_id);
+    st.disconnected.remove(&player_id);
+    if st.uno_match.game().started {
+        st.left.insert(player_id);
+        let res = st.uno_match.remove_player(player_id);
+        accepted(st, res).unwrap_or_default()
+    } else {
+        st.players.retain(|(id, _)| *id != player_id);
+        vec![GE::PlayerLeft { player_id }]
+    }
+}
+
+// 被接受的操作和它产生的事件在持有锁时一起交给回放记录器, 不会和别的线程的操作交错
+fn accepted(st: &SharedState, res: Result<Accepted, RuleError>) -> Result<Vec<GE>, RuleError> {
+    res.map(|a| {
+        if let Some(replay) = &st.replay {
+            replay.record(&a.command, &a.events);
+        }
+        a.events
+    })
+}
+
+/// 规则拒绝的动作只回给发起者, 其余事件广播; 返回动作是否被接受
+fn publish(bus: &Arc<EventBus>, my_tx: &ClientTx, res: Result<Vec<GE>, RuleError>) -> bool {
+    match res {
+        Ok(ev) => {
+            bus.publish(ev);
+            true
+        }
+        Err(e) => {
+            let _ = my_tx.send(Server2Client::ServerError {
+                code: e.into(),
+                message: e.to_string(),
+            });
+            false
+        }
+    }
+}
+
+// 设置了 UNO_REPLAY_DIR 时每一局都写一个回放文件
+fn replay_recorder() -> Option<ReplayRecorder> {
+    let dir = std::env::var("UNO_REPLAY_DIR").ok()?;
+    ReplayRecorder::new(&dir)
+        .map_err(|e| eprintln!("cannot record replays to {}: {}", dir, e))
+        .ok()
+}
+
 /// 生成随机字符串 ID, 用于游戏 ID 或会话 ID
-/// 目前, 基于ID的校验机制还未实现
+/// 会话 ID 用于断线后 Resume 重新绑定玩家
 fn gen_id(len: usize) -> String {
     rand::thread_rng()
         .sample_iter(&Alphanumeric)
@@ -339,11 +640,11 @@ fn gen_id(len: usize) -> String {
 
 fn connection_claim_matches(
     state: &Arc<Mutex<SharedState>>,
-    conn_index: usize,
-    claimed: usize,
+    conn_id: ConnId,
+    claimed: PlayerId,
 ) -> bool {
     let st = state.lock().unwrap();
-    if let Some((_, Some(pid))) = st.clients.get(conn_index) {
+    if let Some((_, Some(pid))) = st.clients.get(&conn_id) {
         *pid == claimed
     } else {
         false
@@ -1,4 +1,5 @@
-use std::io::{self, BufRead};
+use std::collections::BTreeMap;
+use std::io;
 use std::net::TcpStream;
 use std::thread;
 use std::time::{Duration, Instant};
@@ -21,20 +22,30 @@ use ratatui::{
     Terminal,
 };
 
-use uno::game::cards::{Color as UColor, UnoCard};
+use uno::bot::BotDifficulty;
+use uno::game::cards::{Color as UColor, Number, UnoCard};
 use uno::game::events::GameEvent as GE;
-use uno::protocol::{Client2Server, Server2Client};
+use uno::game::{PlayerId, RuleSet};
+use uno::ports::stats::PlayerStats;
+use uno::protocol::chat::MAX_CHAT_LEN;
+use uno::protocol::client2server::MAX_PLAYERS;
+use uno::protocol::codec::read_frame;
+use uno::protocol::{
+    Capability, Client2Server, Codec, Emote, ErrorCode, Frame, RoomInfo, Server2Client, WireFormat,
+    PROTOCOL_VERSION,
+};
 
 // ---------------- 状态定义 ----------------
 #[derive(Default, Clone)]
 struct GameState {
-    player_id: Option<usize>,
+    player_id: Option<PlayerId>,
     session_id: Option<String>,
-    players_cards_count: Vec<(String, usize)>,
+    players_cards_count: Vec<(PlayerId, String, usize)>, // 按座位顺序
     top_card: Option<UnoCard>,
-    current_player: usize,
+    current_player: PlayerId,
     clockwise: bool,
     hand: Vec<UnoCard>,
+    rules: RuleSet,
 }
 #[derive(Default, Clone)]
 struct AppState {
@@ -46,12 +57,57 @@ struct AppState {
     mode: UiMode,
     pending_action: Option<PendingPlay>,
     color_pick_index: usize,
+    swap_pick_index: usize, // 7-0 房规下选择换牌对象
     room_input: String,
     name_input: String,
+    password_input: String,
+    max_players_input: String, // 新建房间时的人数上限, 空着就是默认值
     input_focus: InputFocus,
+    lobby: Vec<RoomInfo>, // 大厅里的房间列表
+    lobby_cursor: usize,
     scoreboard: Option<Vec<ScoreEntry>>,
+    round: u32, // 多局比赛的当前局数
+    match_winner: Option<String>, // 比赛结束时的赢家
     room_id: Option<String>, // 新增: 当前房间ID
+    reconnect: Option<Reconnect>, // 断线后的自动重连计划
+    turn_deadline: Option<(PlayerId, Instant)>, // 回合倒计时 (player_id, 截止时间)
+    join_as: JoinAs,
+    spectator: Option<bool>, // 观战中, 值为是否上帝视角
+    all_hands: BTreeMap<PlayerId, Vec<UnoCard>>, // 上帝视角下所有人的手牌
+    chat: Vec<String>,
+    chat_input: String,
+    host: Option<PlayerId>,
+    room_locked: bool,
+    host_pick_index: usize,
+    server_caps: Option<Vec<Capability>>, // 服务器在 HelloAck 里声明的功能, None 表示老版本服务器
+    account: Option<String>, // 已登录的账号名
+    login_name: String,
+    login_password: String,
+    profile_pick: usize, // 战绩弹窗里正在看的玩家
+    profile: Option<(String, Option<PlayerStats>)>, // 最近一次 Stats 回复, None 表示未注册
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum JoinAs {
+    #[default]
+    Player,
+    Spectator {
+        god_view: bool,
+    },
+}
+#[derive(Clone, Copy, Debug)]
+struct Reconnect {
+    at: Instant,
+    attempt: u32,
 }
+// 网络线程发给 UI 的消息
+enum NetEvent {
+    Msg(Server2Client),
+    Unknown, // 服务器发来的新消息类型, 跳过
+    Disconnected,
+}
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+const RECONNECT_MAX_ATTEMPTS: u32 = 30;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(3);
 #[derive(Clone, Copy, Debug, Default)]
 enum UiMode {
     #[default]
@@ -60,8 +116,19 @@ enum UiMode {
     DrawnCardPlayable {
         card_index: usize,
     },
-    NameInput,
+    Lobby,
     Scoreboard,
+    ChallengePrompt {
+        challenged_id: PlayerId,
+    },
+    SwapPick {
+        card_index: usize,
+        call_uno: bool,
+    },
+    ChatInput,
+    HostMenu,
+    Login,
+    Profile,
 }
 #[derive(Clone, Debug)]
 struct PendingPlay {
@@ -71,13 +138,13 @@ struct PendingPlay {
 #[derive(Clone, Debug)]
 struct ScoreEntry {
     name: String,
-    score: i32,
+    score: i32, // 本局结束时手里剩下的分数
+    total: i32, // 比赛累计得分
     rank: usize,
     is_winner: bool,
 }
-#[derive(Clone, Copy, Debug)]
-enum InputFocus { Room, Name }
-impl Default for InputFocus { fn default() -> Self { InputFocus::Room } }
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum InputFocus { #[default] Room, Name, Password, MaxPlayers }
 
 impl AppState {
     fn push_log<S: Into<String>>(&mut self, s: S) {
@@ -163,13 +230,8 @@ fn card_line(card: &UnoCard, selected: bool) -> Line<'static> {
 // ---------------- 主入口 ----------------
 fn main() -> io::Result<()> {
     let addr = "127.0.0.1:9000";
-    let stream = TcpStream::connect(addr)?;
-    stream.set_nodelay(true)?;
-    let read_stream = stream.try_clone()?;
-    let (net_to_ui_tx, net_to_ui_rx) = flume::bounded::<Server2Client>(1024);
-    let (ui_to_net_tx, ui_to_net_rx) = flume::bounded::<Client2Server>(1024);
-    thread::spawn(move || net_read_loop(read_stream, net_to_ui_tx));
-    thread::spawn(move || net_write_loop(stream, ui_to_net_rx));
+    let (net_to_ui_tx, net_to_ui_rx) = flume::bounded::<NetEvent>(1024);
+    let mut ui_to_net_tx = connect(addr, net_to_ui_tx.clone())?;
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
@@ -177,13 +239,52 @@ fn main() -> io::Result<()> {
     let mut terminal = Terminal::new(backend)?;
     let mut app = AppState::default();
     app.input_hint.push(Line::from("J 加入"));
+    app.input_hint.push(Line::from("A 登录 / I 战绩"));
     app.push_log(format!("连接到 {}，按 J 加入游戏", addr));
     let tick_rate = Duration::from_millis(100);
     let mut last_tick = Instant::now();
     let mut quit = false;
+    app.connected = true;
     while !quit {
-        while let Ok(msg) = net_to_ui_rx.try_recv() {
-            handle_server_msg(&mut app, msg, &ui_to_net_tx);
+        while let Ok(ev) = net_to_ui_rx.try_recv() {
+            match ev {
+                NetEvent::Msg(msg) => handle_server_msg(&mut app, msg, &ui_to_net_tx),
+                NetEvent::Unknown => app.push_log("[Warn] 忽略一条不认识的消息, 服务器版本可能更新"),
+                NetEvent::Disconnected => {
+                    app.connected = false;
+                    app.push_log("与服务器断开连接, 准备自动重连...");
+                    app.reconnect = Some(Reconnect {
+                        at: Instant::now(),
+                        attempt: 0,
+                    });
+                }
+            }
+        }
+        if let Some(r) = app.reconnect.filter(|r| r.at <= Instant::now()) {
+            match connect(addr, net_to_ui_tx.clone()) {
+                Ok(tx) => {
+                    ui_to_net_tx = tx;
+                    app.connected = true;
+                    app.reconnect = None;
+                    if let Some(session_id) = app.game_state.session_id.clone() {
+                        ui_to_net_tx.send(Client2Server::Resume { session_id }).ok();
+                        app.push_log("已重连, 正在恢复座位");
+                    } else {
+                        app.push_log("已重连，按 J 加入游戏");
+                    }
+                }
+                Err(e) if r.attempt + 1 < RECONNECT_MAX_ATTEMPTS => {
+                    app.push_log(format!("重连失败 ({}), {}s 后重试", e, RECONNECT_INTERVAL.as_secs()));
+                    app.reconnect = Some(Reconnect {
+                        at: Instant::now() + RECONNECT_INTERVAL,
+                        attempt: r.attempt + 1,
+                    });
+                }
+                Err(e) => {
+                    app.push_log(format!("重连失败 ({}), 已放弃", e));
+                    app.reconnect = None;
+                }
+            }
         }
         terminal.draw(|f| ui(f, &app))?;
         let timeout = tick_rate.saturating_sub(last_tick.elapsed());
@@ -222,14 +323,27 @@ fn should_quit(key: KeyEvent, app: &mut AppState, tx: &Sender<Client2Server>) ->
             if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
                 return Ok(true);
             }
-            handle_key_normal(key, app, tx)?;
+            // 聊天对玩家和观战者都开放, 观战者不能做其它操作
+            if !handle_key_chat_shortcut(key, app, tx) && app.spectator.is_none() {
+                handle_key_normal(key, app, tx)?;
+            }
         }
         UiMode::ColorPick => handle_key_colorpick(key, app, tx)?,
         UiMode::DrawnCardPlayable { card_index } => {
             handle_key_drawn_playable(key, app, tx, card_index)?
         }
-        UiMode::NameInput => handle_key_name_input(key, app, tx)?,
+        UiMode::Lobby => handle_key_lobby(key, app, tx)?,
         UiMode::Scoreboard => handle_key_scoreboard(key, app, tx)?,
+        UiMode::ChallengePrompt { challenged_id } => {
+            handle_key_challenge(key, app, tx, challenged_id)?
+        }
+        UiMode::SwapPick { card_index, call_uno } => {
+            handle_key_swap_pick(key, app, tx, card_index, call_uno)?
+        }
+        UiMode::ChatInput => handle_key_chat_input(key, app, tx)?,
+        UiMode::HostMenu => handle_key_host_menu(key, app, tx)?,
+        UiMode::Login => handle_key_login(key, app, tx)?,
+        UiMode::Profile => handle_key_profile(key, app, tx)?,
     };
     Ok(false)
 }
@@ -240,12 +354,43 @@ fn handle_key_normal(
     tx: &Sender<Client2Server>,
 ) -> io::Result<()> {
     match key.code {
+        KeyCode::Char('w') | KeyCode::Char('W') if app.game_state.player_id.is_none() => {
+            let god_view = key.code == KeyCode::Char('W');
+            open_lobby(app, tx, JoinAs::Spectator { god_view });
+        }
+        KeyCode::Char('h')
+            if app.host.is_some()
+                && app.host == app.game_state.player_id
+                && server_supports(app, Capability::HostControls) =>
+        {
+            app.host_pick_index = 0;
+            app.mode = UiMode::HostMenu;
+        }
+        KeyCode::Char('a')
+            if app.game_state.player_id.is_none() && server_supports(app, Capability::Accounts) =>
+        {
+            app.login_name = app.account.clone().unwrap_or_default();
+            app.login_password.clear();
+            app.input_focus = InputFocus::Name;
+            app.mode = UiMode::Login;
+        }
+        KeyCode::Char('i') if server_supports(app, Capability::Accounts) => {
+            app.profile_pick = 0;
+            app.profile = None;
+            if let Some(name) = profile_names(app).into_iter().next() {
+                tx.send(Client2Server::GetStats { name }).ok();
+            }
+            app.mode = UiMode::Profile;
+        }
+        KeyCode::Char('l') => {
+            if let Some(pid) = app.game_state.player_id {
+                tx.send(Client2Server::LeaveGame { player_id: pid }).ok();
+                leave_room(app);
+                app.push_log("已离开房间, 按 J 加入其它房间");
+            }
+        }
         KeyCode::Char('j') => {
-            app.mode = UiMode::NameInput;
-            app.room_input.clear();
-            app.name_input.clear();
-            app.input_focus = InputFocus::Room;
-            app.push_log("输入房间与昵称，Tab 切换，Enter 提交，Esc 取消");
+            open_lobby(app, tx, JoinAs::Player);
             app.input_hint = vec![Line::from("S 开局")];
         }
         KeyCode::Char('s') => {
@@ -253,10 +398,17 @@ fn handle_key_normal(
                 tx.send(Client2Server::StartGame { player_id: pid }).ok();
             }
         }
+        KeyCode::Char('b') | KeyCode::Char('B') if app.game_state.player_id.is_some() => {
+            let difficulty = if key.code == KeyCode::Char('B') {
+                BotDifficulty::Hard
+            } else {
+                BotDifficulty::Easy
+            };
+            tx.send(Client2Server::AddBot { difficulty }).ok();
+            app.push_log(format!("添加电脑玩家 ({:?})", difficulty));
+        }
         KeyCode::Up => {
-            if app.cursor > 0 {
-                app.cursor -= 1;
-            }
+            app.cursor = app.cursor.saturating_sub(1);
         }
         KeyCode::Down => {
             app.cursor = app
@@ -295,7 +447,8 @@ fn handle_key_colorpick(
     tx: &Sender<Client2Server>,
 ) -> io::Result<()> {
     match key.code {
-        KeyCode::Esc => {
+        // 没有待出的牌说明是在给第一张万能顶牌选颜色, 不能取消
+        KeyCode::Esc if app.pending_action.is_some() => {
             app.mode = UiMode::Normal;
             app.pending_action = None;
         }
@@ -314,22 +467,27 @@ fn handle_key_colorpick(
         KeyCode::Char('b') => app.color_pick_index = 2,
         KeyCode::Char('y') => app.color_pick_index = 3,
         KeyCode::Enter => {
-            if let Some(p) = app.pending_action.take() {
-                if let Some(pid) = app.game_state.player_id {
-                    let color = match app.color_pick_index {
-                        0 => UColor::RED,
-                        1 => UColor::GREEN,
-                        2 => UColor::BLUE,
-                        _ => UColor::YELLOW,
-                    };
-                    tx.send(Client2Server::PlayCard {
+            if let Some(pid) = app.game_state.player_id {
+                let color = match app.color_pick_index {
+                    0 => UColor::RED,
+                    1 => UColor::GREEN,
+                    2 => UColor::BLUE,
+                    _ => UColor::YELLOW,
+                };
+                match app.pending_action.take() {
+                    Some(p) => tx.send(Client2Server::PlayCard {
                         player_id: pid,
                         card_index: p.card_index,
-                        color,
+                        color: Some(color),
                         call_uno: p.call_uno,
-                    })
-                    .ok();
+                        swap_with: None,
+                    }),
+                    None => tx.send(Client2Server::ChooseColor {
+                        player_id: pid,
+                        color,
+                    }),
                 }
+                .ok();
             }
             app.mode = UiMode::Normal;
         }
@@ -365,6 +523,76 @@ fn handle_key_drawn_playable(
     Ok(())
 }
 
+fn handle_key_challenge(
+    key: KeyEvent,
+    app: &mut AppState,
+    tx: &Sender<Client2Server>,
+    challenged_id: PlayerId,
+) -> io::Result<()> {
+    let Some(pid) = app.game_state.player_id else {
+        app.mode = UiMode::Normal;
+        return Ok(());
+    };
+    match key.code {
+        KeyCode::Char('c') => {
+            tx.send(Client2Server::ChallengeWildDrawFour {
+                challenger_id: pid,
+                challenged_id,
+            })
+            .ok();
+            app.mode = UiMode::Normal;
+        }
+        KeyCode::Char('a') | KeyCode::Enter => {
+            tx.send(Client2Server::AcceptWildDrawFour { player_id: pid })
+                .ok();
+            app.mode = UiMode::Normal;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn handle_key_swap_pick(
+    key: KeyEvent,
+    app: &mut AppState,
+    tx: &Sender<Client2Server>,
+    card_index: usize,
+    call_uno: bool,
+) -> io::Result<()> {
+    let n = app.game_state.players_cards_count.len().max(1);
+    match key.code {
+        KeyCode::Esc => {
+            app.mode = UiMode::Normal;
+        }
+        KeyCode::Up | KeyCode::Left => {
+            app.swap_pick_index = (app.swap_pick_index + n - 1) % n;
+        }
+        KeyCode::Down | KeyCode::Right => {
+            app.swap_pick_index = (app.swap_pick_index + 1) % n;
+        }
+        KeyCode::Enter => {
+            if let Some(pid) = app.game_state.player_id {
+                let other = seat_player(app, app.swap_pick_index);
+                if other == Some(pid) {
+                    app.push_log("不能和自己换牌");
+                    return Ok(());
+                }
+                tx.send(Client2Server::PlayCard {
+                    player_id: pid,
+                    card_index,
+                    color: None,
+                    call_uno,
+                    swap_with: other,
+                })
+                .ok();
+            }
+            app.mode = UiMode::Normal;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 fn try_play_selected(
     call_uno: bool,
     app: &mut AppState,
@@ -381,7 +609,7 @@ fn try_play_selected(
 fn play_card_with_color_resolution(
     app: &mut AppState,
     tx: &Sender<Client2Server>,
-    pid: usize,
+    pid: PlayerId,
     card_index: usize,
     call_uno: bool,
 ) -> io::Result<()> {
@@ -391,8 +619,9 @@ fn play_card_with_color_resolution(
                 tx.send(Client2Server::PlayCard {
                     player_id: pid,
                     card_index,
-                    color: c,
+                    color: Some(c),
                     call_uno,
+                    swap_with: None,
                 })
                 .ok();
             }
@@ -404,12 +633,26 @@ fn play_card_with_color_resolution(
                 app.color_pick_index = 0;
                 app.mode = UiMode::ColorPick;
             }
+            // 7-0 房规: 多于两人时出 7 要先选换牌对象
+            UnoCard::NumberCard(_, Number::SEVEN)
+                if app.game_state.rules.seven_zero
+                    && app.game_state.players_cards_count.len() > 2 =>
+            {
+                let seats = &app.game_state.players_cards_count;
+                let me = seats.iter().position(|(id, _, _)| *id == pid).unwrap_or(0);
+                app.swap_pick_index = (me + 1) % seats.len();
+                app.mode = UiMode::SwapPick {
+                    card_index,
+                    call_uno,
+                };
+            }
             _ => {
                 tx.send(Client2Server::PlayCard {
                     player_id: pid,
                     card_index,
-                    color: UColor::RED, // 在uno_game里面只对WildCard有传入颜色要求
+                    color: None, // 只有万能牌需要颜色
                     call_uno,
+                    swap_with: None,
                 })
                 .ok();
             }
@@ -419,32 +662,113 @@ fn play_card_with_color_resolution(
 }
 
 // ---------------- 网络 IO ----------------
-fn net_read_loop(stream: TcpStream, tx: Sender<Server2Client>) {
-    let reader = std::io::BufReader::new(stream);
-    for line in reader.lines() {
-        match line {
-            Ok(text) => {
-                if let Ok(msg) = serde_json::from_str::<Server2Client>(&text) {
-                    let _ = tx.send(msg);
+// 建立连接并启动读写线程, 返回发往服务器的通道
+// 每次(重)连都换一条新通道, 旧的写线程随旧通道一起退出
+// 设置 UNO_WIRE=msgpack 时握手协商二进制编码, 默认 JSON Lines
+fn connect(addr: &str, net_tx: Sender<NetEvent>) -> io::Result<Sender<Client2Server>> {
+    let stream = TcpStream::connect(addr)?;
+    stream.set_nodelay(true)?;
+    let read_stream = stream.try_clone()?;
+    let (ui_to_net_tx, ui_to_net_rx) = flume::bounded::<Client2Server>(1024);
+    let (format_tx, format_rx) = flume::bounded::<WireFormat>(1);
+    thread::spawn(move || net_read_loop(read_stream, net_tx, format_tx));
+    thread::spawn(move || net_write_loop(stream, ui_to_net_rx, format_rx));
+    let formats = match std::env::var("UNO_WIRE").as_deref() {
+        Ok("msgpack") => vec![WireFormat::MsgPack],
+        _ => Vec::new(),
+    };
+    ui_to_net_tx
+        .send(Client2Server::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            client_name: format!("uno tui_client {}", env!("CARGO_PKG_VERSION")),
+            capabilities: Capability::ALL.to_vec(),
+            formats,
+        })
+        .ok();
+    Ok(ui_to_net_tx)
+}
+fn net_read_loop(mut stream: TcpStream, tx: Sender<NetEvent>, format_tx: Sender<WireFormat>) {
+    let mut format = WireFormat::Json;
+    let mut buf = Vec::new();
+    while let Ok(Some(frame)) = read_frame(&format, &mut stream, &mut buf) {
+        match frame {
+            Frame::Msg(msg) => {
+                // HelloAck 之后服务器改用协商好的编码, 写线程也要跟着换
+                if let Server2Client::HelloAck { format: f, .. } = &msg {
+                    format = *f;
+                    let _ = format_tx.send(*f);
                 }
+                let _ = tx.send(NetEvent::Msg(msg));
+            }
+            Frame::Unknown => {
+                let _ = tx.send(NetEvent::Unknown);
             }
-            Err(_) => break,
+            Frame::Invalid(_) => {}
         }
     }
+    let _ = tx.send(NetEvent::Disconnected);
 }
-fn net_write_loop(mut stream: TcpStream, rx: Receiver<Client2Server>) {
+fn net_write_loop(mut stream: TcpStream, rx: Receiver<Client2Server>, format_rx: Receiver<WireFormat>) {
+    use std::io::Write;
+    let mut format = WireFormat::Json;
+    let mut buf = Vec::new();
     while let Ok(msg) = rx.recv() {
-        if let Ok(json) = serde_json::to_string(&msg) {
-            use std::io::Write;
-            if writeln!(stream, "{}", json).is_err() {
-                break;
-            }
-            let _ = stream.flush();
+        buf.clear();
+        if format.encode(&msg, &mut buf).is_err() {
+            continue;
+        }
+        if stream.write_all(&buf).is_err() {
+            break;
+        }
+        let _ = stream.flush();
+        // 发完 Hello 等服务器确认编码再发下一条, 老服务器不回 HelloAck 就一直用 JSON
+        if matches!(msg, Client2Server::Hello { .. }) {
+            format = format_rx.recv_timeout(HANDSHAKE_TIMEOUT).unwrap_or_default();
         }
     }
 }
 
 // ---------------- 协议消息处理 ----------------
+// 按错误码给出中文提示, 没有对应翻译的直接用服务器的原文
+fn error_text(code: ErrorCode, message: &str) -> String {
+    let text = match code {
+        ErrorCode::BadJson => "消息格式错误",
+        ErrorCode::NotJoined => "请先加入房间",
+        ErrorCode::AlreadyJoined => "已经在房间里了",
+        ErrorCode::PlayerMismatch => "只能操作自己的座位",
+        ErrorCode::SessionExpired => "会话已过期, 请重新加入",
+        ErrorCode::RateLimited => "发言太快了",
+        ErrorCode::RoomFull => "房间已满",
+        ErrorCode::RoomLocked => "房间已锁定",
+        ErrorCode::RoomExists => "房间已存在",
+        ErrorCode::WrongPassword => "房间密码错误",
+        ErrorCode::NotHost => "只有房主可以这样做",
+        ErrorCode::SpectatorCannotPlay => "观战中不能出牌",
+        ErrorCode::GameNotStarted => "游戏还没开始",
+        ErrorCode::GameAlreadyStarted => "游戏已经开始了",
+        ErrorCode::NotYourTurn => "还没轮到你",
+        ErrorCode::InvalidCardIndex => "没有这张牌",
+        ErrorCode::IllegalCard => "这张牌不能出",
+        ErrorCode::ColorRequired => "万能牌需要先选颜色",
+        ErrorCode::MustPlayDrawnCard => "只能出刚摸到的那张牌",
+        ErrorCode::ForcedPlay => "必须打出刚摸到的牌",
+        ErrorCode::AlreadyDrawn => "这回合已经摸过牌了",
+        ErrorCode::MustDrawBeforePass => "先摸牌才能跳过",
+        ErrorCode::JumpInNotIdentical => "抢出只能出和顶牌完全相同的牌",
+        ErrorCode::MustStackOrTake => "只能叠加罚牌或者直接摸牌",
+        ErrorCode::DrawFourPending => "请先质疑或接受 +4",
+        ErrorCode::ColorChoicePending => "请先为第一张牌选颜色",
+        ErrorCode::DeckEmpty => "牌堆已经摸空了",
+        ErrorCode::RulesLocked => "比赛进行中不能修改房规",
+        ErrorCode::RoomNotFound => "房间不存在, 请在大厅里刷新列表或新建房间",
+        ErrorCode::LoginFailed => "账号或密码错误",
+        ErrorCode::NameTaken => "这个账号名已经被注册了",
+        ErrorCode::NameReserved => "这个昵称是已注册的账号, 请先按 A 登录",
+        _ => return message.to_string(),
+    };
+    text.to_string()
+}
+
 fn handle_server_msg(app: &mut AppState, msg: Server2Client, tx: &Sender<Client2Server>) {
     match msg {
         Server2Client::Welcome {
@@ -456,22 +780,75 @@ fn handle_server_msg(app: &mut AppState, msg: Server2Client, tx: &Sender<Client2
             app.connected = true;
             app.push_log(format!("Welcome! 你的 id 是 {}", player_id));
         }
+        Server2Client::HelloAck {
+            protocol_version,
+            server_name,
+            capabilities,
+            format,
+        } => {
+            app.push_log(format!("服务器 {} (协议 v{}, 编码 {:?})", server_name, protocol_version, format));
+            app.server_caps = Some(capabilities);
+        }
         Server2Client::Events(ev) => {
             handle_events(app, &ev, tx);
         }
-        Server2Client::ServerError { message } => app.push_log(format!("[Error] {}", message)),
+        Server2Client::ServerError { code: ErrorCode::Notice, message } => app.push_log(message),
+        Server2Client::ServerError { code, message } => {
+            app.push_log(format!("[Error] {}", error_text(code, &message)))
+        }
+        Server2Client::LoggedIn { name } => {
+            app.push_log(format!("已登录账号 {}, 战绩会记录到账号下", name));
+            app.account = Some(name);
+            app.login_password.clear();
+            if matches!(app.mode, UiMode::Login) {
+                app.mode = UiMode::Normal;
+            }
+        }
+        Server2Client::Stats { name, stats } => {
+            app.profile = Some((name, stats));
+        }
+        Server2Client::RoomList { rooms } => {
+            app.lobby = rooms;
+            app.lobby_cursor = app.lobby_cursor.min(app.lobby.len().saturating_sub(1));
+        }
+        Server2Client::ChatMessage { from, text, ts: _ } => {
+            app.chat.push(format!("{}: {}", from, text));
+        }
         Server2Client::SharedState {
             players_cards_count,
             top_card,
             current_player,
             clockwise,
+            rules,
         } => {
+            app.game_state.rules = rules;
             app.game_state.players_cards_count = players_cards_count;
             app.game_state.top_card = top_card;
             app.game_state.current_player = current_player;
             app.game_state.clockwise = clockwise;
         }
+        Server2Client::TurnDeadline {
+            player_id,
+            deadline_ms,
+        } => {
+            app.turn_deadline =
+                Some((player_id, Instant::now() + Duration::from_millis(deadline_ms)));
+        }
+        Server2Client::Spectating { god_view } => {
+            app.spectator = Some(god_view);
+            app.connected = true;
+            // 上帝视角被收回时不再显示别人的手牌
+            if !god_view {
+                app.all_hands.clear();
+            }
+            app.push_log(if god_view { "正在观战 (上帝视角)" } else { "正在观战" });
+            app.input_hint = vec![Line::from("观战中, 不能操作"), Line::from("Q 退出")];
+        }
         Server2Client::PlayerState { player_id, hand } => {
+            if app.spectator == Some(true) {
+                app.all_hands.insert(player_id, hand);
+                return;
+            }
             if Some(player_id) == app.game_state.player_id {
                 app.game_state.hand = hand;
                 if app.cursor >= app.game_state.hand.len() {
@@ -499,45 +876,69 @@ fn ui(f: &mut ratatui::Frame<'_>, app: &AppState) {
     match app.mode {
         UiMode::ColorPick => draw_color_picker_popup(f, size, app),
         UiMode::DrawnCardPlayable { .. } => draw_drawn_playable_popup(f, size),
-        UiMode::NameInput => draw_name_input_popup(f, size, app),
+        UiMode::Lobby => draw_lobby_popup(f, size, app),
         UiMode::Scoreboard => draw_scoreboard_popup(f, size, app),
-        UiMode::Normal => {}
+        UiMode::ChallengePrompt { challenged_id } => {
+            draw_challenge_popup(f, size, challenged_id)
+        }
+        UiMode::SwapPick { .. } => draw_swap_pick_popup(f, size, app),
+        UiMode::HostMenu => draw_host_menu_popup(f, size, app),
+        UiMode::Login => draw_login_popup(f, size, app),
+        UiMode::Profile => draw_profile_popup(f, size, app),
+        UiMode::Normal | UiMode::ChatInput => {}
     }
 }
 
 fn draw_status(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState) {
+    let countdown = match app.turn_deadline {
+        Some((pid, at)) => format!(
+            " | Player {} 剩余:{}s",
+            pid,
+            at.saturating_duration_since(Instant::now()).as_secs()
+        ),
+        None => String::new(),
+    };
     let title = format!(
-        "UNO | 房间:{} | 玩家:{} | 当前:{} | 方向:{}",
+        "UNO | {} | 房间:{} | 玩家:{} | 当前:{} | 方向:{}{}",
+        if app.connected { "在线" } else { "断开" },
         app.room_id.as_deref().unwrap_or("-"),
         app.game_state
             .player_id
             .map(|v| v.to_string())
             .unwrap_or_else(|| "-".into()),
         app.game_state.current_player,
-        if app.game_state.clockwise { "顺时针" } else { "逆时针" }
+        if app.game_state.clockwise { "顺时针" } else { "逆时针" },
+        countdown
     );
     let para = Paragraph::new(title).block(Block::default().borders(Borders::ALL).title("状态"));
     f.render_widget(para, area);
 }
 
 fn draw_main(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState) {
-    let cols = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
+    // 普通观战者没有手牌面板
+    let constraints = if app.spectator == Some(false) {
+        vec![Constraint::Percentage(50), Constraint::Percentage(50)]
+    } else {
+        vec![
             Constraint::Percentage(33),
             Constraint::Percentage(34),
             Constraint::Percentage(33),
-        ])
+        ]
+    };
+    let cols = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(constraints)
         .split(area);
     // 左：玩家
     let mut players_text: Vec<Line> = Vec::new();
-    for (i, (name, n)) in app.game_state.players_cards_count.iter().enumerate() {
-        let turn = if i == app.game_state.current_player {
+    for (pid, name, n) in app.game_state.players_cards_count.iter() {
+        let turn = if *pid == app.game_state.current_player {
             " ←"
         } else {
             ""
         };
-        players_text.push(Line::from(format!("{}: {:>2}{}", name, n, turn)));
+        let host = if Some(*pid) == app.host { "★" } else { "" };
+        players_text.push(Line::from(format!("{}{}: {:>2}{}", host, name, n, turn)));
     }
     let players = Paragraph::new(Text::from(players_text))
         .block(Block::default().borders(Borders::ALL).title("玩家"));
@@ -553,6 +954,28 @@ fn draw_main(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState) {
     let desk = Paragraph::new(Text::from(lines))
         .block(Block::default().borders(Borders::ALL).title("桌面"));
     f.render_widget(desk, cols[1]);
+    match app.spectator {
+        Some(false) => return,
+        Some(true) => {
+            // 上帝视角: 右侧显示所有人的手牌
+            let mut lines: Vec<Line> = Vec::new();
+            for (pid, hand) in &app.all_hands {
+                let name = app
+                    .game_state
+                    .players_cards_count
+                    .iter()
+                    .find(|(id, _, _)| id == pid)
+                    .map_or("?", |(_, n, _)| n.as_str());
+                lines.push(Line::from(format!("{} ({})", name, hand.len())));
+                lines.extend(hand.iter().map(|c| card_line(c, false)));
+            }
+            let hands = Paragraph::new(Text::from(lines))
+                .block(Block::default().borders(Borders::ALL).title("所有手牌"));
+            f.render_widget(hands, cols[2]);
+            return;
+        }
+        None => {}
+    }
     // 右：手牌
     let hand_lines: Vec<Line> = app
         .game_state
@@ -567,6 +990,10 @@ fn draw_main(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState) {
 }
 
 fn draw_log(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState) {
+    let cols = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
     let lines: Vec<Line> = app
         .log
         .iter()
@@ -577,7 +1004,74 @@ fn draw_log(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState) {
         .collect();
     let para = Paragraph::new(Text::from(lines))
         .block(Block::default().borders(Borders::ALL).title("日志"));
-    f.render_widget(para, area);
+    f.render_widget(para, cols[0]);
+    // 右: 聊天, 输入时最后一行是输入框
+    let typing = matches!(app.mode, UiMode::ChatInput);
+    let rows = cols[1].height.saturating_sub(2) as usize;
+    let shown = if typing { rows.saturating_sub(1) } else { rows };
+    let mut lines: Vec<Line> = app.chat[app.chat.len().saturating_sub(shown)..]
+        .iter()
+        .cloned()
+        .map(Line::from)
+        .collect();
+    if typing {
+        lines.push(Line::from(Span::styled(
+            format!("> {}_", app.chat_input),
+            Style::default().fg(TColor::Yellow),
+        )));
+    }
+    let title = if typing { "聊天 (Enter 发送 / Esc 取消)" } else { "聊天 (T 输入 / 1-6 表情)" };
+    let para = Paragraph::new(Text::from(lines))
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(para, cols[1]);
+}
+
+// T 打开聊天输入, 数字键发送预设表情; 返回是否处理了按键
+fn handle_key_chat_shortcut(key: KeyEvent, app: &mut AppState, tx: &Sender<Client2Server>) -> bool {
+    if !app.connected {
+        return false;
+    }
+    if !server_supports(app, Capability::Chat) {
+        return false;
+    }
+    match key.code {
+        KeyCode::Char('t') | KeyCode::Char('T') => {
+            app.chat_input.clear();
+            app.mode = UiMode::ChatInput;
+            true
+        }
+        KeyCode::Char(c @ '1'..='6') => {
+            let emote = Emote::ALL[c as usize - '1' as usize];
+            tx.send(Client2Server::SendEmote { emote }).ok();
+            true
+        }
+        _ => false,
+    }
+}
+
+fn handle_key_chat_input(
+    key: KeyEvent,
+    app: &mut AppState,
+    tx: &Sender<Client2Server>,
+) -> io::Result<()> {
+    match key.code {
+        KeyCode::Esc => app.mode = UiMode::Normal,
+        KeyCode::Enter => {
+            let text = std::mem::take(&mut app.chat_input);
+            if !text.trim().is_empty() {
+                tx.send(Client2Server::Chat { text }).ok();
+            }
+            app.mode = UiMode::Normal;
+        }
+        KeyCode::Backspace => {
+            app.chat_input.pop();
+        }
+        KeyCode::Char(c) if !c.is_control() && app.chat_input.chars().count() < MAX_CHAT_LEN => {
+            app.chat_input.push(c);
+        }
+        _ => {}
+    }
+    Ok(())
 }
 
 // ---------------- 弹窗 ----------------
@@ -615,51 +1109,349 @@ fn draw_drawn_playable_popup(f: &mut ratatui::Frame<'_>, area: Rect) {
         .block(Block::default().borders(Borders::ALL).title("摸牌可出"));
     f.render_widget(block, popup);
 }
-fn handle_key_name_input(key: KeyEvent, app: &mut AppState, tx: &Sender<Client2Server>) -> io::Result<()> {
+fn draw_challenge_popup(f: &mut ratatui::Frame<'_>, area: Rect, challenged_id: PlayerId) {
+    let popup = centered_rect(50, 20, area);
+    let lines = vec![
+        Line::from(format!("Player {} 对你打出了 +4", challenged_id)),
+        Line::from("C 质疑 (成功对方摸4张, 失败你摸6张) / A 或 Enter 接受摸4张"),
+    ];
+    let block = Paragraph::new(Text::from(lines))
+        .block(Block::default().borders(Borders::ALL).title("+4 质疑"));
+    f.render_widget(block, popup);
+}
+fn draw_swap_pick_popup(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState) {
+    let popup = centered_rect(40, 40, area);
+    let mut lines = vec![Line::from("选择换牌对象 (↑/↓ 选择, Enter 确认, Esc 取消)")];
+    for (i, (_, name, n)) in app.game_state.players_cards_count.iter().enumerate() {
+        let style = if i == app.swap_pick_index {
+            Style::default()
+                .fg(TColor::Yellow)
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED)
+        } else {
+            Style::default().fg(TColor::White)
+        };
+        lines.push(Line::from(Span::styled(format!(" {}: {:>2} ", name, n), style)));
+    }
+    let block = Paragraph::new(Text::from(lines))
+        .block(Block::default().borders(Borders::ALL).title("7 换牌"));
+    f.render_widget(block, popup);
+}
+// 房主菜单可切换的房规, 与数字键 1-6 对应
+const HOST_RULE_TOGGLES: [&str; 6] =
+    ["叠加 +2/+4", "7-0 换牌", "抢出", "摸到能出为止", "摸到必出", "随机座位"];
+
+fn rule_flag(rules: &mut RuleSet, i: usize) -> &mut bool {
+    match i {
+        0 => &mut rules.stack_draws,
+        1 => &mut rules.seven_zero,
+        2 => &mut rules.jump_in,
+        3 => &mut rules.draw_until_playable,
+        4 => &mut rules.forced_play,
+        _ => &mut rules.random_seats,
+    }
+}
+
+fn player_name(app: &AppState, pid: PlayerId) -> Option<String> {
+    app.game_state
+        .players_cards_count
+        .iter()
+        .find(|(id, _, _)| *id == pid)
+        .map(|(_, name, _)| name.clone())
+}
+
+// 玩家面板里第 seat 个座位上的玩家
+// 没收到 HelloAck 的老服务器按全部支持处理
+fn server_supports(app: &AppState, cap: Capability) -> bool {
+    app.server_caps.as_ref().is_none_or(|caps| caps.contains(&cap))
+}
+
+fn seat_player(app: &AppState, seat: usize) -> Option<PlayerId> {
+    app.game_state.players_cards_count.get(seat).map(|(id, _, _)| *id)
+}
+
+fn handle_key_host_menu(
+    key: KeyEvent,
+    app: &mut AppState,
+    tx: &Sender<Client2Server>,
+) -> io::Result<()> {
+    let n = app.game_state.players_cards_count.len().max(1);
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('h') => app.mode = UiMode::Normal,
+        KeyCode::Up => app.host_pick_index = (app.host_pick_index + n - 1) % n,
+        KeyCode::Down => app.host_pick_index = (app.host_pick_index + 1) % n,
+        KeyCode::Char('k') => {
+            if let Some(player_id) = seat_player(app, app.host_pick_index) {
+                tx.send(Client2Server::KickPlayer { player_id }).ok();
+            }
+        }
+        KeyCode::Char('t') => {
+            if let Some(player_id) = seat_player(app, app.host_pick_index) {
+                tx.send(Client2Server::TransferHost { player_id }).ok();
+            }
+            app.mode = UiMode::Normal;
+        }
+        KeyCode::Char('l') => {
+            tx.send(Client2Server::LockRoom { locked: !app.room_locked }).ok();
+        }
+        KeyCode::Char(c @ '1'..='6') => {
+            let mut rules = app.game_state.rules.clone();
+            let flag = rule_flag(&mut rules, c as usize - '1' as usize);
+            *flag = !*flag;
+            tx.send(Client2Server::SetRules { rules }).ok();
+        }
+        _ => {}
+    }
+    Ok(())
+}
+fn draw_host_menu_popup(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState) {
+    let popup = centered_rect(50, 60, area);
+    let mut lines = vec![Line::from("↑/↓ 选择玩家, K 踢出, T 转让房主, L 锁定/解锁, Esc 关闭")];
+    for (i, (pid, name, _)) in app.game_state.players_cards_count.iter().enumerate() {
+        let style = if i == app.host_pick_index {
+            Style::default()
+                .fg(TColor::Yellow)
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED)
+        } else {
+            Style::default().fg(TColor::White)
+        };
+        let host = if Some(*pid) == app.host { " ★" } else { "" };
+        lines.push(Line::from(Span::styled(format!(" {}: {}{} ", pid, name, host), style)));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(format!("房间: {}", if app.room_locked { "已锁定" } else { "开放" })));
+    lines.push(Line::from("房规 (数字键切换, 仅限比赛开始前):"));
+    let mut rules = app.game_state.rules.clone();
+    for (i, label) in HOST_RULE_TOGGLES.iter().enumerate() {
+        let on = *rule_flag(&mut rules, i);
+        lines.push(Line::from(format!(" {} [{}] {}", i + 1, if on { "x" } else { " " }, label)));
+    }
+    let block = Paragraph::new(Text::from(lines))
+        .block(Block::default().borders(Borders::ALL).title("房主菜单"));
+    f.render_widget(block, popup);
+}
+// 连接回到大厅, 清掉房间里的状态
+fn leave_room(app: &mut AppState) {
+    app.game_state = GameState::default();
+    app.room_id = None;
+    app.host = None;
+    app.turn_deadline = None;
+    app.scoreboard = None;
+    app.mode = UiMode::Normal;
+}
+fn open_lobby(app: &mut AppState, tx: &Sender<Client2Server>, join_as: JoinAs) {
+    app.join_as = join_as;
+    app.mode = UiMode::Lobby;
+    app.room_input.clear();
+    app.name_input.clear();
+    app.password_input.clear();
+    app.max_players_input.clear();
+    // 登录后用账号名入座, 服务器也会忽略别的昵称
+    if let Some(account) = &app.account {
+        app.name_input = account.clone();
+    }
+    app.input_focus = InputFocus::Room;
+    app.lobby_cursor = 0;
+    tx.send(Client2Server::ListRooms).ok();
+    app.push_log("↑/↓ 选择房间或输入新房间名，Tab 切换，Enter 提交，F5 刷新，Esc 取消");
+}
+fn handle_key_lobby(key: KeyEvent, app: &mut AppState, tx: &Sender<Client2Server>) -> io::Result<()> {
     match key.code {
         KeyCode::Esc => { app.mode = UiMode::Normal; }
-        KeyCode::Tab => { app.input_focus = match app.input_focus { InputFocus::Room => InputFocus::Name, InputFocus::Name => InputFocus::Room }; }
+        KeyCode::F(5) => { tx.send(Client2Server::ListRooms).ok(); }
+        KeyCode::Up | KeyCode::Down if !app.lobby.is_empty() => {
+            let n = app.lobby.len();
+            app.lobby_cursor = if key.code == KeyCode::Up { (app.lobby_cursor + n - 1) % n } else { (app.lobby_cursor + 1) % n };
+            app.room_input = app.lobby[app.lobby_cursor].room_id.clone();
+        }
+        KeyCode::Tab => {
+            app.input_focus = match app.input_focus {
+                InputFocus::Room => InputFocus::Name,
+                InputFocus::Name => InputFocus::Password,
+                InputFocus::Password => InputFocus::MaxPlayers,
+                InputFocus::MaxPlayers => InputFocus::Room,
+            };
+        }
         KeyCode::Enter => {
             if app.room_input.trim().is_empty() { app.push_log("房间ID不能为空"); }
             else if app.name_input.trim().is_empty() { app.push_log("昵称不能为空"); }
             else {
                 let room_id = app.room_input.trim().to_string();
                 let name = app.name_input.trim().to_string();
-                tx.send(Client2Server::JoinGame { room_id: room_id.clone(), name: name.clone() }).ok();
+                let password = Some(app.password_input.clone()).filter(|p| !p.is_empty());
+                let exists = app.lobby.iter().any(|r| r.room_id == room_id);
                 app.room_id = Some(room_id.clone());
-                app.push_log(format!("发送 JoinGame room={} name={}", room_id, name));
                 app.mode = UiMode::Normal;
-                app.input_hint = vec![Line::from("S 开局"), Line::from("↑/↓ 选牌 ...")];
+                if let JoinAs::Spectator { god_view } = app.join_as {
+                    tx.send(Client2Server::Spectate { room_id: room_id.clone(), name: name.clone(), god_view, referee_key: None, password }).ok();
+                    app.push_log(format!("发送 Spectate room={} name={}", room_id, name));
+                    return Ok(());
+                }
+                if exists {
+                    tx.send(Client2Server::JoinGame { room_id: room_id.clone(), name: name.clone(), seed: None, rules: None, password }).ok();
+                    app.push_log(format!("发送 JoinGame room={} name={}", room_id, name));
+                } else {
+                    let max_players = app.max_players_input.parse().unwrap_or(MAX_PLAYERS);
+                    tx.send(Client2Server::CreateRoom { room_id: room_id.clone(), name: name.clone(), max_players, password, rules: None }).ok();
+                    app.push_log(format!("发送 CreateRoom room={} name={} max_players={}", room_id, name, max_players));
+                }
+                app.input_hint = vec![Line::from("S 开局"), Line::from("B/Shift+B 加电脑"), Line::from("↑/↓ 选牌 ...")];
             }
         }
         KeyCode::Backspace => {
-            match app.input_focus { InputFocus::Room => { app.room_input.pop(); } InputFocus::Name => { app.name_input.pop(); } }
-        }
-        KeyCode::Left => {}
-        KeyCode::Right => {}
-        KeyCode::Char(c) => {
-            if !c.is_control() {
-                match app.input_focus {
-                    InputFocus::Room => if app.room_input.len() < 24 { app.room_input.push(c); },
-                    InputFocus::Name => if app.name_input.len() < 24 { app.name_input.push(c); },
-                }
+            match app.input_focus {
+                InputFocus::Room => { app.room_input.pop(); }
+                InputFocus::Name => { app.name_input.pop(); }
+                InputFocus::Password => { app.password_input.pop(); }
+                InputFocus::MaxPlayers => { app.max_players_input.pop(); }
+            }
+        }
+        KeyCode::Char(c) if !c.is_control() => {
+            match app.input_focus {
+                InputFocus::Room => if app.room_input.len() < 24 { app.room_input.push(c); },
+                InputFocus::Name => if app.name_input.len() < 24 { app.name_input.push(c); },
+                InputFocus::Password => if app.password_input.len() < 24 { app.password_input.push(c); },
+                InputFocus::MaxPlayers => if c.is_ascii_digit() && app.max_players_input.len() < 2 { app.max_players_input.push(c); },
             }
         }
         _ => {}
     }
     Ok(())
 }
-fn draw_name_input_popup(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState) {
-    let popup = centered_rect(60, 40, area);
+fn draw_lobby_popup(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState) {
+    let popup = centered_rect(70, 60, area);
     let mut lines: Vec<Line> = Vec::new();
-    lines.push(Line::from("输入房间与昵称 (Tab 切换, Enter 确认 / Esc 取消)"));
+    lines.push(Line::from("房间列表 (↑/↓ 选择, F5 刷新)"));
+    if app.lobby.is_empty() {
+        lines.push(Line::from("  <暂无房间, 输入房间名新建>"));
+    }
+    for (i, r) in app.lobby.iter().enumerate() {
+        let text = format!(
+            "{} {:<16} {:>2}/{:<2} {} {}{}",
+            if i == app.lobby_cursor { ">" } else { " " },
+            r.room_id,
+            r.players,
+            r.max_players,
+            if r.started { "进行中" } else { "等待中" },
+            if r.rules == RuleSet::default() { "官方规则" } else { "房规" },
+            if r.has_password { " [密码]" } else { "" },
+        );
+        let style = if i == app.lobby_cursor { Style::default().fg(TColor::Yellow) } else { Style::default() };
+        lines.push(Line::from(Span::styled(text, style)));
+    }
     lines.push(Line::from(""));
-    let room_style = if matches!(app.input_focus, InputFocus::Room) { Style::default().fg(TColor::Yellow).add_modifier(Modifier::BOLD | Modifier::UNDERLINED) } else { Style::default().fg(TColor::White) };
-    let name_style = if matches!(app.input_focus, InputFocus::Name) { Style::default().fg(TColor::Yellow).add_modifier(Modifier::BOLD | Modifier::UNDERLINED) } else { Style::default().fg(TColor::White) };
-    lines.push(Line::from(vec![Span::styled("房间: ", Style::default().fg(TColor::Cyan)), Span::styled(if app.room_input.is_empty() { "<空>".into() } else { app.room_input.clone() }, room_style)]));
-    lines.push(Line::from(vec![Span::styled("昵称: ", Style::default().fg(TColor::Cyan)), Span::styled(if app.name_input.is_empty() { "<空>".into() } else { app.name_input.clone() }, name_style)]));
+    let field = |label: &'static str, value: &str, focus: InputFocus, placeholder: &'static str| {
+        let style = if app.input_focus == focus { Style::default().fg(TColor::Yellow).add_modifier(Modifier::BOLD | Modifier::UNDERLINED) } else { Style::default().fg(TColor::White) };
+        Line::from(vec![Span::styled(label, Style::default().fg(TColor::Cyan)), Span::styled(if value.is_empty() { placeholder.to_string() } else { value.to_string() }, style)])
+    };
+    let masked = "*".repeat(app.password_input.chars().count());
+    lines.push(field("房间: ", &app.room_input, InputFocus::Room, "<空>"));
+    lines.push(field("昵称: ", &app.name_input, InputFocus::Name, "<空>"));
+    lines.push(field("密码: ", &masked, InputFocus::Password, "<无>"));
+    lines.push(field("人数上限(新建): ", &app.max_players_input, InputFocus::MaxPlayers, "<10>"));
     if let Some(r) = &app.room_id { lines.push(Line::from(format!("已加入房间: {}", r))); }
-    let block = Paragraph::new(Text::from(lines)).block(Block::default().borders(Borders::ALL).title("加入游戏"));
+    let title = if app.join_as == JoinAs::Player { "大厅 - 加入/新建 (Tab 切换, Enter 确认 / Esc 取消)" } else { "大厅 - 观战 (Tab 切换, Enter 确认 / Esc 取消)" };
+    let block = Paragraph::new(Text::from(lines)).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(block, popup);
+}
+// Tab 切换账号名/密码, Enter 登录, F2 注册新账号
+fn handle_key_login(key: KeyEvent, app: &mut AppState, tx: &Sender<Client2Server>) -> io::Result<()> {
+    match key.code {
+        KeyCode::Esc => app.mode = UiMode::Normal,
+        KeyCode::Tab => {
+            app.input_focus = if app.input_focus == InputFocus::Name { InputFocus::Password } else { InputFocus::Name };
+        }
+        KeyCode::Enter | KeyCode::F(2) => {
+            if app.login_name.trim().is_empty() || app.login_password.is_empty() {
+                app.push_log("账号名和密码都不能为空");
+                return Ok(());
+            }
+            let register = key.code == KeyCode::F(2);
+            tx.send(Client2Server::Login {
+                name: app.login_name.trim().to_string(),
+                password: app.login_password.clone(),
+                register,
+            })
+            .ok();
+            app.push_log(if register { "正在注册..." } else { "正在登录..." });
+        }
+        KeyCode::Backspace => {
+            if app.input_focus == InputFocus::Name {
+                app.login_name.pop();
+            } else {
+                app.login_password.pop();
+            }
+        }
+        KeyCode::Char(c) if !c.is_control() => {
+            let input = if app.input_focus == InputFocus::Name { &mut app.login_name } else { &mut app.login_password };
+            if input.chars().count() < 24 {
+                input.push(c);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+fn draw_login_popup(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState) {
+    let popup = centered_rect(50, 30, area);
+    let field = |label: &'static str, value: String, focus: InputFocus| {
+        let style = if app.input_focus == focus { Style::default().fg(TColor::Yellow).add_modifier(Modifier::BOLD | Modifier::UNDERLINED) } else { Style::default().fg(TColor::White) };
+        Line::from(vec![Span::styled(label, Style::default().fg(TColor::Cyan)), Span::styled(if value.is_empty() { "<空>".to_string() } else { value }, style)])
+    };
+    let mut lines = vec![
+        field("账号: ", app.login_name.clone(), InputFocus::Name),
+        field("密码: ", "*".repeat(app.login_password.chars().count()), InputFocus::Password),
+        Line::from(""),
+    ];
+    if let Some(account) = &app.account {
+        lines.push(Line::from(format!("当前账号: {}", account)));
+    }
+    let block = Paragraph::new(Text::from(lines))
+        .block(Block::default().borders(Borders::ALL).title("账号 (Tab 切换, Enter 登录, F2 注册, Esc 取消)"));
+    f.render_widget(block, popup);
+}
+// 战绩弹窗可以查看的名字: 自己在前, 然后是同房间的其他玩家
+fn profile_names(app: &AppState) -> Vec<String> {
+    let me = app.account.clone().or_else(|| Some(app.name_input.trim().to_string()).filter(|n| !n.is_empty()));
+    let mut names: Vec<String> = me.into_iter().collect();
+    for (_, name, _) in &app.game_state.players_cards_count {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+    names
+}
+fn handle_key_profile(key: KeyEvent, app: &mut AppState, tx: &Sender<Client2Server>) -> io::Result<()> {
+    let names = profile_names(app);
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('i') => app.mode = UiMode::Normal,
+        KeyCode::Up | KeyCode::Down if !names.is_empty() => {
+            let n = names.len();
+            app.profile_pick = if key.code == KeyCode::Up { (app.profile_pick + n - 1) % n } else { (app.profile_pick + 1) % n };
+            tx.send(Client2Server::GetStats { name: names[app.profile_pick].clone() }).ok();
+        }
+        _ => {}
+    }
+    Ok(())
+}
+fn draw_profile_popup(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState) {
+    let popup = centered_rect(50, 40, area);
+    let mut lines = Vec::new();
+    match &app.profile {
+        None => lines.push(Line::from("查询中...")),
+        Some((name, None)) => lines.push(Line::from(format!("{} 不是注册玩家, 没有战绩", name))),
+        Some((name, Some(stats))) => {
+            let win_rate = if stats.games_played == 0 { 0.0 } else { stats.wins as f64 * 100.0 / stats.games_played as f64 };
+            lines.push(Line::from(Span::styled(name.clone(), Style::default().fg(TColor::Yellow).add_modifier(Modifier::BOLD))));
+            lines.push(Line::from(""));
+            lines.push(Line::from(format!("对局数:     {}", stats.games_played)));
+            lines.push(Line::from(format!("胜场:       {} ({:.0}%)", stats.wins, win_rate)));
+            lines.push(Line::from(format!("平均剩牌分: {:.1}", stats.average_penalty())));
+            lines.push(Line::from(format!("UNO 罚牌:   {}", stats.uno_penalties)));
+            lines.push(Line::from(format!("打出 +4:    {}", stats.draw_fours_played)));
+        }
+    }
+    let block = Paragraph::new(Text::from(lines))
+        .block(Block::default().borders(Borders::ALL).title("战绩 (↑/↓ 切换玩家, Esc 关闭)"));
     f.render_widget(block, popup);
 }
 fn handle_key_scoreboard(
@@ -685,7 +1477,16 @@ fn draw_scoreboard_popup(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState)
     if let Some(entries) = &app.scoreboard {
         let popup = centered_rect(60, 60, area);
         let mut lines: Vec<Line> = Vec::new();
-        lines.push(Line::from("本局结果 (Enter/Esc 关闭, N 再来一局)"));
+        match &app.match_winner {
+            Some(name) => lines.push(Line::from(format!(
+                "比赛结束, {} 获胜! (Enter/Esc 关闭, N 开始新比赛)",
+                name
+            ))),
+            None => lines.push(Line::from(format!(
+                "第 {} 局结果 (Enter/Esc 关闭, N 下一局)",
+                app.round
+            ))),
+        }
         lines.push(Line::from(""));
         for e in entries {
             let style = if e.is_winner {
@@ -702,9 +1503,13 @@ fn draw_scoreboard_popup(f: &mut ratatui::Frame<'_>, area: Rect, app: &AppState)
                 ),
                 Span::styled(format!("{:<12}", e.name), style),
                 Span::styled(
-                    format!(" 分数: {:>4}", e.score),
+                    format!(" 剩牌: {:>4}", e.score),
                     Style::default().fg(TColor::Cyan),
                 ),
+                Span::styled(
+                    format!(" 累计: {:>4}", e.total),
+                    Style::default().fg(TColor::Yellow),
+                ),
                 Span::raw(if e.is_winner { "  <- WIN" } else { "" }),
             ]);
             lines.push(line);
@@ -738,10 +1543,12 @@ fn centered_rect(pct_x: u16, pct_y: u16, r: Rect) -> Rect {
 fn handle_events(app: &mut AppState, events: &[GE], _tx: &Sender<Client2Server>) {
     for e in events {
         match e {
+            // 牌局状态由后面的 PlayerJoined / GameStarted 等事件给出
+            GE::GameCreated { .. } => {}
             GE::PlayerJoined { player_id, name } => {
                 app.push_log(format!("Player {} joined: {}", player_id, name))
             }
-            GE::GameStarted { game_id } => {
+            GE::GameStarted { game_id, .. } => {
                 app.push_log(format!("Game started: {}", game_id));
                 app.mode = UiMode::Normal;
                 app.input_hint = vec![
@@ -753,27 +1560,32 @@ fn handle_events(app: &mut AppState, events: &[GE], _tx: &Sender<Client2Server>)
                     Line::from("Q 退出"),
                 ];
             }
-            GE::CardPlayed { player_id, card } => {
-                app.push_log(format!("Player {} played {}", player_id, card.to_string()))
+            GE::CardPlayed { player_id, card, .. } => {
+                app.push_log(format!("Player {} played {}", player_id, card))
+            }
+            GE::GameError { error } => {
+                app.push_log(format!("Error: {}", error_text((*error).into(), &error.to_string())))
             }
-            GE::GameError { message } => app.push_log(format!("Error: {}", message)),
             GE::CardDraw { player_id, card } => {
                 if Some(*player_id) == app.game_state.player_id {
-                    app.push_log(format!("You drew: {}", card.to_string()));
+                    app.push_log(format!("You drew: {}", card));
                 } else {
                     app.push_log(format!("Player {} drew a card", player_id));
                 }
             }
+            GE::CardsDrawn { player_id, count } => {
+                app.push_log(format!("Player {} drew {} card(s)", player_id, count))
+            }
             GE::DrawnCardPlayable { player_id } => {
-                if Some(*player_id) == app.game_state.player_id {
-                    if !app.game_state.hand.is_empty() {
-                        let idx = app.game_state.hand.len();
-                        // 由于服务端先发DrawnCardPlayable,再发PlayerState,所以此时手牌数已经+1,
-                        // 而且TUI渲染出来隔了大约0.1s,
-                        // 正常用户的手速也不会快到在这之间出牌引发panic...
-                        app.mode = UiMode::DrawnCardPlayable { card_index: idx };
-                        app.push_log("你刚摸的牌可立即出");
-                    }
+                if Some(*player_id) == app.game_state.player_id
+                    && !app.game_state.hand.is_empty()
+                {
+                    let idx = app.game_state.hand.len();
+                    // 由于服务端先发DrawnCardPlayable,再发PlayerState,所以此时手牌数已经+1,
+                    // 而且TUI渲染出来隔了大约0.1s,
+                    // 正常用户的手速也不会快到在这之间出牌引发panic...
+                    app.mode = UiMode::DrawnCardPlayable { card_index: idx };
+                    app.push_log("你刚摸的牌可立即出");
                 }
             }
             GE::DirectionChanged { clockwise } => app.push_log(format!(
@@ -786,7 +1598,79 @@ fn handle_events(app: &mut AppState, events: &[GE], _tx: &Sender<Client2Server>)
             GE::DrawTwoApplied { target_player_id } => {
                 app.push_log(format!("+2 -> Player {}", target_player_id))
             }
+            GE::DrawStacked {
+                target_player_id,
+                total,
+            } => app.push_log(format!(
+                "罚牌叠加到 {} 张 -> Player {}",
+                total, target_player_id
+            )),
+            GE::DrawStackTaken { player_id, count } => {
+                app.push_log(format!("Player {} 接下了 {} 张罚牌", player_id, count))
+            }
+            GE::ColorChoiceRequired { player_id } => {
+                if Some(*player_id) == app.game_state.player_id {
+                    app.pending_action = None;
+                    app.color_pick_index = 0;
+                    app.mode = UiMode::ColorPick;
+                    app.push_log("第一张顶牌是万能牌, 请选择颜色");
+                } else {
+                    app.push_log(format!("等待 Player {} 为第一张牌选颜色", player_id));
+                }
+            }
+            GE::ColorChosen { player_id, color } => {
+                app.push_log(format!("Player {} chose {:?}", player_id, color))
+            }
+            GE::PlayerAfk { player_id } => {
+                if Some(*player_id) == app.game_state.player_id {
+                    app.push_log("你连续超时, 已由电脑代打, 任意操作即可收回");
+                } else {
+                    app.push_log(format!("Player {} 挂机, 由电脑代打", player_id));
+                }
+            }
+            GE::PlayerReturned { player_id } => {
+                app.push_log(format!("Player {} 回来了", player_id))
+            }
+            GE::PlayerLeft { player_id } => {
+                app.push_log(format!("Player {} 离开了牌局", player_id))
+            }
+            GE::HostChanged { player_id } => {
+                app.host = Some(*player_id);
+                if Some(*player_id) == app.game_state.player_id {
+                    app.push_log("你现在是房主, 按 H 打开房主菜单");
+                } else {
+                    app.push_log(format!("Player {} 成为房主", player_id));
+                }
+            }
+            GE::PlayerKicked { player_id } if Some(*player_id) == app.game_state.player_id => {
+                leave_room(app);
+                app.push_log("你被房主踢出了房间, 按 J 重新加入");
+            }
+            GE::PlayerKicked { player_id } => {
+                app.push_log(format!("Player {} 被房主踢出", player_id))
+            }
+            GE::RoomLocked { locked } => {
+                app.room_locked = *locked;
+                app.push_log(if *locked { "房间已锁定, 不再接受新玩家" } else { "房间已解锁" });
+            }
+            GE::RulesChanged { rules } => {
+                app.game_state.rules = rules.clone();
+                app.push_log(format!("房规已修改: {:?}", rules));
+            }
+            GE::JumpedIn { player_id } => app.push_log(format!("Player {} 抢出!", player_id)),
+            GE::HandsSwapped {
+                player_id,
+                other_id,
+            } => app.push_log(format!(
+                "Player {} 和 Player {} 交换了手牌",
+                player_id, other_id
+            )),
+            GE::HandsRotated { clockwise } => app.push_log(format!(
+                "所有人按{}传递手牌",
+                if *clockwise { "顺时针" } else { "逆时针" }
+            )),
             GE::GameOver { winner, scores } => {
+                app.turn_deadline = None;
                 app.push_log(format!("Game over! Winner {} scores {:?}", winner, scores));
                 // 构建比分表：UNO 规则中分数越低（负分绝对值越小）谁赢？假设 winner 已经由服务器判断
                 let mut entries: Vec<ScoreEntry> = scores
@@ -795,6 +1679,7 @@ fn handle_events(app: &mut AppState, events: &[GE], _tx: &Sender<Client2Server>)
                     .map(|(i, (name, sc))| ScoreEntry {
                         name: name.clone(),
                         score: *sc,
+                        total: 0,
                         rank: 0,
                         is_winner: i == 0, // 服务端已经排好序,第一个就是赢家
                     })
@@ -808,6 +1693,65 @@ fn handle_events(app: &mut AppState, events: &[GE], _tx: &Sender<Client2Server>)
                 app.scoreboard = Some(entries);
                 app.mode = UiMode::Scoreboard;
             }
+            GE::RoundOver {
+                round,
+                winner,
+                points,
+                totals,
+            } => {
+                app.round = *round;
+                app.match_winner = None;
+                app.push_log(format!("第 {} 局结束, Player {} 得 {} 分", round, winner, points));
+                // 用累计分数重新排名, 累计分高的在前
+                let winner_name = player_name(app, *winner);
+                let mut entries: Vec<ScoreEntry> = totals
+                    .iter()
+                    .map(|(name, total)| ScoreEntry {
+                        name: name.clone(),
+                        score: app
+                            .scoreboard
+                            .iter()
+                            .flatten()
+                            .find(|e| &e.name == name)
+                            .map_or(0, |e| e.score),
+                        total: *total,
+                        rank: 0,
+                        is_winner: winner_name.as_ref() == Some(name),
+                    })
+                    .collect();
+                entries.sort_by_key(|e| std::cmp::Reverse(e.total));
+                for (idx, e) in entries.iter_mut().enumerate() {
+                    e.rank = idx + 1;
+                }
+                app.scoreboard = Some(entries);
+                app.mode = UiMode::Scoreboard;
+            }
+            GE::MatchOver { winner, totals } => {
+                let name = player_name(app, *winner)
+                    .filter(|n| totals.iter().any(|(t, _)| t == n))
+                    .unwrap_or_else(|| format!("Player {}", winner));
+                app.push_log(format!("比赛结束! {} 获胜", name));
+                app.match_winner = Some(name);
+            }
+            GE::ChallengeWindow {
+                challenger_id,
+                challenged_id,
+            } => {
+                if Some(*challenger_id) == app.game_state.player_id {
+                    app.mode = UiMode::ChallengePrompt {
+                        challenged_id: *challenged_id,
+                    };
+                    app.push_log(format!(
+                        "Player {} 对你打出 +4: C 质疑 / A 接受",
+                        challenged_id
+                    ));
+                } else {
+                    app.push_log(format!(
+                        "Player {} may challenge the +4 from {}",
+                        challenger_id, challenged_id
+                    ));
+                }
+            }
             GE::PlayerChallenged {
                 challenger_id,
                 challenged_id,
@@ -824,6 +1768,12 @@ fn handle_events(app: &mut AppState, events: &[GE], _tx: &Sender<Client2Server>)
                 app.game_state.top_card = Some(*top_card);
                 app.push_log("Top card changed");
             }
+            GE::FirstCardRedrawn { card } => {
+                app.push_log(format!("首张 {} 放回重洗", card))
+            }
+            GE::DeckReshuffled { deck_size } => {
+                app.push_log(format!("牌堆已用完, 弃牌重新洗入 ({} 张)", deck_size))
+            }
             GE::UnoCalled { player_id } => app.push_log(format!("Player {} UNO!", player_id)),
             GE::UnoPenalty { player_id } => {
                 app.push_log(format!("UNO penalty -> Player {}", player_id))
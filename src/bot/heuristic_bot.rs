@@ -0,0 +1,43 @@
+use crate::bot::strategy::{BotAction, BotView, Strategy};
+use crate::game::cards::UnoCard;
+
+// 简单的启发式:
+// 1. 万能牌留到最后, 没有别的牌可出时才用
+// 2. 优先打掉分值高的牌; 下家快出完时优先用功能牌卡住他
+// 3. 万能牌选手里最多的颜色
+pub struct HeuristicBot;
+
+impl HeuristicBot {
+    fn priority(card: &UnoCard, next_is_close: bool) -> i32 {
+        match card {
+            UnoCard::WildCard(_, _) => -1,
+            UnoCard::ActionCard(_, _) if next_is_close => 100,
+            _ => card.get_value(),
+        }
+    }
+}
+
+impl Strategy for HeuristicBot {
+    fn decide(&mut self, view: &BotView) -> BotAction {
+        let next_is_close = view.opponents_cards_count.first().is_some_and(|&n| n <= 2);
+        let best = match view.drawn_card {
+            Some(idx) => Some(idx),
+            None => view
+                .hand
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| view.can_play(c))
+                .max_by_key(|(_, c)| Self::priority(c, next_is_close))
+                .map(|(i, _)| i),
+        };
+        match best {
+            Some(card_index) => BotAction::Play {
+                card_index,
+                color: view.dominant_color(Some(card_index)),
+                call_uno: view.hand.len() == 2,
+            },
+            None if view.drawn_card.is_some() => BotAction::Pass,
+            None => BotAction::Draw,
+        }
+    }
+}
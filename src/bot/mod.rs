@@ -0,0 +1,6 @@
+pub mod strategy;
+pub mod random_bot;
+pub mod heuristic_bot;
+pub use strategy::*;
+pub use random_bot::RandomBot;
+pub use heuristic_bot::HeuristicBot;
@@ -0,0 +1,65 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::bot::strategy::{BotAction, BotView, Strategy};
+use crate::game::cards::Color;
+
+// 随机出一张合法的牌, 没有就摸牌
+pub struct RandomBot {
+    rng: StdRng,
+}
+
+impl Default for RandomBot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RandomBot {
+    pub fn new() -> RandomBot {
+        Self::with_seed(rand::thread_rng().gen())
+    }
+
+    pub fn with_seed(seed: u64) -> RandomBot {
+        RandomBot {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn random_color(&mut self) -> Color {
+        Color::from_u8(self.rng.gen_range(0..4)).unwrap_or(Color::RED)
+    }
+}
+
+impl Strategy for RandomBot {
+    fn decide(&mut self, view: &BotView) -> BotAction {
+        let candidates: Vec<usize> = match view.drawn_card {
+            Some(idx) => vec![idx],
+            None => view
+                .hand
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| view.can_play(c))
+                .map(|(i, _)| i)
+                .collect(),
+        };
+        match candidates.choose(&mut self.rng) {
+            Some(&card_index) => BotAction::Play {
+                card_index,
+                color: self.random_color(),
+                call_uno: view.hand.len() == 2,
+            },
+            None if view.drawn_card.is_some() => BotAction::Pass,
+            None => BotAction::Draw,
+        }
+    }
+
+    fn challenge_draw_four(&mut self, _view: &BotView) -> bool {
+        self.rng.gen_bool(0.5)
+    }
+
+    fn choose_color(&mut self, _view: &BotView) -> Color {
+        self.random_color()
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Serialize, Deserialize};
+
+use crate::bot::{HeuristicBot, RandomBot};
+use crate::game::cards::{can_stack_draw, valid_card, Color, UnoCard};
+use crate::game::{PlayerId, UnoGame};
+
+// 机器人在自己回合能做的决定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BotAction {
+    Play {
+        card_index: usize,
+        color: Color, // 只对万能牌有意义
+        call_uno: bool,
+    },
+    Draw,
+    Pass,
+}
+
+// 机器人能看到的信息, 不包含对手的手牌
+#[derive(Clone)]
+pub struct BotView {
+    pub hand: Vec<UnoCard>,
+    pub top_card: Option<UnoCard>,
+    pub opponents_cards_count: Vec<usize>, // 从下家开始, 按出牌顺序
+    pub drawn_card: Option<usize>, // 刚摸到的可出牌, 此时只能出这张或者跳过
+    pub stacked_draws: usize, // 大于 0 时只能继续叠罚牌或者摸牌
+}
+
+impl BotView {
+    pub fn from_game(game: &UnoGame, player_id: PlayerId) -> BotView {
+        // 按座位顺序, 从自己的下家开始数
+        let seats: Vec<(PlayerId, usize)> = game
+            .get_players_cards_count()
+            .into_iter()
+            .map(|(id, _, n)| (id, n))
+            .collect();
+        let n = seats.len();
+        let me = seats.iter().position(|&(id, _)| id == player_id).unwrap_or(0);
+        let opponents_cards_count = (1..n)
+            .map(|k| {
+                if game.direction {
+                    seats[(me + k) % n]
+                } else {
+                    seats[(me + n - k) % n]
+                }
+            })
+            .filter(|&(id, _)| !game.has_left(id))
+            .map(|(_, count)| count)
+            .collect();
+        let drawn_card = if game.current_player() == player_id {
+            game.drawn_card_playable()
+        } else {
+            None
+        };
+        BotView {
+            hand: game.get_player_hand(player_id),
+            top_card: game.top_card,
+            opponents_cards_count,
+            drawn_card,
+            stacked_draws: game.stacked_draws(),
+        }
+    }
+
+    // 手里最多的颜色, except 为准备打出的那张牌
+    pub fn dominant_color(&self, except: Option<usize>) -> Color {
+        let mut counts = [0usize; 4];
+        for (i, card) in self.hand.iter().enumerate() {
+            if Some(i) == except {
+                continue;
+            }
+            if let Ok(c) = card.get_color() {
+                counts[c.to_u8() as usize] += 1;
+            }
+        }
+        let best = (0..4).max_by_key(|&i| (counts[i], 4 - i)).unwrap_or(0);
+        Color::from_u8(best as u8).unwrap_or(Color::RED)
+    }
+
+    pub fn can_play(&self, card: &UnoCard) -> bool {
+        valid_card(card, &self.top_card)
+            && (self.stacked_draws == 0 || can_stack_draw(card, &self.top_card))
+    }
+}
+
+pub trait Strategy: Send {
+    fn decide(&mut self, view: &BotView) -> BotAction;
+
+    // 被 +4 时是否质疑, 默认直接接受
+    fn challenge_draw_four(&mut self, _view: &BotView) -> bool {
+        false
+    }
+
+    // 为第一张万能顶牌选颜色, 默认选手里最多的颜色
+    fn choose_color(&mut self, view: &BotView) -> Color {
+        view.dominant_color(None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BotDifficulty {
+    Easy,
+    Hard,
+}
+
+pub fn new_strategy(difficulty: BotDifficulty) -> Box<dyn Strategy> {
+    match difficulty {
+        BotDifficulty::Easy => Box::new(RandomBot::new()),
+        BotDifficulty::Hard => Box::new(HeuristicBot),
+    }
+}
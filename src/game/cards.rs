@@ -28,6 +28,14 @@ impl UnoCard {
         }
     }
 
+    // 万能牌回到牌堆时要清掉之前选的颜色
+    pub fn without_chosen_color(self) -> UnoCard {
+        match self {
+            UnoCard::WildCard(_, wild_type) => UnoCard::WildCard(None, wild_type),
+            _ => self,
+        }
+    }
+
     pub fn get_value(&self) -> i32 {
         // 计算剩余牌所代表的分数作为自己的负分
         // 0-9数字牌计0-9分，功能牌计20分，万能牌计50分
@@ -46,17 +54,17 @@ impl Display for UnoCard {
             UnoCard::NumberCard(color, number) => write!(
                 f,
                 "Number Card: {} {}",
-                color.to_string(),
-                number.to_string()
+                color,
+                number
             )?,
             UnoCard::ActionCard(color, action) => {
-                write!(f, "Action Card: {} {}", color.to_string(), action.to_string())?
+                write!(f, "Action Card: {} {}", color, action)?
             }
             UnoCard::WildCard(color, wild_type) => {
                 if let Some(c) = color {
-                    write!(f, "  Wild Card: {} {}", c.to_string(), wild_type.to_string())?
+                    write!(f, "  Wild Card: {} {}", c, wild_type)?
                 } else {
-                    write!(f, "  Wild Card: {}", wild_type.to_string())?
+                    write!(f, "  Wild Card: {}", wild_type)?
                 }
             }
         }
@@ -80,13 +88,26 @@ pub fn valid_card(card: &UnoCard, top_card: &Option<UnoCard>) -> bool {
     false
 }
 
-fn is_wild_card(card: &UnoCard) -> bool {
+// 叠加罚牌: +2 只能叠在 +2 上, +4 可以叠在 +2/+4 上
+pub fn can_stack_draw(card: &UnoCard, top_card: &Option<UnoCard>) -> bool {
     match card {
-        UnoCard::WildCard(_, _) => true,
+        UnoCard::WildCard(_, WildType::DRAWFOUR) => true,
+        UnoCard::ActionCard(_, Action::DRAWTWO) => {
+            matches!(top_card, Some(UnoCard::ActionCard(_, Action::DRAWTWO)))
+        }
         _ => false,
     }
 }
 
+// 抢出: 颜色和牌面都与顶牌相同, 万能牌不算
+pub fn identical_card(card: &UnoCard, top_card: &Option<UnoCard>) -> bool {
+    !is_wild_card(card) && Some(*card) == *top_card
+}
+
+fn is_wild_card(card: &UnoCard) -> bool {
+    matches!(card, UnoCard::WildCard(_, _))
+}
+
 fn same_color(card: &UnoCard, top_card: &UnoCard) -> bool {
     if card.get_color().ok() == top_card.get_color().ok() {
         return true;
@@ -97,7 +118,7 @@ fn same_color(card: &UnoCard, top_card: &UnoCard) -> bool {
 fn same_number(card: &UnoCard, top_card: &UnoCard) -> bool {
     let card_num = card.get_number();
     let top_card_num = top_card.get_number();
-    if card_num == None || top_card_num == None || card.get_number() != top_card.get_number() {
+    if card_num.is_none() || top_card_num.is_none() || card.get_number() != top_card.get_number() {
         return false;
     }
     true
@@ -215,16 +236,16 @@ impl Display for Number {
             // Number::SEVEN => write!(f, "{}", "7".blue())?,
             // Number::EIGHT => write!(f, "{}", "8".green())?,
             // Number::NINE => write!(f, "{}", "9".red())?,
-            Number::ZERO => write!(f, "{}", "0")?,
-            Number::ONE => write!(f, "{}", "1")?,
-            Number::TWO => write!(f, "{}", "2")?,
-            Number::THREE => write!(f, "{}", "3")?,
-            Number::FOUR => write!(f, "{}", "4")?,
-            Number::FIVE => write!(f, "{}", "5")?,
-            Number::SIX => write!(f, "{}", "6")?,
-            Number::SEVEN => write!(f, "{}", "7")?,
-            Number::EIGHT => write!(f, "{}", "8")?,
-            Number::NINE => write!(f, "{}", "9")?,
+            Number::ZERO => write!(f, "0")?,
+            Number::ONE => write!(f, "1")?,
+            Number::TWO => write!(f, "2")?,
+            Number::THREE => write!(f, "3")?,
+            Number::FOUR => write!(f, "4")?,
+            Number::FIVE => write!(f, "5")?,
+            Number::SIX => write!(f, "6")?,
+            Number::SEVEN => write!(f, "7")?,
+            Number::EIGHT => write!(f, "8")?,
+            Number::NINE => write!(f, "9")?,
         }
         Ok(())
     }
@@ -281,6 +302,7 @@ impl Display for WildType {
     
 }
 
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct UnoDeck {
     // number_cards: Vec<NumberCard>,
     // action_cards: Vec<ActionCard>,
@@ -288,6 +310,12 @@ pub struct UnoDeck {
     pub cards: Vec<UnoCard>,
 }
 
+impl Default for UnoDeck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl UnoDeck {
     pub fn new() -> UnoDeck {
         // Initialize the deck with standard Uno cards
@@ -298,7 +326,7 @@ impl UnoDeck {
             for number in 0..10 {
                 if let Some(num) = Number::from_u8(number) {
                     number_cards.push(NumberCard {
-                        color: color.clone(),
+                        color,
                         number: num,
                     });
                 }
@@ -307,7 +335,7 @@ impl UnoDeck {
             for number in 1..10 {
                 if let Some(num) = Number::from_u8(number) {
                     number_cards.push(NumberCard {
-                        color: color.clone(),
+                        color,
                         number: num,
                     });
                 }
@@ -319,15 +347,15 @@ impl UnoDeck {
         for &color in [Color::RED, Color::GREEN, Color::BLUE, Color::YELLOW].iter() {
             for _ in 0..2 {
                 action_cards.push(ActionCard {
-                    color: color.clone(),
+                    color,
                     action: Action::SKIP,
                 });
                 action_cards.push(ActionCard {
-                    color: color.clone(),
+                    color,
                     action: Action::REVERSE,
                 });
                 action_cards.push(ActionCard {
-                    color: color.clone(),
+                    color,
                     action: Action::DRAWTWO,
                 });
             }
@@ -362,12 +390,13 @@ impl UnoDeck {
     }
 
     pub fn shuffle(&mut self) {
-        use rand::seq::SliceRandom;
-        use rand::thread_rng;
+        self.shuffle_with(&mut rand::thread_rng());
+    }
 
-        let mut rng = thread_rng();
+    // 使用指定的随机源洗牌, 便于复现牌局
+    pub fn shuffle_with<R: rand::Rng + ?Sized>(&mut self, rng: &mut R) {
+        use rand::seq::SliceRandom;
 
-        // Shuffle number cards
-        self.cards.shuffle(&mut rng);
+        self.cards.shuffle(rng);
     }
 }
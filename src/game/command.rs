@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::cards::Color;
+use crate::game::error::RuleError;
+use crate::game::events::GameEvent as GE;
+use crate::game::player::PlayerId;
+use crate::game::uno_game::UnoGame;
+
+// 改变牌局状态的操作, 按顺序重放被接受的操作就能得到同一局
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum Command {
+    PlayCard {
+        player_id: PlayerId,
+        card_index: usize,
+        call_uno: bool,
+        color: Option<Color>,
+        swap_with: Option<PlayerId>,
+    },
+    DrawCard {
+        player_id: PlayerId,
+    },
+    PassTurn {
+        player_id: PlayerId,
+    },
+    ChooseColor {
+        player_id: PlayerId,
+        color: Color,
+    },
+    Challenge {
+        challenger_id: PlayerId,
+        challenged_id: PlayerId,
+    },
+    AcceptDrawFour {
+        player_id: PlayerId,
+    },
+    Leave {
+        player_id: PlayerId,
+    },
+}
+
+impl Command {
+    pub fn apply(&self, game: &mut UnoGame) -> Result<Vec<GE>, RuleError> {
+        match *self {
+            Command::PlayCard { player_id, card_index, call_uno, color, swap_with } => {
+                game.play_card_with_target(player_id, card_index, call_uno, color, swap_with)
+            }
+            Command::DrawCard { player_id } => game.draw_card(player_id),
+            Command::PassTurn { player_id } => game.player_pass(player_id),
+            Command::ChooseColor { player_id, color } => game.choose_color(player_id, color),
+            Command::Challenge { challenger_id, challenged_id } => {
+                game.challenge(challenger_id, challenged_id)
+            }
+            Command::AcceptDrawFour { player_id } => game.accept_draw_four(player_id),
+            Command::Leave { player_id } => game.remove_player(player_id),
+        }
+    }
+}
+
+// Match 接受的一次操作和它产生的事件, 服务器广播事件, 录回放时把操作也记下来
+pub struct Accepted {
+    pub command: Command,
+    pub events: Vec<GE>,
+}
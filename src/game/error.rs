@@ -0,0 +1,60 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+// UnoGame 拒绝一个动作的原因, 被拒绝的动作不会改变牌局状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleError {
+    GameNotStarted,
+    GameAlreadyStarted,
+    NoPlayers,
+    InvalidPlayer,
+    NotYourTurn,
+    InvalidCardIndex,
+    CannotPlayCard,
+    ColorRequired,
+    MustPlayDrawnCard, // 摸到可出的牌之后只能出这张或者跳过
+    ForcedPlay,        // forced_play 房规下摸到能出的牌必须出
+    AlreadyDrawn,
+    MustDrawBeforePass,
+    JumpInNotIdentical,
+    MustStackOrTake,
+    DrawFourPending,
+    ColorChoicePending,
+    NoColorToChoose,
+    NoDrawFourToChallenge,
+    NoDrawFourToAccept,
+    DeckEmpty,
+    RulesLocked,
+}
+
+impl Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            RuleError::GameNotStarted => "Game not started",
+            RuleError::GameAlreadyStarted => "Game already started",
+            RuleError::NoPlayers => "No players to start the game",
+            RuleError::InvalidPlayer => "Invalid player id",
+            RuleError::NotYourTurn => "It's not your turn",
+            RuleError::InvalidCardIndex => "Invalid card index",
+            RuleError::CannotPlayCard => "Cannot play this card",
+            RuleError::ColorRequired => "Must choose a colour for a wild card",
+            RuleError::MustPlayDrawnCard => "Can only play the card just drawn",
+            RuleError::ForcedPlay => "Must play the drawn card",
+            RuleError::AlreadyDrawn => "Already drew a card this turn",
+            RuleError::MustDrawBeforePass => "Must draw a card before passing",
+            RuleError::JumpInNotIdentical => "Can only jump in with an identical card",
+            RuleError::MustStackOrTake => "Must stack a draw card or take the draws",
+            RuleError::DrawFourPending => "Must challenge or accept the Wild Draw Four first",
+            RuleError::ColorChoicePending => "Must choose a colour for the first card first",
+            RuleError::NoColorToChoose => "No colour to choose",
+            RuleError::NoDrawFourToChallenge => "No Wild Draw Four to challenge",
+            RuleError::NoDrawFourToAccept => "No Wild Draw Four to accept",
+            RuleError::DeckEmpty => "No more cards in the deck to draw",
+            RuleError::RulesLocked => "Rules can only be changed between matches",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl std::error::Error for RuleError {}
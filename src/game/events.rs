@@ -1,37 +1,55 @@
 use std::fmt::Display;
 
-use crate::game::cards::UnoCard;
+use crate::game::cards::{Color, UnoCard};
+use crate::game::error::RuleError;
+use crate::game::player::PlayerId;
+use crate::game::rules::RuleSet;
 use serde::{Serialize, Deserialize};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub enum GameEvent {
+    // 一局的第一个事件, 足够从事件流重建整个牌局
+    GameCreated {
+        seed: u64,
+        rules: RuleSet,
+        first_seat: usize, // 首位出牌的座位
+    },
     PlayerJoined {
-        player_id: usize,
+        player_id: PlayerId,
         name: String,
     },
     GameStarted {
         game_id: usize,
+        // 用 UnoGame::with_seed(seed) 可复现本局; 种子决定整副牌的顺序, 只有上帝视角能看到
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seed: Option<u64>,
     },
 
     CardPlayed {
-        player_id: usize,
+        player_id: PlayerId,
         card: UnoCard,
+        card_index: usize, // 打出前在手牌中的下标
     },
     CardDraw {
-        player_id: usize,
+        player_id: PlayerId,
         card: UnoCard,
     },
+    // 发给其他玩家的 CardDraw, 隐去具体牌面
+    CardsDrawn {
+        player_id: PlayerId,
+        count: usize,
+    },
 
     // Player has no cards to play and has drawn a card
     DrawnCardPlayable {
-        player_id: usize,
+        player_id: PlayerId,
     },
     PlayerPassed {
-        player_id: usize,
+        player_id: PlayerId,
     },
 
     UnoCalled {
-        player_id: usize,
+        player_id: PlayerId,
     },
     DirectionChanged {
         clockwise: bool,
@@ -39,60 +57,143 @@ pub enum GameEvent {
     TopCardChanged {
         top_card: UnoCard,
     },
+    // 翻开的第一张不能做首张, 放回牌堆重新洗牌
+    FirstCardRedrawn {
+        card: UnoCard,
+    },
+    // 牌堆摸空, 弃牌堆已洗回牌堆
+    DeckReshuffled {
+        deck_size: usize,
+    },
     PlayerTurn {
-        player_id: usize,
+        player_id: PlayerId,
     },
     PlayerSkipped {
-        player_id: usize,
+        player_id: PlayerId,
     },
     DrawFourApplied {
-        target_player_id: usize,
+        target_player_id: PlayerId,
     },
     DrawTwoApplied {
-        target_player_id: usize,
+        target_player_id: PlayerId,
+    },
+    // 叠加房规: 罚牌累加到下家
+    DrawStacked {
+        target_player_id: PlayerId,
+        total: usize,
+    },
+    // 叠加房规: 接不住, 一次摸完累计的罚牌
+    DrawStackTaken {
+        player_id: PlayerId,
+        count: usize,
+    },
+    // 第一张顶牌是万能牌, 首位玩家先选颜色
+    ColorChoiceRequired {
+        player_id: PlayerId,
+    },
+    ColorChosen {
+        player_id: PlayerId,
+        color: Color,
+    },
+    // 连续超时被判定挂机, 由电脑代打直到本人再次操作
+    PlayerAfk {
+        player_id: PlayerId,
+    },
+    PlayerReturned {
+        player_id: PlayerId,
+    },
+    // 中途离开, 手牌洗回牌堆
+    PlayerLeft {
+        player_id: PlayerId,
+    },
+    // 房主管理
+    HostChanged {
+        player_id: PlayerId,
+    },
+    PlayerKicked {
+        player_id: PlayerId,
+    },
+    RoomLocked {
+        locked: bool,
+    },
+    RulesChanged {
+        rules: RuleSet,
+    },
+    // 抢出房规: 不在自己回合打出了与顶牌相同的牌
+    JumpedIn {
+        player_id: PlayerId,
+    },
+    // 7-0 房规
+    HandsSwapped {
+        player_id: PlayerId,
+        other_id: PlayerId,
+    },
+    HandsRotated {
+        clockwise: bool,
     },
 
+    // +4 打出后, 被罚玩家可以选择质疑或接受
+    ChallengeWindow {
+        challenger_id: PlayerId,
+        challenged_id: PlayerId,
+    },
     PlayerChallenged {
-        challenger_id: usize,
-        challenged_id: usize,
+        challenger_id: PlayerId,
+        challenged_id: PlayerId,
     },
     ChallengedFailed {
-        challenger_id: usize,
-        challenged_id: usize,
+        challenger_id: PlayerId,
+        challenged_id: PlayerId,
     },
     ChallengedSuccess {
-        challenger_id: usize,
-        challenged_id: usize,
+        challenger_id: PlayerId,
+        challenged_id: PlayerId,
     },
     UnoPenalty {
-        player_id: usize,
+        player_id: PlayerId,
     },
 
 
     GameOver {
-        winner: usize,
+        winner: PlayerId,
         scores: Vec<(String, i32)>,
     },
+    // 多局比赛: 本局赢家拿走其他人手牌的分数
+    RoundOver {
+        round: u32,
+        winner: PlayerId,
+        points: i32,
+        totals: Vec<(String, i32)>, // 按加入顺序的累计分数
+    },
+    MatchOver {
+        winner: PlayerId,
+        totals: Vec<(String, i32)>,
+    },
     GameError {
-        message: String,
+        error: RuleError,
     },
 }
 
 impl Display for GameEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            GameEvent::GameCreated { seed, first_seat, .. } => 
+                write!(f, "GameCreated: seed={}, first_seat={}", seed, first_seat)?,
             GameEvent::PlayerJoined { 
                 player_id, name } => 
                 write!(f, "PlayerJoined: id={}, name={}", player_id, name)?,
             GameEvent::GameStarted { 
-                game_id } => 
-                write!(f, "GameStarted: game_id={}", game_id)?,
+                game_id, seed } => 
+                write!(f, "GameStarted: game_id={}, seed={:?}", game_id, seed)?,
             GameEvent::CardPlayed { 
-                player_id, card } => 
-                write!(f, "CardPlayed: id={}, card={}", player_id, card.to_string())?,
+                player_id, card, .. } => 
+                write!(f, "CardPlayed: id={}, card={}", player_id, card)?,
             GameEvent::CardDraw { 
                 player_id, card } => 
-                write!(f, "CardDraw: id={}, card={}", player_id, card.to_string())?,
+                write!(f, "CardDraw: id={}, card={}", player_id, card)?,
+            GameEvent::CardsDrawn { 
+                player_id, count } => 
+                write!(f, "CardsDrawn: id={}, count={}", player_id, count)?,
             GameEvent::DrawnCardPlayable { 
                 player_id, .. } => 
                 write!(f, "DrawnCardPlayable: id={}", player_id)?,
@@ -104,7 +205,11 @@ impl Display for GameEvent {
             GameEvent::DirectionChanged { clockwise } => 
                 write!(f, "DirectionChanged: clockwise={}", clockwise)?,
             GameEvent::TopCardChanged { top_card } => 
-                write!(f, "TopCardChanged: top_card={}", top_card.to_string())?,
+                write!(f, "TopCardChanged: top_card={}", top_card)?,
+            GameEvent::FirstCardRedrawn { card } => 
+                write!(f, "FirstCardRedrawn: card={}", card)?,
+            GameEvent::DeckReshuffled { deck_size } => 
+                write!(f, "DeckReshuffled: deck_size={}", deck_size)?,
             GameEvent::PlayerTurn { player_id } => 
                 write!(f, "PlayerTurn: id={}", player_id)?,
             GameEvent::PlayerSkipped { player_id } => 
@@ -113,6 +218,36 @@ impl Display for GameEvent {
                 write!(f, "DrawFourApplied: target_id={}", target_player_id)?,
             GameEvent::DrawTwoApplied { target_player_id } => 
                 write!(f, "DrawTwoApplied: target_id={}", target_player_id)?,
+            GameEvent::ChallengeWindow { challenger_id, challenged_id } => 
+                write!(f, "ChallengeWindow: challenger_id={}, challenged_id={}", challenger_id, challenged_id)?,
+            GameEvent::DrawStacked { target_player_id, total } => 
+                write!(f, "DrawStacked: target_id={}, total={}", target_player_id, total)?,
+            GameEvent::DrawStackTaken { player_id, count } => 
+                write!(f, "DrawStackTaken: id={}, count={}", player_id, count)?,
+            GameEvent::ColorChoiceRequired { player_id } => 
+                write!(f, "ColorChoiceRequired: id={}", player_id)?,
+            GameEvent::ColorChosen { player_id, color } => 
+                write!(f, "ColorChosen: id={}, color={:?}", player_id, color)?,
+            GameEvent::PlayerAfk { player_id } => 
+                write!(f, "PlayerAfk: id={}", player_id)?,
+            GameEvent::PlayerReturned { player_id } => 
+                write!(f, "PlayerReturned: id={}", player_id)?,
+            GameEvent::PlayerLeft { player_id } => 
+                write!(f, "PlayerLeft: id={}", player_id)?,
+            GameEvent::HostChanged { player_id } => 
+                write!(f, "HostChanged: id={}", player_id)?,
+            GameEvent::PlayerKicked { player_id } => 
+                write!(f, "PlayerKicked: id={}", player_id)?,
+            GameEvent::RoomLocked { locked } => 
+                write!(f, "RoomLocked: locked={}", locked)?,
+            GameEvent::RulesChanged { rules } => 
+                write!(f, "RulesChanged: rules={:?}", rules)?,
+            GameEvent::JumpedIn { player_id } => 
+                write!(f, "JumpedIn: id={}", player_id)?,
+            GameEvent::HandsSwapped { player_id, other_id } => 
+                write!(f, "HandsSwapped: id={}, other_id={}", player_id, other_id)?,
+            GameEvent::HandsRotated { clockwise } => 
+                write!(f, "HandsRotated: clockwise={}", clockwise)?,
             GameEvent::PlayerChallenged { challenger_id, challenged_id } => 
                 write!(f, "PlayerChallenged: challenger_id={}, challenged_id={}", challenger_id, challenged_id)?,
             GameEvent::ChallengedFailed { challenger_id, challenged_id } => 
@@ -123,9 +258,51 @@ impl Display for GameEvent {
                 write!(f, "UnoPenalty: id={}", player_id)?,
             GameEvent::GameOver { winner, scores } => 
                 write!(f, "GameOver: winner={}, scores={:?}", winner, scores)?,
-            GameEvent::GameError { message } => 
-                write!(f, "GameError: message={}", message)?,
+            GameEvent::RoundOver { round, winner, points, totals } => 
+                write!(f, "RoundOver: round={}, winner={}, points={}, totals={:?}", round, winner, points, totals)?,
+            GameEvent::MatchOver { winner, totals } => 
+                write!(f, "MatchOver: winner={}, totals={:?}", winner, totals)?,
+            GameEvent::GameError { error } => 
+                write!(f, "GameError: error={:?}", error)?,
         }
         Ok(())
     }
 }
+
+impl GameEvent {
+    /// 按接收者投影事件: 别人摸到的牌只告诉张数, 种子一律隐去
+    /// `viewer` 为 None 表示未入座的连接, 看不到任何人的牌
+    pub fn view_for(&self, viewer: Option<PlayerId>) -> GameEvent {
+        match self {
+            GameEvent::CardDraw { player_id, .. } if Some(*player_id) != viewer => {
+                GameEvent::CardsDrawn { player_id: *player_id, count: 1 }
+            }
+            GameEvent::GameStarted { game_id, .. } => GameEvent::GameStarted { game_id: *game_id, seed: None },
+            _ => self.clone(),
+        }
+    }
+}
+
+/// 对一批事件做投影, 并把同一玩家连续的 CardsDrawn 合并成一条
+/// GameCreated 带着种子, 只留在服务器端(事件溯源和回放), 不发给任何连接
+pub fn redact_events(events: &[GameEvent], viewer: Option<PlayerId>) -> Vec<GameEvent> {
+    let mut out: Vec<GameEvent> = Vec::with_capacity(events.len());
+    for e in events {
+        if matches!(e, GameEvent::GameCreated { .. }) {
+            continue;
+        }
+        let e = e.view_for(viewer);
+        if let (
+            Some(GameEvent::CardsDrawn { player_id: last_id, count }),
+            GameEvent::CardsDrawn { player_id, count: n },
+        ) = (out.last_mut(), &e)
+        {
+            if *last_id == *player_id {
+                *count += n;
+                continue;
+            }
+        }
+        out.push(e);
+    }
+    out
+}
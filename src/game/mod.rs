@@ -2,6 +2,14 @@ pub mod uno_game;
 pub mod cards;
 pub mod player;
 pub mod events;
+pub mod rules;
+pub mod uno_match;
+pub mod error;
+pub mod command;
 pub use uno_game::UnoGame;
 pub use cards::*;
-pub use player::Player;
\ No newline at end of file
+pub use player::{Player, PlayerId};
+pub use rules::RuleSet;
+pub use uno_match::Match;
+pub use error::RuleError;
+pub use command::{Accepted, Command};
@@ -1,19 +1,41 @@
 use std::fmt::Display;
+use serde::{Serialize, Deserialize};
 use crate::game::cards::UnoCard;
 
+// 玩家身份, 按加入顺序分配, 与座位顺序无关
+// 序列化成普通数字, 协议里仍然是 "player_id": 0
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PlayerId(pub usize);
+
+impl PlayerId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl Display for PlayerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct Player {
     pub name: String,
     hand: Vec<UnoCard>,
-    pub id: usize,
+    pub id: PlayerId,
+    pub left: bool, // 中途离开, 座位保留但不再参与轮转
 }
 
 
 impl Player {
-    pub fn new(name: &str, id: usize) -> Player {
+    pub fn new(name: &str, id: PlayerId) -> Player {
         Player {
             name: name.to_string(),
             hand: Vec::new(),
             id,
+            left: false,
         }
     }
 
@@ -25,6 +47,11 @@ impl Player {
         &self.hand
     }
 
+    // 换上新的手牌, 返回原来的手牌 (7-0 换牌用)
+    pub fn replace_hand(&mut self, hand: Vec<UnoCard>) -> Vec<UnoCard> {
+        std::mem::replace(&mut self.hand, hand)
+    }
+
     pub fn remove_card(&mut self, card_idx: usize) -> Result<UnoCard, String> {
         if card_idx >= self.hand.len() {
             return Err("Invalid card index".to_string());
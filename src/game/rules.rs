@@ -0,0 +1,65 @@
+use serde::{Serialize, Deserialize};
+
+// 一副标准 UNO 牌的张数
+pub const DECK_SIZE: usize = 108;
+
+/// 房规配置, 默认值即官方规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleSet {
+    /// +2 可以叠在 +2 上, +4 可以叠在 +2/+4 上, 最后接不住的玩家一次摸完.
+    /// 开启后 +4 直接进入叠加, 不再有质疑环节
+    pub stack_draws: bool,
+    /// 出 7 与指定玩家交换手牌, 出 0 所有人沿出牌方向传递手牌
+    pub seven_zero: bool,
+    /// 手里有与顶牌完全相同(颜色和牌面)的牌时, 不在自己回合也可以抢出
+    pub jump_in: bool,
+    /// 一直摸到能出的牌为止, 关闭时只摸一张
+    pub draw_until_playable: bool,
+    /// 摸到能出的牌必须打出, 不能跳过
+    pub forced_play: bool,
+    pub starting_hand_size: usize,
+    /// 翻开的第一张顶牌按官方规则生效, 关闭时一直翻到数字牌为止
+    pub first_card_effects: bool,
+    /// 多局比赛中先累计到这个分数的玩家获胜
+    pub target_score: i32,
+    /// 每回合限时(秒), 超时由服务器代为摸牌并跳过, 0 表示不限时
+    pub turn_timeout_secs: u64,
+    /// 开局时随机排座位, 关闭时按加入顺序入座
+    pub random_seats: bool,
+}
+
+impl RuleSet {
+    /// players 人开局时起手牌张数的上限: 发完牌后剩下的牌必须能翻出合格的首张顶牌.
+    /// 最坏情况下剩下的全是要放回重翻的牌: 4 张 +4, 不开首牌效果时还有 28 张功能牌和 4 张万能牌
+    pub fn max_hand_size(&self, players: usize) -> usize {
+        let redraws = if self.first_card_effects { 4 } else { 32 };
+        (DECK_SIZE - redraws - 1) / players.max(1)
+    }
+
+    /// 检查房规能否用于最多 players 人的房间, 不能时返回原因
+    pub fn check(&self, players: usize) -> Result<(), String> {
+        let max = self.max_hand_size(players);
+        if !(1..=max).contains(&self.starting_hand_size) {
+            return Err(format!("starting_hand_size must be between 1 and {} for {} players", max, players));
+        }
+        Ok(())
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet {
+            stack_draws: false,
+            seven_zero: false,
+            jump_in: false,
+            draw_until_playable: false,
+            forced_play: false,
+            starting_hand_size: 7,
+            first_card_effects: true,
+            target_score: 500,
+            turn_timeout_secs: 0,
+            random_seats: false,
+        }
+    }
+}
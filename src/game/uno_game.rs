@@ -1,291 +1,752 @@
-use crate::game::{cards::*, uno_game};
-use crate::game::player::Player;
+use std::borrow::Borrow;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::game::cards::*;
+use crate::game::error::RuleError;
+use crate::game::player::{Player, PlayerId};
 use crate::game::events::GameEvent as GE;
+use crate::game::rules::RuleSet;
 
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct UnoGame {
     deck: UnoDeck,
-    players: Vec<Player>,
-    pub current_player: usize,
+    discard_pile: Vec<UnoCard>, // 不含顶牌
+    players: Vec<Player>, // 按座位顺序, 下标是座位而不是 PlayerId
+    current: usize, // 当前出牌玩家的座位
     pub top_card: Option<UnoCard>,
     pub direction: bool, // true for clockwise, false for counter-clockwise
     pub started: bool,
+    rules: RuleSet,
+    seed: u64,
+    rng: ChaCha12Rng, // 所有洗牌都来自这个随机源; 与 StdRng 算法相同, 但可以存进快照
+    pending_draw_four: Option<PendingDrawFour>,
+    drawn_card: Option<usize>, // 当前玩家刚摸到且可以立即打出的牌的下标
+    stacked_draws: usize, // 叠加房规下, 当前玩家要么继续叠要么摸这么多张
+    color_pending: bool, // 第一张顶牌是万能牌, 等待首位玩家选颜色
+}
+
+// +4 打出后, 等待被罚玩家选择质疑或接受
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct PendingDrawFour {
+    player_seat: usize, // 打出 +4 的玩家
+    target_seat: usize, // 被 +4 的玩家
+    bluffed: bool,    // 打出时手里是否还有与前一张顶牌同色的牌
+}
+
+impl Default for UnoGame {
+    fn default() -> Self {
+        Self::new(RuleSet::default())
+    }
 }
 
 impl UnoGame {
-    pub fn new() -> UnoGame{
+    pub fn new(rules: RuleSet) -> UnoGame{
+        Self::with_seed(rand::thread_rng().gen(), rules)
+    }
+
+    // 相同的种子得到完全相同的牌局
+    pub fn with_seed(seed: u64, rules: RuleSet) -> UnoGame {
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
         let mut d = UnoDeck::new();
-        d.shuffle();
+        d.shuffle_with(&mut rng);
         UnoGame {
             deck: d,
+            discard_pile: Vec::new(),
             players: Vec::new(),
             top_card: None,
             direction: true,
-            current_player: 0,
+            current: 0,
             started: false,
+            rules,
+            seed,
+            rng,
+            pending_draw_four: None,
+            drawn_card: None,
+            stacked_draws: 0,
+            color_pending: false,
         }
     }
 
-    pub fn get_player_hand(&self, player_id: usize) -> Vec<UnoCard> {
-        if let Some(player) = self.players.get(player_id) {
-            player.display_hand().clone()
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    // 叠加房规下当前玩家面临的罚牌张数
+    pub fn stacked_draws(&self) -> usize {
+        self.stacked_draws
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // 剩余的牌堆, 最后一张是下一张被摸到的牌
+    pub fn deck(&self) -> &[UnoCard] {
+        &self.deck.cards
+    }
+
+    pub fn current_player(&self) -> PlayerId {
+        self.players.get(self.current).map_or_else(PlayerId::default, |p| p.id)
+    }
+
+    // 按座位顺序的玩家
+    pub fn seating(&self) -> Vec<PlayerId> {
+        self.players.iter().map(|p| p.id).collect()
+    }
+
+    fn seat_of(&self, player_id: PlayerId) -> Option<usize> {
+        self.players.iter().position(|p| p.id == player_id)
+    }
+
+    // 在座且没有离开的玩家的座位
+    fn active_seat(&self, player_id: PlayerId) -> Option<usize> {
+        self.seat_of(player_id).filter(|&seat| !self.players[seat].left)
+    }
+
+    // 正在等待质疑/接受 +4 的 (challenger_id, challenged_id)
+    pub fn challenge_window(&self) -> Option<(PlayerId, PlayerId)> {
+        self.pending_draw_four
+            .map(|p| (self.players[p.target_seat].id, self.players[p.player_seat].id))
+    }
+
+    // 等待为第一张万能顶牌选颜色的玩家
+    pub fn color_choice(&self) -> Option<PlayerId> {
+        self.color_pending.then(|| self.current_player())
+    }
+
+    // 当前玩家刚摸到的可出牌在手牌中的下标
+    pub fn drawn_card_playable(&self) -> Option<usize> {
+        self.drawn_card
+    }
+
+    pub fn get_player_hand(&self, player_id: PlayerId) -> Vec<UnoCard> {
+        if let Some(seat) = self.seat_of(player_id) {
+            self.players[seat].display_hand().clone()
         } else {
             Vec::new()
         }
     }
 
-    pub fn get_players_cards_count(&self) -> Vec<(String, usize)> {
+    pub fn has_left(&self, player_id: PlayerId) -> bool {
+        self.seat_of(player_id).is_some_and(|seat| self.players[seat].left)
+    }
+
+    // 按座位顺序的 (id, 名字, 手牌数)
+    pub fn get_players_cards_count(&self) -> Vec<(PlayerId, String, usize)> {
         self.players.iter()
-            .map(|p| (p.name.clone(), p.display_hand().len()))
+            .map(|p| (p.id, p.name.clone(), p.display_hand().len()))
             .collect()
     }
 
-    pub fn init_game(&mut self, players: Vec<String>) -> Vec<GE>{
+    // 从完整的(没有隐去手牌的)事件流重建牌局, 事件流从 GameCreated 开始
+    pub fn from_events<I>(events: I) -> UnoGame
+    where
+        I: IntoIterator,
+        I::Item: Borrow<GE>,
+    {
+        let mut game = UnoGame::default();
+        for e in events {
+            game.apply(e.borrow());
+        }
+        game
+    }
+
+    // 所有对牌局状态的修改都在这里, 操作只负责校验并产生事件
+    // 与牌局无关的事件(房间 / 比分)直接忽略
+    pub fn apply(&mut self, event: &GE) {
+        match event {
+            GE::GameCreated { seed, rules, first_seat } => {
+                *self = UnoGame::with_seed(*seed, rules.clone());
+                self.current = *first_seat;
+            }
+            GE::PlayerJoined { player_id, name } => {
+                self.players.push(Player::new(name, *player_id));
+            }
+            GE::GameStarted { .. } => self.started = true,
+            GE::CardDraw { player_id, card } => {
+                self.deck.cards.pop();
+                if let Some(seat) = self.seat_of(*player_id) {
+                    self.players[seat].push_card(*card);
+                }
+            }
+            // 翻到不能做首张的牌, 放回去重新洗牌
+            GE::FirstCardRedrawn { .. } => self.deck.shuffle_with(&mut self.rng),
+            GE::TopCardChanged { top_card } => {
+                // 开局翻开的第一张来自牌堆, 其余情况顶牌已经在桌上
+                if self.top_card.is_none() {
+                    self.deck.cards.pop();
+                }
+                self.top_card = Some(*top_card);
+            }
+            GE::CardPlayed { player_id, card, card_index } => {
+                if let Some(seat) = self.seat_of(*player_id) {
+                    let _ = self.players[seat].remove_card(*card_index);
+                }
+                if let Some(previous) = self.top_card.replace(*card) {
+                    self.discard_pile.push(previous);
+                }
+                self.drawn_card = None;
+            }
+            // 除顶牌外的弃牌全部洗回牌堆, 万能牌的已选颜色清空
+            GE::DeckReshuffled { .. } => {
+                self.deck.cards.extend(
+                    self.discard_pile.drain(..).map(|c| c.without_chosen_color()));
+                self.deck.shuffle_with(&mut self.rng);
+            }
+            GE::PlayerTurn { player_id } | GE::JumpedIn { player_id } => {
+                if let Some(seat) = self.seat_of(*player_id) {
+                    self.current = seat;
+                }
+            }
+            GE::DirectionChanged { clockwise } => self.direction = *clockwise,
+            GE::DrawnCardPlayable { player_id } => {
+                self.drawn_card = self.seat_of(*player_id)
+                    .and_then(|seat| self.players[seat].display_hand().len().checked_sub(1));
+            }
+            GE::PlayerPassed { .. } => self.drawn_card = None,
+            GE::DrawStacked { total, .. } => self.stacked_draws = *total,
+            GE::DrawStackTaken { .. } => self.stacked_draws = 0,
+            GE::ColorChoiceRequired { .. } => self.color_pending = true,
+            GE::ColorChosen { .. } => self.color_pending = false,
+            GE::ChallengeWindow { challenger_id, challenged_id } => {
+                let (Some(player_seat), Some(target_seat)) =
+                    (self.seat_of(*challenged_id), self.seat_of(*challenger_id)) else {
+                    return;
+                };
+                // 打出时手里是否还有与前一张顶牌同色的牌, 前一张顶牌刚进弃牌堆
+                let previous_color = self.discard_pile.last()
+                    .and_then(|c| c.get_color().ok().copied());
+                let bluffed = previous_color.is_some_and(|pc| self.players[player_seat]
+                    .display_hand().iter().any(|c| c.get_color().ok() == Some(&pc)));
+                self.pending_draw_four = Some(PendingDrawFour { player_seat, target_seat, bluffed });
+            }
+            GE::PlayerChallenged { .. } | GE::DrawFourApplied { .. } => {
+                self.pending_draw_four = None;
+            }
+            GE::HandsSwapped { player_id, other_id } => {
+                if let (Some(a), Some(b)) = (self.seat_of(*player_id), self.seat_of(*other_id)) {
+                    let mine = self.players[a].replace_hand(Vec::new());
+                    let theirs = self.players[b].replace_hand(mine);
+                    self.players[a].replace_hand(theirs);
+                }
+            }
+            GE::HandsRotated { clockwise } => {
+                // 所有人把手牌交给出牌方向上的下一位, 已离开的玩家不参与
+                let seats: Vec<usize> = (0..self.players.len())
+                    .filter(|&i| !self.players[i].left)
+                    .collect();
+                let n = seats.len();
+                let hands: Vec<Vec<UnoCard>> = seats.iter()
+                    .map(|&i| self.players[i].replace_hand(Vec::new()))
+                    .collect();
+                for (i, hand) in hands.into_iter().enumerate() {
+                    let to = if *clockwise { (i + 1) % n } else { (i + n - 1) % n };
+                    self.players[seats[to]].replace_hand(hand);
+                }
+            }
+            // 中途离开: 手牌洗回牌堆, 座位保留但轮转时跳过
+            GE::PlayerLeft { player_id } => {
+                let Some(seat) = self.seat_of(*player_id) else {
+                    return;
+                };
+                let hand = self.players[seat].replace_hand(Vec::new());
+                self.players[seat].left = true;
+                self.deck.cards.extend(hand.into_iter().map(|c| c.without_chosen_color()));
+                self.deck.shuffle_with(&mut self.rng);
+                // 与离开的人有关的 +4 没法再质疑, 轮到他时叠加的罚牌作废
+                if self.pending_draw_four
+                    .is_some_and(|p| p.player_seat == seat || p.target_seat == seat) {
+                    self.pending_draw_four = None;
+                }
+                if self.current == seat {
+                    self.stacked_draws = 0;
+                    self.drawn_card = None;
+                }
+                // 剩下不到两人时这一局结束
+                if self.started && self.players.iter().filter(|p| !p.left).count() < 2 {
+                    self.finish();
+                }
+            }
+            GE::GameOver { .. } => self.finish(),
+            _ => {}
+        }
+    }
+
+    // 应用事件并记下来
+    fn emit(&mut self, ev: &mut Vec<GE>, event: GE) {
+        self.apply(&event);
+        ev.push(event);
+    }
+
+    fn finish(&mut self) {
+        self.pending_draw_four = None;
+        self.stacked_draws = 0;
+        self.color_pending = false;
+        self.drawn_card = None;
+        self.started = false;
+    }
+
+    // 按名单顺序分配 PlayerId, 开启 random_seats 时打乱座位
+    // 座位用单独的随机源, 牌堆的随机源只在开局之后使用, 才能从事件重建
+    pub fn init_game(&mut self, players: Vec<String>) -> Result<Vec<GE>, RuleError> {
+        let mut seated: Vec<(PlayerId, String)> = players.into_iter()
+            .enumerate()
+            .map(|(i, name)| (PlayerId(i), name))
+            .collect();
+        if self.rules.random_seats {
+            seated.shuffle(&mut StdRng::seed_from_u64(self.seed));
+        }
+        self.init_round(seated, 0)
+    }
+
+    // players: 按座位顺序的 (id, 名字); first_player: 庄家左手边第一个出牌的座位
+    pub fn init_round(
+        &mut self,
+        players: Vec<(PlayerId, String)>,
+        first_player: usize,
+    ) -> Result<Vec<GE>, RuleError> {
         let mut ev = Vec::new();
         if self.started {
-            ev.push(GE::GameError { message: "Game already started!".to_string() });
-            return ev;
+            return Err(RuleError::GameAlreadyStarted);
+        }
+        if players.is_empty() {
+            return Err(RuleError::NoPlayers);
+        }
+        // 牌不够发时在产生任何事件之前拒绝
+        if self.rules.starting_hand_size > self.rules.max_hand_size(players.len()) {
+            return Err(RuleError::DeckEmpty);
+        }
+        let first_seat = first_player % players.len();
+        self.emit(&mut ev, GE::GameCreated {
+            seed: self.seed,
+            rules: self.rules.clone(),
+            first_seat,
+        });
+        for (player_id, name) in players {
+            self.emit(&mut ev, GE::PlayerJoined { player_id, name });
         }
-        self.add_players(players, &mut ev);
         // Distribute initial cards to players
         for i in 0..self.players.len() {
-            ev.extend(self.cards_distribution(i, 7));
+            ev.extend(self.cards_distribution(i, self.rules.starting_hand_size));
         }
         // Draw the first card from the deck to start the game
         loop {
-            if let Some(card) = self.deck.cards.pop() {
-                if !card.get_number().is_none(){
-                    self.top_card = Some(card);
-                    ev.push(GE::TopCardChanged { top_card: self.top_card.
-                        expect("Top card should be set").clone() });
-                    ev.push(GE::PlayerTurn { player_id: self.current_player });
-                    break;
-                }
-                self.deck.cards.push(card); // 如果抽到的不是数字牌，放回去继续抽
-                self.deck.shuffle(); // 重新洗牌
-            } else {
-                ev.push(GE::GameError { message: 
-                    "No more cards in the deck to start the game!".to_string() });
+            let Some(&card) = self.deck.cards.last() else {
+                return Err(RuleError::DeckEmpty);
+            };
+            // +4 总是放回重翻; 不开首牌效果时只接受数字牌
+            let redraw = match card {
+                UnoCard::WildCard(_, WildType::DRAWFOUR) => true,
+                _ => !self.rules.first_card_effects && card.get_number().is_none(),
+            };
+            if !redraw {
+                self.emit(&mut ev, GE::TopCardChanged { top_card: card });
+                self.first_card_effect(card, &mut ev);
                 break;
             }
+            self.emit(&mut ev, GE::FirstCardRedrawn { card });
         }
-        self.started = true;
-        ev.push(GE::GameStarted { game_id: 0 }); // 这里可以设置一个实际的游戏ID
-        ev
+        // 这里可以设置一个实际的游戏ID
+        self.emit(&mut ev, GE::GameStarted { game_id: 0, seed: Some(self.seed) });
+        Ok(ev)
     }
 
-    fn add_players(&mut self, players: Vec<String>, ev: &mut Vec<GE>) {
-        for (index, name) in players.into_iter().enumerate() {
-            let player = Player::new(&name, index);
-            self.players.push(player);
-            ev.push(GE::PlayerJoined {
-                player_id: index,
-                name: name.clone(),
-            });
-        }
+    // 第一张顶牌的效果, current 此时是庄家左手边的首位玩家
+    fn first_card_effect(&mut self, card: UnoCard, ev: &mut Vec<GE>) {
+        let first = self.current;
+        let turn = match card {
+            UnoCard::ActionCard(_, Action::SKIP) => {
+                self.emit(ev, GE::PlayerSkipped { player_id: self.players[first].id });
+                self.next_seat(first)
+            }
+            UnoCard::ActionCard(_, Action::REVERSE) => {
+                // 反向后由庄家(首位玩家的上家)先出
+                self.emit(ev, GE::DirectionChanged { clockwise: !self.direction });
+                self.next_seat(first)
+            }
+            UnoCard::ActionCard(_, Action::DRAWTWO) => {
+                self.emit(ev, GE::DrawTwoApplied { target_player_id: self.players[first].id });
+                ev.extend(self.cards_distribution(first, 2));
+                self.next_seat(first)
+            }
+            UnoCard::WildCard(_, _) => {
+                self.emit(ev, GE::ColorChoiceRequired { player_id: self.players[first].id });
+                return;
+            }
+            _ => first,
+        };
+        self.emit(ev, GE::PlayerTurn { player_id: self.players[turn].id });
     }
 
-    fn change_direction(&mut self) {
-        self.direction = !self.direction;
+    // 首位玩家为第一张万能顶牌指定颜色, 然后照常出牌
+    pub fn choose_color(&mut self, player_id: PlayerId, color: Color) -> Result<Vec<GE>, RuleError> {
+        let mut ev = Vec::new();
+        match self.color_choice() {
+            None => return Err(RuleError::NoColorToChoose),
+            Some(p) if p != player_id => return Err(RuleError::NotYourTurn),
+            Some(_) => {}
+        }
+        let top_card = self.top_card
+            .map(|c| match c {
+                UnoCard::WildCard(_, wt) => UnoCard::WildCard(Some(color), wt),
+                _ => c,
+            })
+            .expect("Top card should be set");
+        self.emit(&mut ev, GE::ColorChosen { player_id, color });
+        self.emit(&mut ev, GE::TopCardChanged { top_card });
+        self.emit(&mut ev, GE::PlayerTurn { player_id });
+        Ok(ev)
     }
 
-    fn cards_distribution(&mut self, player_index: usize, num_cards: usize) -> Vec<GE>{
+    fn cards_distribution(&mut self, seat: usize, num_cards: usize) -> Vec<GE>{
         let mut ev = Vec::new();
+        let player_id = self.players[seat].id;
         for _ in 0..num_cards {
-            if let Some(card) = self.deck.cards.pop() {
-                self.players[player_index].push_card(card);
-                ev.push(GE::CardDraw { 
-                    player_id: player_index, card });
+            if let Some(card) = self.peek_deck(&mut ev) {
+                self.emit(&mut ev, GE::CardDraw { player_id, card });
             }
             else {
-                ev.push(GE::GameError { message: 
-                    "No more cards in the deck to draw!".to_string() });
+                ev.push(GE::GameError { error: RuleError::DeckEmpty });
                 break;
             }
         }
         ev
     }
 
-    fn no_card_to_play(&mut self) -> Result<UnoCard, String> {
-        // 如果没有牌可以打，抽一张牌
-        if let Some(card) = self.deck.cards.pop() {
-            // println!("{} draws a card.", self.players[self.current_player].name);
-            return Ok(card);
-        } else {
-            // eprintln!("No more cards in the deck to draw!");
-            return Err("No more cards in the deck to draw!".to_string());
+    // 牌堆和弃牌堆都空了就没法再摸牌
+    fn can_draw(&self) -> bool {
+        !self.deck.cards.is_empty() || !self.discard_pile.is_empty()
+    }
+
+    // 下一张要摸的牌, 牌堆空了先把弃牌堆洗回去
+    fn peek_deck(&mut self, ev: &mut Vec<GE>) -> Option<UnoCard> {
+        if self.deck.cards.is_empty() && !self.discard_pile.is_empty() {
+            let deck_size = self.discard_pile.len();
+            self.emit(ev, GE::DeckReshuffled { deck_size });
         }
+        self.deck.cards.last().copied()
+    }
+
+    // from 在出牌方向上的下一位, 跳过已经离开的玩家
+    fn next_seat(&self, from: usize) -> usize {
+        let n = self.players.len();
+        let mut next = from;
+        for _ in 0..n {
+            next = if self.direction { (next + 1) % n } else { (next + n - 1) % n };
+            if !self.players[next].left {
+                break;
+            }
+        }
+        next
     }
 
     fn next_player(&self) -> usize {
-        if self.direction {
-            (self.current_player + 1) % self.players.len()
-        } else {
-            (self.current_player + self.players.len() - 1) % self.players.len()
+        self.next_seat(self.current)
+    }
+
+    // 玩家中途离开: 手牌洗回牌堆, 座位保留但轮转时跳过, 其他人的 id 不变
+    // 剩下不到两人时直接结束这一局
+    pub fn remove_player(&mut self, player_id: PlayerId) -> Result<Vec<GE>, RuleError> {
+        let mut ev = Vec::new();
+        let Some(seat) = self.active_seat(player_id) else {
+            return Err(RuleError::InvalidPlayer);
+        };
+        let started = self.started;
+        let pending = self.pending_draw_four;
+        self.emit(&mut ev, GE::PlayerLeft { player_id });
+        if !started {
+            return Ok(ev);
+        }
+
+        let remaining: Vec<PlayerId> = self.players.iter()
+            .filter(|p| !p.left)
+            .map(|p| p.id)
+            .collect();
+        if remaining.len() < 2 {
+            if let Some(&winner) = remaining.first() {
+                self.emit(&mut ev, GE::GameOver { winner, scores: self.calculate_scores() });
+            }
+            return Ok(ev);
+        }
+
+        if let Some(pending) = pending.filter(|p| p.player_seat == seat) {
+            // 出 +4 的人走了, 没法再质疑, 直接生效
+            self.emit(&mut ev, GE::DrawFourApplied { 
+                target_player_id: self.players[pending.target_seat].id });
+            ev.extend(self.cards_distribution(pending.target_seat, 4));
+            let next = self.next_player();
+            self.emit(&mut ev, GE::PlayerTurn { player_id: self.players[next].id });
+            return Ok(ev);
+        }
+        if self.current != seat {
+            return Ok(ev);
+        }
+        // 轮到离开的人: 给第一张万能牌选颜色的事交给下一位
+        let next = self.players[self.next_player()].id;
+        if self.color_pending {
+            self.emit(&mut ev, GE::ColorChoiceRequired { player_id: next });
         }
+        self.emit(&mut ev, GE::PlayerTurn { player_id: next });
+        Ok(ev)
     }
 
-    fn previous_player(&self) -> usize {
-        if self.direction {
-            (self.current_player + self.players.len() - 1) % self.players.len()
-        } else {
-            (self.current_player + 1) % self.players.len()
+    fn check_no_pending_decision(&self) -> Result<(), RuleError> {
+        if !self.started {
+            return Err(RuleError::GameNotStarted);
+        }
+        if self.pending_draw_four.is_some() {
+            return Err(RuleError::DrawFourPending);
         }
+        if self.color_pending {
+            return Err(RuleError::ColorChoicePending);
+        }
+        Ok(())
+    }
+
+    // 不是 player_id 的回合就拒绝, 返回其座位
+    fn check_turn(&self, player_id: PlayerId) -> Result<usize, RuleError> {
+        let seat = self.active_seat(player_id).ok_or(RuleError::InvalidPlayer)?;
+        if seat != self.current {
+            return Err(RuleError::NotYourTurn);
+        }
+        Ok(seat)
     }
 
     pub fn play_card(
         &mut self, 
-        player_id: usize,
+        player_id: PlayerId,
+        card_idx: usize,
+        call_uno: bool,
+        color: Option<Color>,
+    ) -> Result<Vec<GE>, RuleError> {
+        self.play_card_with_target(player_id, card_idx, call_uno, color, None)
+    }
+
+    // color: 只有万能牌需要, 万能牌不带颜色会被拒绝
+    // swap_with: 7-0 房规下出 7 时交换手牌的对象, 不指定则与下家交换
+    pub fn play_card_with_target(
+        &mut self, 
+        player_id: PlayerId,
         card_idx: usize,
         call_uno: bool,
-        color: Color
-    ) -> Vec<GE> {
+        color: Option<Color>,
+        swap_with: Option<PlayerId>,
+    ) -> Result<Vec<GE>, RuleError> {
         let mut ev = Vec::new();
+        self.check_no_pending_decision()?;
 
-        // 校验玩家, 已在服务端校验
-        // if self.players.get(self.current_player).map(|p| p.id) != Some(player_id) {
-        //     ev.push(GE::GameError { message: format!("It's not Player {}'s turn!", player_id).to_string() });
-        //     return ev;
-        // }
+        // 校验玩家, 抢出房规下允许不在自己回合出牌
+        let seat = self.active_seat(player_id).ok_or(RuleError::InvalidPlayer)?;
+        let jump_in = seat != self.current;
+        if jump_in && !self.rules.jump_in {
+            return Err(RuleError::NotYourTurn);
+        }
 
-        let hand = self.players[self.current_player].display_hand();
+        let hand = self.players[seat].display_hand();
         if card_idx >= hand.len() {
-            ev.push(GE::GameError { message: 
-                "Invalid card index".to_string() });
-            return ev;
-        }
-        let card = hand[card_idx].clone();
-        let card = match card {
-            UnoCard::WildCard(_, wt) => UnoCard::WildCard(Some(color), wt),
-            _ => card,
+            return Err(RuleError::InvalidCardIndex);
+        }
+        if !jump_in && self.drawn_card.is_some_and(|drawn| drawn != card_idx) {
+            return Err(RuleError::MustPlayDrawnCard);
+        }
+        let card = match hand[card_idx] {
+            UnoCard::WildCard(_, wt) => {
+                UnoCard::WildCard(Some(color.ok_or(RuleError::ColorRequired)?), wt)
+            }
+            card => card,
         };
+        if jump_in && (self.stacked_draws > 0 || !identical_card(&card, &self.top_card)) {
+            return Err(RuleError::JumpInNotIdentical);
+        }
+        if self.stacked_draws > 0 && !can_stack_draw(&card, &self.top_card) {
+            return Err(RuleError::MustStackOrTake);
+        }
         if !valid_card(&card, &self.top_card) {
-            ev.push(GE::GameError { message: 
-                "Cannot play this card".to_string() });
-            return ev;
+            return Err(RuleError::CannotPlayCard);
+        }
+        if jump_in {
+            self.emit(&mut ev, GE::JumpedIn { player_id });
         }
         
         // 出牌
-        let _ = self.players[self.current_player].remove_card(card_idx).unwrap();
-        self.top_card = Some(card.clone());
-        ev.push(GE::CardPlayed { 
-            player_id: player_id, card: card.clone() });
-        ev.push(GE::TopCardChanged { top_card: self.top_card.
-            expect("Top card should be set").clone() });
+        self.emit(&mut ev, GE::CardPlayed { player_id, card, card_index: card_idx });
+        self.emit(&mut ev, GE::TopCardChanged { top_card: card });
+        let cards_left = self.players[seat].display_hand().len();
 
-
-        // 牌生效
+        // 牌生效, next 是接下来出牌的座位
+        let mut next = self.next_seat(seat);
         match card {
             UnoCard::ActionCard(_, act ) => {
                 match act {
                     Action::SKIP => {
-                        let skipped_player = self.next_player();
-                        ev.push(GE::PlayerSkipped { player_id: 
-                            self.players[skipped_player].id });
-                        self.current_player = self.next_player();
+                        self.emit(&mut ev, GE::PlayerSkipped { player_id: 
+                            self.players[next].id });
+                        next = self.next_seat(next);
                     },
 
                     Action::REVERSE => {
-                        self.change_direction();
-                        ev.push(GE::DirectionChanged { clockwise: self.direction });
+                        self.emit(&mut ev, GE::DirectionChanged { clockwise: !self.direction });
+                        next = self.next_seat(seat);
                     }, 
 
+                    Action::DRAWTWO if self.rules.stack_draws => {
+                        ev.extend(self.stack_draws(2, cards_left == 0, next));
+                    }
+
                     Action::DRAWTWO => {
-                        let affected_player = self.next_player();
-                        self.current_player = self.next_player();
-                        ev.push(GE::DrawTwoApplied { 
-                            target_player_id: self.players[affected_player].id });
-                        ev.extend( self.cards_distribution(affected_player, 2) );
+                        self.emit(&mut ev, GE::DrawTwoApplied { 
+                            target_player_id: self.players[next].id });
+                        ev.extend( self.cards_distribution(next, 2) );
+                        next = self.next_seat(next);
                     }
                 }
             }
 
-            UnoCard::WildCard(_, wt) => {
-                match wt {
-                    WildType::DRAWFOUR => {
-                        let affected_player = self.next_player();
-                        ev.push(GE::DrawFourApplied { 
-                            target_player_id: self.players[affected_player].id });
-                        ev.extend( self.cards_distribution(affected_player, 4) );
-                        self.current_player = self.next_player();
-                    }
-                    _ => { }
+            UnoCard::WildCard(_, WildType::DRAWFOUR) if self.rules.stack_draws => {
+                ev.extend(self.stack_draws(4, cards_left == 0, next));
+            }
+
+            UnoCard::WildCard(_, WildType::DRAWFOUR) => {
+                if cards_left == 0 {
+                    // 最后一张牌, 直接生效, 不再给质疑机会
+                    self.emit(&mut ev, GE::DrawFourApplied { 
+                        target_player_id: self.players[next].id });
+                    ev.extend( self.cards_distribution(next, 4) );
+                } else {
+                    // 被罚的玩家先决定质疑还是接受
+                    self.emit(&mut ev, GE::ChallengeWindow { 
+                        challenger_id: self.players[next].id,
+                        challenged_id: player_id });
                 }
             }
 
+            UnoCard::NumberCard(_, Number::SEVEN) if self.rules.seven_zero && cards_left > 0 => {
+                let other = swap_with
+                    .and_then(|o| self.active_seat(o))
+                    .filter(|&o| o != seat)
+                    .unwrap_or(next);
+                self.emit(&mut ev, GE::HandsSwapped { player_id, other_id: self.players[other].id });
+            }
+
+            UnoCard::NumberCard(_, Number::ZERO) if self.rules.seven_zero && cards_left > 0 => {
+                self.emit(&mut ev, GE::HandsRotated { clockwise: self.direction });
+            }
+
             _ => { }
         }
-        self.current_player = self.next_player();
 
-        // 检查是否有玩家获胜,并切换到下一个玩家
-        if self.players[player_id].display_hand().is_empty() {
-            ev.push(GE::GameOver { winner: player_id, scores: self.calculate_scores() });
-            self.started = false; // 标记结束，防止再接受动作
-            return ev;
+        // 检查是否有玩家获胜
+        if cards_left == 0 {
+            self.emit(&mut ev, GE::GameOver { winner: player_id, scores: self.calculate_scores() });
+            return Ok(ev);
         }
         
         // 检查玩家是否需要叫UNO, 并进行惩罚
-        if call_uno ^ (self.players[player_id].display_hand().len() == 1) {
-            ev.push(GE::UnoPenalty { player_id: player_id });
-            ev.extend(self.cards_distribution(player_id, 2));
+        if call_uno ^ (cards_left == 1) {
+            self.emit(&mut ev, GE::UnoPenalty { player_id });
+            ev.extend(self.cards_distribution(seat, 2));
         } else if call_uno{
-            ev.push(GE::UnoCalled { player_id: player_id });
+            self.emit(&mut ev, GE::UnoCalled { player_id });
         }
 
-        ev.push(GE::PlayerTurn { player_id: 
-            self.players[self.current_player].id });
-        
+        self.emit(&mut ev, GE::PlayerTurn { player_id: self.players[next].id });
+        Ok(ev)
+    }
+
+    // 叠加房规: 罚牌累加给下家, 下家接不住时一次摸完
+    // 出牌者已经出完牌时直接结算, 计入终局分数
+    fn stack_draws(&mut self, count: usize, last_card: bool, target: usize) -> Vec<GE> {
+        let mut ev = Vec::new();
+        let total = self.stacked_draws + count;
+        let target_id = self.players[target].id;
+        if last_card {
+            self.emit(&mut ev, GE::DrawStackTaken { player_id: target_id, count: total });
+            ev.extend(self.cards_distribution(target, total));
+        } else {
+            self.emit(&mut ev, GE::DrawStacked { target_player_id: target_id, total });
+        }
         ev
     }
 
-    pub fn draw_card(&mut self, player_id: usize) -> Vec<GE> {
+    pub fn draw_card(&mut self, player_id: PlayerId) -> Result<Vec<GE>, RuleError> {
         let mut ev = Vec::new();
-        
-        // 校验玩家
-        if self.players.get(self.current_player).map(|p| p.id) != Some(player_id) {
-            ev.push(GE::GameError { message: "It's not your turn!".to_string() });
-            return ev;
-        }
-
-        // 抽一张牌
-        let drawn_card = self.no_card_to_play();
-        match drawn_card {
-            Ok(drawn_card) => {
-                self.players[self.current_player].push_card(drawn_card.clone());
-                ev.push(GE::CardDraw { player_id, card: drawn_card });
-                if valid_card(&drawn_card, &self.top_card) {
-                    ev.push(GE::DrawnCardPlayable { 
-                        player_id: player_id,
-                    });
-                }
-                else {
-                    ev.extend(self.player_pass(player_id));
+        self.check_no_pending_decision()?;
+        let seat = self.check_turn(player_id)?;
+        if self.drawn_card.is_some() {
+            return Err(RuleError::AlreadyDrawn);
+        }
+
+        // 接不住叠加的罚牌: 一次摸完并跳过回合
+        if self.stacked_draws > 0 {
+            let count = self.stacked_draws;
+            self.emit(&mut ev, GE::DrawStackTaken { player_id, count });
+            ev.extend(self.cards_distribution(seat, count));
+            let next = self.next_player();
+            self.emit(&mut ev, GE::PlayerTurn { player_id: self.players[next].id });
+            return Ok(ev);
+        }
+
+        // 抽一张牌, 房规允许时一直摸到能出为止
+        let mut drawn_any = false;
+        loop {
+            match self.peek_deck(&mut ev) {
+                Some(card) => {
+                    drawn_any = true;
+                    self.emit(&mut ev, GE::CardDraw { player_id, card });
+                    if valid_card(&card, &self.top_card) {
+                        self.emit(&mut ev, GE::DrawnCardPlayable { player_id });
+                        return Ok(ev);
+                    }
+                    if !self.rules.draw_until_playable {
+                        break;
+                    }
                 }
-            }
-            Err(e) => {
-                ev.push(GE::GameError { message: e });
+                // 一张都没摸到就拒绝, 摸到过就停下来跳过
+                None if !drawn_any => return Err(RuleError::DeckEmpty),
+                None => break,
             }
         }
-        ev
+        ev.extend(self.pass_turn(player_id));
+        Ok(ev)
     }
 
-    pub fn player_pass(&mut self, player_id: usize) -> Vec<GE> {
-        let mut ev = Vec::new();
-        ev.push(GE::PlayerPassed { player_id: player_id });
-        self.current_player = self.next_player();
-        ev.push(GE::PlayerTurn { player_id: 
-            self.players[self.current_player].id });
+    // 摸牌后跳过; 摸到的牌不能出时 draw_card 会自动跳过
+    pub fn player_pass(&mut self, player_id: PlayerId) -> Result<Vec<GE>, RuleError> {
+        self.check_no_pending_decision()?;
+        self.check_turn(player_id)?;
+        if self.stacked_draws > 0 {
+            return Err(RuleError::MustStackOrTake);
+        }
+        if self.rules.forced_play && self.drawn_card.is_some() {
+            return Err(RuleError::ForcedPlay);
+        }
+        if self.drawn_card.is_none() && self.can_draw() {
+            return Err(RuleError::MustDrawBeforePass);
+        }
+        Ok(self.pass_turn(player_id))
+    }
 
+    fn pass_turn(&mut self, player_id: PlayerId) -> Vec<GE> {
+        let mut ev = Vec::new();
+        self.emit(&mut ev, GE::PlayerPassed { player_id });
+        let next = self.next_player();
+        self.emit(&mut ev, GE::PlayerTurn { player_id: self.players[next].id });
         ev
     }
 
     fn calculate_scores(&self) -> Vec<(String, i32)> {
         // 游戏结束，计算每个玩家的分数并公布排名
         let mut scores = Vec::new();
-        for player in &self.players {
+        for player in self.players.iter().filter(|p| !p.left) {
             let score: i32 = player.display_hand().iter().map(|card| card.get_value()).sum();
             scores.push((player.name.clone(), score));
         }
-        scores.sort_by(|a, b| a.1.cmp(&b.1)); // 按分数升序排序
+        scores.sort_by_key(|a| a.1); // 按分数升序排序
         scores
 
         // 打印美观的分数表
@@ -298,20 +759,49 @@ impl UnoGame {
         // println!("=============================================");
     }
 
-    // 挑战逻辑
-    // 如果玩家发现对手没有叫“UNO”，可以挑战
-    // 如果挑战成功，对手需要抽取两张牌
-    // 如果挑战失败，挑战者需要抽取两张牌
-    // 这里可以实现更复杂的逻辑
-    fn challenge(&mut self, challenger_id: usize, challenged_id: usize) -> Vec<GE> {
+    // +4 质疑逻辑
+    // 被 +4 的玩家可以质疑打出者是否手里还有与前一张顶牌同色的牌
+    // 如果质疑成功, 打出者抽4张, 质疑者正常出牌
+    // 如果质疑失败, 质疑者抽6张并跳过回合
+    pub fn challenge(
+        &mut self,
+        challenger_id: PlayerId,
+        challenged_id: PlayerId,
+    ) -> Result<Vec<GE>, RuleError> {
         let mut ev = Vec::new();
-        let challenge_successful = false;
-        if challenge_successful {
-            ev.push(GE::ChallengedSuccess { challenger_id, challenged_id });
-            ev.extend(self.cards_distribution(challenged_id, 2));
+        let pending = match self.pending_draw_four {
+            Some(p) if self.challenge_window() == Some((challenger_id, challenged_id)) => p,
+            Some(p) if self.players[p.target_seat].id != challenger_id => {
+                return Err(RuleError::NotYourTurn);
+            }
+            _ => return Err(RuleError::NoDrawFourToChallenge),
+        };
+        self.emit(&mut ev, GE::PlayerChallenged { challenger_id, challenged_id });
+        let next = if pending.bluffed {
+            self.emit(&mut ev, GE::ChallengedSuccess { challenger_id, challenged_id });
+            ev.extend(self.cards_distribution(pending.player_seat, 4));
+            self.current
         } else {
-            ev.extend(self.cards_distribution(challenger_id, 2));
-        }
-        ev
+            self.emit(&mut ev, GE::ChallengedFailed { challenger_id, challenged_id });
+            ev.extend(self.cards_distribution(pending.target_seat, 6));
+            self.next_player()
+        };
+        self.emit(&mut ev, GE::PlayerTurn { player_id: self.players[next].id });
+        Ok(ev)
+    }
+
+    // 不质疑, 接受 +4: 抽4张并跳过回合
+    pub fn accept_draw_four(&mut self, player_id: PlayerId) -> Result<Vec<GE>, RuleError> {
+        let mut ev = Vec::new();
+        let pending = match self.pending_draw_four {
+            Some(p) if self.players[p.target_seat].id == player_id => p,
+            Some(_) => return Err(RuleError::NotYourTurn),
+            None => return Err(RuleError::NoDrawFourToAccept),
+        };
+        self.emit(&mut ev, GE::DrawFourApplied { target_player_id: player_id });
+        ev.extend(self.cards_distribution(pending.target_seat, 4));
+        let next = self.next_player();
+        self.emit(&mut ev, GE::PlayerTurn { player_id: self.players[next].id });
+        Ok(ev)
     }
 }
@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Serialize, Deserialize};
+
+use crate::game::cards::Color;
+use crate::game::command::{Accepted, Command};
+use crate::game::error::RuleError;
+use crate::game::events::GameEvent as GE;
+use crate::game::player::PlayerId;
+use crate::game::rules::RuleSet;
+use crate::game::uno_game::UnoGame;
+
+// 多局比赛: 每局结束时赢家拿走其他人手牌的分数, 先到 target_score 的玩家赢得比赛
+// 庄家每局轮换, 庄家左手边的玩家先出
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Match {
+    game: UnoGame,
+    rules: RuleSet,
+    seed: u64, // 第 n 局用 seed + n 开局, 整场比赛可复现
+    players: Vec<(PlayerId, String)>, // 按加入顺序
+    totals: BTreeMap<PlayerId, i32>,
+    seating: Vec<PlayerId>, // 整场比赛不变的座位顺序
+    dealer: usize, // 庄家的座位
+    round: u32,
+    in_progress: bool, // 比赛进行中(包括两局之间)
+}
+
+impl Match {
+    pub fn new(rules: RuleSet) -> Match {
+        Self::with_seed(rand::thread_rng().gen(), rules)
+    }
+
+    pub fn with_seed(seed: u64, rules: RuleSet) -> Match {
+        Match {
+            game: UnoGame::with_seed(seed, rules.clone()),
+            rules,
+            seed,
+            players: Vec::new(),
+            totals: BTreeMap::new(),
+            seating: Vec::new(),
+            dealer: 0,
+            round: 0,
+            in_progress: false,
+        }
+    }
+
+    // 当前这一局, 两局之间保留上一局的最终状态
+    pub fn game(&self) -> &UnoGame {
+        &self.game
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    pub fn dealer(&self) -> PlayerId {
+        self.seating.get(self.dealer).copied().unwrap_or_default()
+    }
+
+    // 按加入顺序的累计分数
+    pub fn totals(&self) -> Vec<(String, i32)> {
+        self.players.iter()
+            .map(|(id, name)| (name.clone(), self.totals.get(id).copied().unwrap_or(0)))
+            .collect()
+    }
+
+    pub fn in_progress(&self) -> bool {
+        self.in_progress
+    }
+
+    // 换房规只能在比赛开始前或结束后, 会丢弃上一局的最终状态
+    pub fn set_rules(&mut self, rules: RuleSet) -> Result<Vec<GE>, RuleError> {
+        if self.in_progress {
+            return Err(RuleError::RulesLocked);
+        }
+        self.game = UnoGame::with_seed(self.seed, rules.clone());
+        self.rules = rules.clone();
+        Ok(vec![GE::RulesChanged { rules }])
+    }
+
+    // 开始下一局; 比赛已结束就从第一局重新开始
+    // players 按加入顺序, 没有开启 random_seats 时也按这个顺序入座
+    // 两局之间玩家名单变了不重开比赛: 离开的人连同分数一起去掉, 新来的人从 0 分坐到最后
+    pub fn start_round(&mut self, players: Vec<(PlayerId, String)>) -> Result<Vec<GE>, RuleError> {
+        if self.game.started {
+            return Err(RuleError::GameAlreadyStarted);
+        }
+        if players.is_empty() {
+            return Err(RuleError::NoPlayers);
+        }
+        // 新的座位和比分先算在局部变量里, 开局成功后才写回, 失败时比赛保持原样
+        let (seating, totals, dealer, round) = if !self.in_progress {
+            let mut seating: Vec<PlayerId> = players.iter().map(|(id, _)| *id).collect();
+            if self.rules.random_seats {
+                seating.shuffle(&mut StdRng::seed_from_u64(self.seed));
+            }
+            let totals = players.iter().map(|(id, _)| (*id, 0)).collect();
+            // 第一局让 0 号座位先出
+            let dealer = seating.len() - 1;
+            (seating, totals, dealer, 1)
+        } else {
+            let stays = |id: &PlayerId| players.iter().any(|(p, _)| p == id);
+            // 庄家顺延给原座位顺序上还在的下一个人
+            let n = self.seating.len();
+            let next_dealer = (1..=n).map(|i| self.seating[(self.dealer + i) % n]).find(|id| stays(id));
+            let mut seating: Vec<PlayerId> = self.seating.iter().copied().filter(stays).collect();
+            let mut totals = self.totals.clone();
+            totals.retain(|id, _| stays(id));
+            for (id, _) in &players {
+                if !seating.contains(id) {
+                    seating.push(*id);
+                    totals.insert(*id, 0);
+                }
+            }
+            let dealer = next_dealer
+                .and_then(|d| seating.iter().position(|&id| id == d))
+                .unwrap_or(seating.len() - 1);
+            (seating, totals, dealer, self.round + 1)
+        };
+        let mut game = UnoGame::with_seed(self.seed.wrapping_add(round as u64), self.rules.clone());
+        let seated: Vec<(PlayerId, String)> = seating.iter()
+            .filter_map(|id| players.iter().find(|(p, _)| p == id).cloned())
+            .collect();
+        let mut ev = game.init_round(seated, dealer + 1)?;
+        self.game = game;
+        self.seating = seating;
+        self.totals = totals;
+        self.dealer = dealer;
+        self.round = round;
+        self.players = players;
+        self.in_progress = true;
+        self.score(&mut ev);
+        Ok(ev)
+    }
+
+    pub fn play_card(
+        &mut self,
+        player_id: PlayerId,
+        card_idx: usize,
+        call_uno: bool,
+        color: Option<Color>,
+        swap_with: Option<PlayerId>,
+    ) -> Result<Accepted, RuleError> {
+        self.apply(Command::PlayCard {
+            player_id,
+            card_index: card_idx,
+            call_uno,
+            color,
+            swap_with,
+        })
+    }
+
+    pub fn draw_card(&mut self, player_id: PlayerId) -> Result<Accepted, RuleError> {
+        self.apply(Command::DrawCard { player_id })
+    }
+
+    pub fn player_pass(&mut self, player_id: PlayerId) -> Result<Accepted, RuleError> {
+        self.apply(Command::PassTurn { player_id })
+    }
+
+    pub fn challenge(
+        &mut self,
+        challenger_id: PlayerId,
+        challenged_id: PlayerId,
+    ) -> Result<Accepted, RuleError> {
+        self.apply(Command::Challenge { challenger_id, challenged_id })
+    }
+
+    pub fn accept_draw_four(&mut self, player_id: PlayerId) -> Result<Accepted, RuleError> {
+        self.apply(Command::AcceptDrawFour { player_id })
+    }
+
+    pub fn choose_color(&mut self, player_id: PlayerId, color: Color) -> Result<Accepted, RuleError> {
+        self.apply(Command::ChooseColor { player_id, color })
+    }
+
+    // 只剩一人时这一局直接结束, 照常计分
+    pub fn remove_player(&mut self, player_id: PlayerId) -> Result<Accepted, RuleError> {
+        self.apply(Command::Leave { player_id })
+    }
+
+    // 操作被接受时连同计分后的事件一起返回
+    fn apply(&mut self, command: Command) -> Result<Accepted, RuleError> {
+        let mut events = command.apply(&mut self.game)?;
+        self.score(&mut events);
+        Ok(Accepted { command, events })
+    }
+
+    // 一局结束时按官方规则计分, 追加 RoundOver / MatchOver
+    fn score(&mut self, ev: &mut Vec<GE>) {
+        let Some(winner) = ev.iter().find_map(|e| match e {
+            GE::GameOver { winner, .. } => Some(*winner),
+            _ => None,
+        }) else {
+            return;
+        };
+        let points: i32 = self.seating.iter()
+            .filter(|&&p| p != winner)
+            .flat_map(|&p| self.game.get_player_hand(p))
+            .map(|c| c.get_value())
+            .sum();
+        let Some(total) = self.totals.get_mut(&winner) else {
+            return;
+        };
+        *total += points;
+        let total = *total;
+        ev.push(GE::RoundOver {
+            round: self.round,
+            winner,
+            points,
+            totals: self.totals(),
+        });
+        if total >= self.rules.target_score {
+            self.in_progress = false;
+            ev.push(GE::MatchOver { winner, totals: self.totals() });
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub mod bot;
 pub mod game;
 pub mod ports;
-pub mod protocol;
\ No newline at end of file
+pub mod protocol;
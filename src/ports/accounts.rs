@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// Argon2id 的迭代次数, 每个账号单独记录, 以后调高不影响老账号登录
+pub const ARGON2_ROUNDS: u32 = 2;
+// Argon2id 占用的内存 (KiB)
+const ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+pub const MAX_NAME_LEN: usize = 24;
+pub const MIN_PASSWORD_LEN: usize = 6;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountError {
+    InvalidName,
+    WeakPassword,
+    NameTaken,
+    WrongPassword, // 账号不存在也报这个, 不暴露哪些名字注册过
+    Storage(String),
+}
+
+impl Display for AccountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountError::InvalidName => {
+                write!(f, "Account name must be 1-{} characters without control characters", MAX_NAME_LEN)
+            }
+            AccountError::WeakPassword => {
+                write!(f, "Password must be at least {} characters", MIN_PASSWORD_LEN)
+            }
+            AccountError::NameTaken => write!(f, "Account name is already registered"),
+            AccountError::WrongPassword => write!(f, "Wrong account name or password"),
+            AccountError::Storage(e) => write!(f, "Cannot save account: {}", e),
+        }
+    }
+}
+
+// Argon2id 摘要, 参数不合法 (比如文件被改坏) 时返回 None
+fn argon2id(password: &str, salt: &[u8], rounds: u32) -> Option<[u8; 32]> {
+    let params = Params::new(ARGON2_MEMORY_KIB, rounds, 1, Some(32)).ok()?;
+    let mut out = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut out)
+        .ok()?;
+    Some(out)
+}
+
+// 注册账号, 只保存加盐的 Argon2id 摘要
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub name: String,
+    salt: String, // 十六进制
+    hash: String, // 十六进制
+    rounds: u32,
+}
+
+impl Account {
+    // 计算摘要比较慢, 服务器应放到阻塞线程里做
+    pub fn new(name: &str, password: &str) -> Result<Account, AccountError> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LEN || name.chars().any(char::is_control) {
+            return Err(AccountError::InvalidName);
+        }
+        if password.chars().count() < MIN_PASSWORD_LEN {
+            return Err(AccountError::WeakPassword);
+        }
+        let salt: [u8; 16] = rand::thread_rng().gen();
+        let hash = argon2id(password, &salt, ARGON2_ROUNDS).expect("argon2 parameters are valid");
+        Ok(Account {
+            name: name.to_string(),
+            salt: to_hex(&salt),
+            hash: to_hex(&hash),
+            rounds: ARGON2_ROUNDS,
+        })
+    }
+
+    pub fn verify(&self, password: &str) -> bool {
+        let Some(salt) = from_hex(&self.salt) else {
+            return false;
+        };
+        let Some(hash) = argon2id(password, &salt, self.rounds).map(|h| to_hex(&h)) else {
+            return false;
+        };
+        // 逐字节比较完, 不因为提前返回泄露匹配长度
+        hash.len() == self.hash.len()
+            && hash.bytes().zip(self.hash.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+    }
+}
+
+/// 账号表, 整个文件是一个 JSON 对象; 没有路径时只保存在内存里
+pub struct AccountStore {
+    path: Option<PathBuf>,
+    accounts: BTreeMap<String, Account>,
+}
+
+impl AccountStore {
+    pub fn in_memory() -> AccountStore {
+        AccountStore {
+            path: None,
+            accounts: BTreeMap::new(),
+        }
+    }
+
+    // 文件不存在时从空表开始
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<AccountStore> {
+        let path = path.into();
+        let accounts = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(AccountStore {
+            path: Some(path),
+            accounts,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Account> {
+        self.accounts.get(name)
+    }
+
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.accounts.contains_key(name)
+    }
+
+    pub fn insert(&mut self, account: Account) -> Result<(), AccountError> {
+        if self.is_registered(&account.name) {
+            return Err(AccountError::NameTaken);
+        }
+        let name = account.name.clone();
+        self.accounts.insert(name.clone(), account);
+        if let Err(e) = self.save() {
+            self.accounts.remove(&name);
+            return Err(AccountError::Storage(e.to_string()));
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> io::Result<()> {
+        match &self.path {
+            Some(path) => write_atomic(path, &serde_json::to_vec_pretty(&self.accounts)?),
+            None => Ok(()),
+        }
+    }
+}
+
+// 先写临时文件再改名, 写到一半崩溃也不会留下半个文件
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
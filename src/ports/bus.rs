@@ -8,6 +8,12 @@ pub struct EventBus {
     handlers: Vec<Box<dyn EventHandler>>,
 }
 
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl EventBus {
     pub fn new() -> Self {
         EventBus {
@@ -1 +1,4 @@
-pub mod bus;
\ No newline at end of file
+pub mod accounts;
+pub mod bus;
+pub mod replay;
+pub mod stats;
@@ -0,0 +1,240 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::cards::UnoCard;
+use crate::game::events::GameEvent as GE;
+use crate::game::{PlayerId, RuleError, RuleSet, UnoGame};
+
+pub use crate::game::Command;
+
+// 回放文件格式的版本, ReplayLine / Command 的结构变了就加一
+pub const REPLAY_VERSION: u32 = 2;
+
+// 回放文件的第一行, 足够用 UnoGame::with_seed 重新开出同一局
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    pub version: u32,
+    pub seed: u64,
+    pub round: u32,
+    pub rules: RuleSet,
+    pub seating: Vec<(PlayerId, String)>, // 按座位顺序
+    pub first_player: usize, // 首位出牌的座位
+    pub deck: Vec<UnoCard>, // 发牌前的牌堆, 最后一张最先发出
+}
+
+impl ReplayHeader {
+    // 从一局开头的事件(GameCreated 和随后的入座)取出头部, 事件里没有开局时返回 None
+    pub fn from_events(round: u32, events: &[GE]) -> Option<ReplayHeader> {
+        let start = events.iter().position(|e| matches!(e, GE::GameCreated { .. }))?;
+        let GE::GameCreated { seed, rules, first_seat } = &events[start] else {
+            unreachable!()
+        };
+        let seating = events[start + 1..]
+            .iter()
+            .map_while(|e| match e {
+                GE::PlayerJoined { player_id, name } => Some((*player_id, name.clone())),
+                _ => None,
+            })
+            .collect();
+        Some(ReplayHeader {
+            version: REPLAY_VERSION,
+            seed: *seed,
+            round,
+            rules: rules.clone(),
+            seating,
+            first_player: *first_seat,
+            deck: UnoGame::with_seed(*seed, rules.clone()).deck().to_vec(),
+        })
+    }
+
+    // 按头部重新发牌, 返回发完牌的牌局
+    pub fn start(&self) -> Result<UnoGame, RuleError> {
+        let mut game = UnoGame::with_seed(self.seed, self.rules.clone());
+        game.init_round(self.seating.clone(), self.first_player)?;
+        Ok(game)
+    }
+}
+
+// 回放文件的一行
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum ReplayLine {
+    Header(ReplayHeader),
+    Command(Command),
+    Events(Vec<GE>),
+}
+
+/// 把每一局写成一个 JSON Lines 文件: 头部, 然后交替的操作和它产生的事件
+/// 服务器开局时 begin, 每个被接受的操作连同它产生的事件 record; 一局结束时关闭文件
+/// 操作和事件作为一个任务交给后台线程, 多个线程同时记录也不会交错; 调用方不会被磁盘阻塞
+#[derive(Clone)]
+pub struct ReplayRecorder {
+    tx: Sender<Job>,
+}
+
+enum Job {
+    Begin(ReplayHeader, Vec<GE>),
+    Step(Command, Vec<GE>),
+    Flush(Sender<()>),
+}
+
+impl ReplayRecorder {
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<ReplayRecorder> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || write_loop(&dir, rx));
+        Ok(ReplayRecorder { tx })
+    }
+
+    // 开始记录新的一局, 写入头部和发牌的事件; 上一局没写完的文件直接关闭
+    pub fn begin(&self, header: ReplayHeader, opening: &[GE]) {
+        let _ = self.tx.send(Job::Begin(header, opening.to_vec()));
+    }
+
+    pub fn record(&self, command: &Command, events: &[GE]) {
+        let _ = self.tx.send(Job::Step(command.clone(), events.to_vec()));
+    }
+
+    // 等后台线程写完之前交给它的内容
+    pub fn flush(&self) {
+        let (done, wait) = mpsc::channel();
+        if self.tx.send(Job::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+}
+
+// 写失败时放弃这一局的记录, 不影响牌局本身; 所有 ReplayRecorder 都丢掉后线程退出
+fn write_loop(dir: &Path, rx: Receiver<Job>) {
+    let mut file: Option<File> = None;
+    for job in rx {
+        match job {
+            Job::Begin(header, opening) => {
+                let ms = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+                let path = dir.join(format!("uno-{}-{}-r{}.jsonl", ms, header.seed, header.round));
+                file = File::create(&path)
+                    .and_then(|mut f| write_line(&mut f, &ReplayLine::Header(header)).map(|_| f))
+                    .map_err(|e| eprintln!("[ReplayRecorder] cannot create {}: {}", path.display(), e))
+                    .ok();
+                write_events(&mut file, opening);
+            }
+            Job::Step(command, events) => {
+                if let Some(f) = file.as_mut() {
+                    if let Err(e) = write_line(f, &ReplayLine::Command(command)) {
+                        eprintln!("[ReplayRecorder] write failed, stop recording: {}", e);
+                        file = None;
+                    }
+                }
+                write_events(&mut file, events);
+            }
+            Job::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+// 没有事件时不写这一行; 一局结束后关闭文件
+fn write_events(file: &mut Option<File>, events: Vec<GE>) {
+    if events.is_empty() {
+        return;
+    }
+    let game_over = events.iter().any(|e| matches!(e, GE::GameOver { .. }));
+    if let Some(f) = file.as_mut() {
+        if let Err(e) = write_line(f, &ReplayLine::Events(events)) {
+            eprintln!("[ReplayRecorder] write failed, stop recording: {}", e);
+            *file = None;
+        }
+    }
+    if game_over {
+        *file = None;
+    }
+}
+
+// 每行立即写入文件, 服务器崩溃时也能留下完整的前半局
+fn write_line(f: &mut File, line: &ReplayLine) -> io::Result<()> {
+    let mut buf = serde_json::to_vec(line)?;
+    buf.push(b'\n');
+    f.write_all(&buf)
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+pub struct ReplayStep {
+    pub command: Command,
+    pub events: Vec<GE>, // 录制时产生的事件, 含 Match 追加的计分
+}
+
+// 读回来的一局
+pub struct Replay {
+    pub header: ReplayHeader,
+    pub opening: Vec<GE>, // 发牌和第一张顶牌
+    pub steps: Vec<ReplayStep>,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Replay> {
+        Self::parse(BufReader::new(File::open(path)?))
+    }
+
+    pub fn parse(r: impl BufRead) -> io::Result<Replay> {
+        let mut lines = r.lines();
+        let first = lines.next().ok_or_else(|| invalid("empty replay".into()))??;
+        let ReplayLine::Header(header) = serde_json::from_str(&first)? else {
+            return Err(invalid("replay must start with a header".into()));
+        };
+        if header.version > REPLAY_VERSION {
+            return Err(invalid(format!(
+                "replay version {} is newer than supported version {}",
+                header.version, REPLAY_VERSION
+            )));
+        }
+        // 洗牌算法变了的话, 同一个种子开出的就不是录下来的那一局
+        if UnoGame::with_seed(header.seed, header.rules.clone()).deck() != header.deck.as_slice() {
+            return Err(invalid("deck order does not match the seed, replay was recorded by an incompatible build".into()));
+        }
+        let mut opening = Vec::new();
+        let mut steps: Vec<ReplayStep> = Vec::new();
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line)? {
+                ReplayLine::Header(_) => return Err(invalid("unexpected second header".into())),
+                ReplayLine::Command(command) => steps.push(ReplayStep { command, events: Vec::new() }),
+                ReplayLine::Events(ev) => match steps.last_mut() {
+                    Some(step) => step.events.extend(ev),
+                    None => opening.extend(ev),
+                },
+            }
+        }
+        Ok(Replay { header, opening, steps })
+    }
+
+    // 执行完前 step 个操作后的牌局, 0 是刚发完牌
+    pub fn game_at(&self, step: usize) -> Result<UnoGame, RuleError> {
+        let mut game = self.header.start()?;
+        for s in self.steps.iter().take(step) {
+            s.command.apply(&mut game)?;
+        }
+        Ok(game)
+    }
+
+    // 第 step 步录下的事件
+    pub fn events_at(&self, step: usize) -> &[GE] {
+        match step {
+            0 => &self.opening,
+            n => self.steps.get(n - 1).map_or(&[], |s| &s.events),
+        }
+    }
+}
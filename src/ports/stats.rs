@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::cards::{UnoCard, WildType};
+use crate::game::events::GameEvent as GE;
+use crate::game::PlayerId;
+use crate::ports::accounts::write_atomic;
+
+// 注册玩家的累计战绩
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerStats {
+    pub games_played: u32,
+    pub wins: u32,
+    pub penalty_points: i64, // 每局结束时手里剩下的分数之和
+    pub uno_penalties: u32,
+    pub draw_fours_played: u32,
+}
+
+impl PlayerStats {
+    // 平均每局剩牌的分数
+    pub fn average_penalty(&self) -> f64 {
+        if self.games_played == 0 {
+            return 0.0;
+        }
+        self.penalty_points as f64 / self.games_played as f64
+    }
+}
+
+/// 账号名 -> 战绩, 整个文件是一个 JSON 对象; 没有路径时只保存在内存里
+/// 文件由后台线程写, record 不会被磁盘阻塞
+pub struct StatsStore {
+    writer: Option<Sender<Job>>,
+    stats: BTreeMap<String, PlayerStats>,
+}
+
+enum Job {
+    Save(Vec<u8>),
+    Flush(Sender<()>),
+}
+
+impl StatsStore {
+    pub fn in_memory() -> StatsStore {
+        StatsStore {
+            writer: None,
+            stats: BTreeMap::new(),
+        }
+    }
+
+    // 文件不存在时从空表开始
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<StatsStore> {
+        let path = path.into();
+        let stats = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || write_loop(&path, rx));
+        Ok(StatsStore {
+            writer: Some(tx),
+            stats,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PlayerStats> {
+        self.stats.get(name)
+    }
+
+    // 统计一批牌局事件, accounts 是这一桌要记录的玩家 (pid -> 账号名), 其余玩家忽略
+    // 有变化时才把新内容交给后台线程写文件
+    pub fn record(&mut self, events: &[GE], accounts: &HashMap<PlayerId, String>) -> io::Result<()> {
+        let mut changed = false;
+        for e in events {
+            match e {
+                GE::UnoPenalty { player_id } => {
+                    if let Some(s) = self.entry(accounts, *player_id) {
+                        s.uno_penalties += 1;
+                        changed = true;
+                    }
+                }
+                GE::CardPlayed { player_id, card: UnoCard::WildCard(_, WildType::DRAWFOUR), .. } => {
+                    if let Some(s) = self.entry(accounts, *player_id) {
+                        s.draw_fours_played += 1;
+                        changed = true;
+                    }
+                }
+                // scores 里是打完这一局的玩家, 中途离开的不算
+                GE::GameOver { winner, scores } => {
+                    for (pid, name) in accounts {
+                        let Some((_, points)) = scores.iter().find(|(n, _)| n == name) else {
+                            continue;
+                        };
+                        let s = self.stats.entry(name.clone()).or_default();
+                        s.games_played += 1;
+                        s.penalty_points += *points as i64;
+                        if pid == winner {
+                            s.wins += 1;
+                        }
+                        changed = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !changed {
+            return Ok(());
+        }
+        if let Some(tx) = &self.writer {
+            let _ = tx.send(Job::Save(serde_json::to_vec_pretty(&self.stats)?));
+        }
+        Ok(())
+    }
+
+    // 等后台线程写完之前交给它的内容
+    pub fn flush(&self) {
+        let Some(tx) = &self.writer else { return };
+        let (done, wait) = mpsc::channel();
+        if tx.send(Job::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+
+    fn entry(&mut self, accounts: &HashMap<PlayerId, String>, pid: PlayerId) -> Option<&mut PlayerStats> {
+        let name = accounts.get(&pid)?;
+        Some(self.stats.entry(name.clone()).or_default())
+    }
+}
+
+// 积压的多次保存只写最新的一份; StatsStore 丢掉后线程退出
+fn write_loop(path: &Path, rx: Receiver<Job>) {
+    while let Ok(job) = rx.recv() {
+        let mut data = None;
+        let mut flushes = Vec::new();
+        for job in std::iter::once(job).chain(rx.try_iter()) {
+            match job {
+                Job::Save(d) => data = Some(d),
+                Job::Flush(done) => flushes.push(done),
+            }
+        }
+        if let Some(data) = data {
+            if let Err(e) = write_atomic(path, &data) {
+                eprintln!("[StatsStore] cannot save {}: {}", path.display(), e);
+            }
+        }
+        for done in flushes {
+            let _ = done.send(());
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+// 单条聊天消息的最大字符数
+pub const MAX_CHAT_LEN: usize = 200;
+
+// 预设的快捷表情, 客户端一键发送
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Emote {
+    Hello,
+    GoodGame,
+    WellPlayed,
+    Oops,
+    Thinking,
+    Uno,
+}
+
+impl Emote {
+    pub const ALL: [Emote; 6] = [
+        Emote::Hello,
+        Emote::GoodGame,
+        Emote::WellPlayed,
+        Emote::Oops,
+        Emote::Thinking,
+        Emote::Uno,
+    ];
+
+    pub fn text(&self) -> &'static str {
+        match self {
+            Emote::Hello => "👋 你好!",
+            Emote::GoodGame => "🤝 GG",
+            Emote::WellPlayed => "👏 打得好!",
+            Emote::Oops => "😅 失误了",
+            Emote::Thinking => "🤔 让我想想...",
+            Emote::Uno => "🔥 UNO!",
+        }
+    }
+}
+
+// 去掉首尾空白和控制字符, 拒绝空消息和超长消息
+pub fn sanitize_chat(text: &str) -> Result<String, String> {
+    let text: String = text.trim().chars().filter(|c| !c.is_control()).collect();
+    if text.is_empty() {
+        return Err("Chat message is empty".to_string());
+    }
+    if text.chars().count() > MAX_CHAT_LEN {
+        return Err(format!("Chat message longer than {} characters", MAX_CHAT_LEN));
+    }
+    Ok(text)
+}
@@ -1,36 +1,148 @@
+use crate::bot::BotDifficulty;
 use crate::game::cards::Color;
+use crate::game::{PlayerId, RuleSet};
+use crate::protocol::chat::Emote;
+use crate::protocol::codec::WireFormat;
+use crate::protocol::handshake::Capability;
 use serde::{Serialize, Deserialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 pub enum Client2Server {
+    // 连接后的第一条消息, 老客户端不发也能正常游戏
+    Hello {
+        protocol_version: u32,
+        client_name: String,
+        #[serde(default)]
+        capabilities: Vec<Capability>,
+        // 客户端能用的编码, 按偏好排序; 不填就一直用 JSON Lines
+        #[serde(default)]
+        formats: Vec<WireFormat>,
+    },
+    // 大厅: 列出所有房间, 加入前后都可以发
+    ListRooms,
+    // 新建房间并以玩家身份加入, 房间已存在时报错
+    CreateRoom {
+        room_id: String,
+        name: String,
+        #[serde(default = "default_max_players")]
+        max_players: usize,
+        #[serde(default)]
+        password: Option<String>,
+        #[serde(default)]
+        rules: Option<RuleSet>,
+    },
     JoinGame {
         room_id: String,
         name: String,
+        // 仅调试构建有效: 新建房间时使用固定种子
+        #[serde(default)]
+        seed: Option<u64>,
+        // 新建房间时使用的房规, 加入已有房间时忽略
+        #[serde(default)]
+        rules: Option<RuleSet>,
+        // 加入有密码的房间时需要
+        #[serde(default)]
+        password: Option<String>,
+    },
+    // 只读观战, 不占座位
+    Spectate {
+        room_id: String,
+        name: String,
+        // 上帝视角可以看到所有人的手牌, 只对房主或持有裁判口令的人生效
+        #[serde(default)]
+        god_view: bool,
+        #[serde(default)]
+        referee_key: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+    },
+    // 登录注册账号, 之后加入房间一律用账号名; register 为真时先注册
+    Login {
+        name: String,
+        password: String,
+        #[serde(default)]
+        register: bool,
+    },
+    // 查询注册玩家的战绩, 加入前后都可以发
+    GetStats {
+        name: String,
+    },
+    // 断线后用 Welcome 中的 session_id 重新接入原座位
+    Resume {
+        session_id: String,
     },
     StartGame {
-        player_id: usize,
+        player_id: PlayerId,
+    },
+    // 开局前往房间里加一个电脑玩家
+    AddBot {
+        difficulty: BotDifficulty,
     },
 
     PlayCard {
-        player_id: usize,
+        player_id: PlayerId,
         card_index: usize,
-        color: Color,
+        // 只有万能牌需要, 万能牌不带颜色会被拒绝
+        #[serde(default)]
+        color: Option<Color>,
         call_uno: bool,
+        // 7-0 房规下出 7 时选择交换手牌的玩家
+        #[serde(default)]
+        swap_with: Option<PlayerId>,
     },
     DrawCard {
-        player_id: usize,
+        player_id: PlayerId,
         count: usize,
     },
+    // 第一张顶牌是万能牌时由首位玩家选颜色
+    ChooseColor {
+        player_id: PlayerId,
+        color: Color,
+    },
     PassTurn {
-        player_id: usize,
+        player_id: PlayerId,
     },
     ChallengeWildDrawFour {
-        challenger_id: usize,
-        challenged_id: usize,
+        challenger_id: PlayerId,
+        challenged_id: PlayerId,
+    },
+    AcceptWildDrawFour {
+        player_id: PlayerId,
     },
 
     LeaveGame {
-        player_id: usize,
+        player_id: PlayerId,
+    },
+
+    // 房主管理命令, 只有房主可以发
+    KickPlayer {
+        player_id: PlayerId,
+    },
+    TransferHost {
+        player_id: PlayerId,
+    },
+    // 锁定后不再接受新玩家加入, 观战和断线重连不受影响
+    LockRoom {
+        locked: bool,
     },
+    // 只能在比赛开始前或结束后修改
+    SetRules {
+        rules: RuleSet,
+    },
+
+    // 房间内聊天, 玩家和观战者都可以发
+    Chat {
+        text: String,
+    },
+    SendEmote {
+        emote: Emote,
+    },
+}
+
+// UNO 官方建议 2-10 人
+pub const MAX_PLAYERS: usize = 10;
+
+fn default_max_players() -> usize {
+    MAX_PLAYERS
 }
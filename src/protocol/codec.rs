@@ -0,0 +1,176 @@
+use std::io::{self, Read};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::protocol::handshake::from_tagged;
+
+// 单帧最大字节数, 防止对方发来超大的长度前缀把内存吃光
+pub const MAX_FRAME_LEN: usize = 1 << 20;
+
+// 连接上使用的编码; 握手(Hello / HelloAck)总是 JSON Lines, 之后双方切换到协商结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WireFormat {
+    // 每行一条 JSON, 默认编码, 方便调试
+    #[default]
+    Json,
+    // 4 字节大端长度前缀 + MessagePack
+    MsgPack,
+    // 对方版本更新, 本端还不认识的编码
+    #[serde(other)]
+    Unknown,
+}
+
+// 从连接上解出的一条消息
+pub enum Frame<T> {
+    Msg(T),
+    // 对方版本更新, 本端不认识的消息类型, 跳过即可
+    Unknown,
+    // 帧是完整的, 但内容解不出来
+    Invalid(String),
+}
+
+/// 消息的编解码和分帧, 连接两端用同一个实现
+pub trait Codec {
+    /// 只编码消息内容, 不含分帧; WebSocket 一帧就是一条消息, 直接用这个
+    fn encode_payload<T: Serialize>(&self, msg: &T) -> io::Result<Vec<u8>>;
+    fn decode_payload<T: DeserializeOwned>(&self, payload: &[u8]) -> Frame<T>;
+    /// 编码并加上分帧, 追加到 dst
+    fn encode<T: Serialize>(&self, msg: &T, dst: &mut Vec<u8>) -> io::Result<()>;
+    /// 从 src 开头切下完整的一帧, 数据还不够一帧时返回 Ok(None)
+    fn split_frame(&self, src: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>>;
+
+    /// 从缓冲区取出并解码下一条消息
+    fn decode<T: DeserializeOwned>(&self, src: &mut Vec<u8>) -> io::Result<Option<Frame<T>>> {
+        Ok(self.split_frame(src)?.map(|payload| self.decode_payload(&payload)))
+    }
+}
+
+fn to_frame<T: DeserializeOwned, E: std::fmt::Display>(value: Result<Value, E>) -> Frame<T> {
+    match value.map_err(|e| e.to_string()).and_then(|v| from_tagged(v).map_err(|e| e.to_string())) {
+        Ok(Some(msg)) => Frame::Msg(msg),
+        Ok(None) => Frame::Unknown,
+        Err(e) => Frame::Invalid(e),
+    }
+}
+
+fn too_long() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "frame too long")
+}
+
+pub struct JsonLines;
+
+impl Codec for JsonLines {
+    fn encode_payload<T: Serialize>(&self, msg: &T) -> io::Result<Vec<u8>> {
+        Ok(serde_json::to_vec(msg)?)
+    }
+
+    fn decode_payload<T: DeserializeOwned>(&self, payload: &[u8]) -> Frame<T> {
+        to_frame(serde_json::from_slice(payload))
+    }
+
+    fn encode<T: Serialize>(&self, msg: &T, dst: &mut Vec<u8>) -> io::Result<()> {
+        serde_json::to_writer(&mut *dst, msg)?;
+        dst.push(b'\n');
+        Ok(())
+    }
+
+    fn split_frame(&self, src: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+        let Some(pos) = src.iter().position(|&b| b == b'\n') else {
+            return if src.len() > MAX_FRAME_LEN { Err(too_long()) } else { Ok(None) };
+        };
+        let mut line: Vec<u8> = src.drain(..=pos).collect();
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(Some(line))
+    }
+}
+
+pub struct MsgPack;
+
+impl Codec for MsgPack {
+    // 结构体按字段名编码, 服务端的带标签枚举和 #[serde(default)] 字段都依赖这一点
+    fn encode_payload<T: Serialize>(&self, msg: &T) -> io::Result<Vec<u8>> {
+        rmp_serde::to_vec_named(msg).map_err(io::Error::other)
+    }
+
+    fn decode_payload<T: DeserializeOwned>(&self, payload: &[u8]) -> Frame<T> {
+        to_frame(rmp_serde::from_slice(payload))
+    }
+
+    fn encode<T: Serialize>(&self, msg: &T, dst: &mut Vec<u8>) -> io::Result<()> {
+        let payload = self.encode_payload(msg)?;
+        if payload.len() > MAX_FRAME_LEN {
+            return Err(too_long());
+        }
+        dst.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        dst.extend_from_slice(&payload);
+        Ok(())
+    }
+
+    fn split_frame(&self, src: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+        let Some(len) = src.first_chunk::<4>().map(|b| u32::from_be_bytes(*b) as usize) else {
+            return Ok(None);
+        };
+        if len > MAX_FRAME_LEN {
+            return Err(too_long());
+        }
+        if src.len() < 4 + len {
+            return Ok(None);
+        }
+        Ok(Some(src.drain(..4 + len).skip(4).collect()))
+    }
+}
+
+// 按协商结果选择实现, 不认识的编码按 JSON 处理
+impl Codec for WireFormat {
+    fn encode_payload<T: Serialize>(&self, msg: &T) -> io::Result<Vec<u8>> {
+        match self {
+            WireFormat::MsgPack => MsgPack.encode_payload(msg),
+            _ => JsonLines.encode_payload(msg),
+        }
+    }
+
+    fn decode_payload<T: DeserializeOwned>(&self, payload: &[u8]) -> Frame<T> {
+        match self {
+            WireFormat::MsgPack => MsgPack.decode_payload(payload),
+            _ => JsonLines.decode_payload(payload),
+        }
+    }
+
+    fn encode<T: Serialize>(&self, msg: &T, dst: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            WireFormat::MsgPack => MsgPack.encode(msg, dst),
+            _ => JsonLines.encode(msg, dst),
+        }
+    }
+
+    fn split_frame(&self, src: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+        match self {
+            WireFormat::MsgPack => MsgPack.split_frame(src),
+            _ => JsonLines.split_frame(src),
+        }
+    }
+}
+
+/// 阻塞读取下一条消息, 对方关闭连接时返回 Ok(None); 没用完的字节留在 buf 里
+pub fn read_frame<T: DeserializeOwned>(
+    codec: &impl Codec,
+    r: &mut impl Read,
+    buf: &mut Vec<u8>,
+) -> io::Result<Option<Frame<T>>> {
+    let mut chunk = [0u8; 4096];
+    loop {
+        if let Some(frame) = codec.decode(buf)? {
+            return Ok(Some(frame));
+        }
+        let n = r.read(&mut chunk)?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
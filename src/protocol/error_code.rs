@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::RuleError;
+
+// ServerError 附带的错误码, 客户端按错误码处理, message 只给人看
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ErrorCode {
+    // 连接 / 协议
+    BadJson,
+    UnsupportedVersion,
+    NotJoined,
+    AlreadyJoined,
+    PlayerMismatch,
+    SessionExpired,
+    RateLimited,
+    Unsupported,
+    // 房间
+    RoomFull,
+    RoomLocked,
+    RoomExists,
+    RoomNotFound,
+    WrongPassword,
+    InvalidRoomConfig,
+    NotHost,
+    SpectatorCannotPlay,
+    InvalidChat,
+    // 账号
+    LoginFailed,
+    NameTaken,
+    NameReserved,
+    InvalidAccount,
+    // 牌局规则, 与 RuleError 一一对应
+    GameNotStarted,
+    GameAlreadyStarted,
+    NoPlayers,
+    InvalidPlayer,
+    NotYourTurn,
+    InvalidCardIndex,
+    IllegalCard,
+    ColorRequired,
+    MustPlayDrawnCard,
+    ForcedPlay,
+    AlreadyDrawn,
+    MustDrawBeforePass,
+    JumpInNotIdentical,
+    MustStackOrTake,
+    DrawFourPending,
+    ColorChoicePending,
+    NoColorToChoose,
+    NoDrawFourToChallenge,
+    NoDrawFourToAccept,
+    DeckEmpty,
+    RulesLocked,
+    // 不是错误, 只是提示
+    Notice,
+    // 对方版本更新, 本端还不认识的错误码
+    #[serde(other)]
+    Unknown,
+}
+
+impl From<RuleError> for ErrorCode {
+    fn from(e: RuleError) -> Self {
+        match e {
+            RuleError::GameNotStarted => ErrorCode::GameNotStarted,
+            RuleError::GameAlreadyStarted => ErrorCode::GameAlreadyStarted,
+            RuleError::NoPlayers => ErrorCode::NoPlayers,
+            RuleError::InvalidPlayer => ErrorCode::InvalidPlayer,
+            RuleError::NotYourTurn => ErrorCode::NotYourTurn,
+            RuleError::InvalidCardIndex => ErrorCode::InvalidCardIndex,
+            RuleError::CannotPlayCard => ErrorCode::IllegalCard,
+            RuleError::ColorRequired => ErrorCode::ColorRequired,
+            RuleError::MustPlayDrawnCard => ErrorCode::MustPlayDrawnCard,
+            RuleError::ForcedPlay => ErrorCode::ForcedPlay,
+            RuleError::AlreadyDrawn => ErrorCode::AlreadyDrawn,
+            RuleError::MustDrawBeforePass => ErrorCode::MustDrawBeforePass,
+            RuleError::JumpInNotIdentical => ErrorCode::JumpInNotIdentical,
+            RuleError::MustStackOrTake => ErrorCode::MustStackOrTake,
+            RuleError::DrawFourPending => ErrorCode::DrawFourPending,
+            RuleError::ColorChoicePending => ErrorCode::ColorChoicePending,
+            RuleError::NoColorToChoose => ErrorCode::NoColorToChoose,
+            RuleError::NoDrawFourToChallenge => ErrorCode::NoDrawFourToChallenge,
+            RuleError::NoDrawFourToAccept => ErrorCode::NoDrawFourToAccept,
+            RuleError::DeckEmpty => ErrorCode::DeckEmpty,
+            RuleError::RulesLocked => ErrorCode::RulesLocked,
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+// 协议版本, 消息格式有不兼容的改动时加一
+pub const PROTOCOL_VERSION: u32 = 1;
+// 服务器还接受的最低客户端版本
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+// 可选功能, 握手时双方各自声明支持哪些
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Capability {
+    Chat,
+    Challenge,
+    Reconnect,
+    Lobby,
+    Bots,
+    HostControls,
+    Spectate,
+    Accounts,
+    // 对方声明了本端还不认识的功能
+    #[serde(other)]
+    Unknown,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 8] = [
+        Capability::Chat,
+        Capability::Challenge,
+        Capability::Reconnect,
+        Capability::Lobby,
+        Capability::Bots,
+        Capability::HostControls,
+        Capability::Spectate,
+        Capability::Accounts,
+    ];
+}
+
+// 消息外层, 先只取出类型标签
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(rename = "type")]
+    tag: String,
+}
+
+/// 解析一行消息; 对方发来本端不认识的消息类型时返回 Ok(None), 由调用方记录后跳过
+/// 认识的消息里有不认识的取值(比如没有的颜色)仍然是错误
+pub fn decode<T: DeserializeOwned>(line: &str) -> serde_json::Result<Option<T>> {
+    from_tagged(serde_json::from_str(line)?)
+}
+
+// 同 decode, 用于已经解析成 Value 的消息, 其他编码也先转成 Value 再走这里
+pub(crate) fn from_tagged<T: DeserializeOwned>(value: Value) -> serde_json::Result<Option<T>> {
+    let Envelope { tag } = Envelope::deserialize(&value)?;
+    match serde_json::from_value(value) {
+        Ok(msg) => Ok(Some(msg)),
+        Err(_) if !known_tag::<T>(json!({ "type": tag })) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// 用只有标签的消息试解析: 只有标签本身不认识时才会报 unknown variant,
+// 认识的标签最多报缺内容, 不会被内容里的取值误判
+pub(crate) fn known_tag<T: DeserializeOwned>(probe: Value) -> bool {
+    match serde_json::from_value::<T>(probe) {
+        Ok(_) => true,
+        Err(e) => !e.to_string().starts_with("unknown variant"),
+    }
+}
@@ -1,5 +1,13 @@
+pub mod chat;
 pub mod client2server;
+pub mod codec;
+pub mod error_code;
+pub mod handshake;
 pub mod server2client;
 
+pub use chat::Emote;
 pub use client2server::Client2Server;
-pub use server2client::Server2Client;
\ No newline at end of file
+pub use codec::{Codec, Frame, WireFormat};
+pub use error_code::ErrorCode;
+pub use handshake::{decode, Capability, PROTOCOL_VERSION};
+pub use server2client::{RoomInfo, Server2Client};
\ No newline at end of file
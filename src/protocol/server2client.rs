@@ -1,27 +1,102 @@
-use serde::{Serialize, Deserialize};
-use crate::game::{events::GameEvent, UnoCard};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use crate::game::{events::GameEvent, PlayerId, RuleSet, UnoCard};
+use crate::ports::stats::PlayerStats;
+use crate::protocol::handshake::known_tag;
+use crate::protocol::{Capability, ErrorCode, WireFormat};
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 pub enum Server2Client {
+    // 回复 Hello, 带上服务器的协议版本和支持的功能
+    HelloAck {
+        protocol_version: u32,
+        server_name: String,
+        capabilities: Vec<Capability>,
+        // 这条消息之后双方改用的编码
+        #[serde(default)]
+        format: WireFormat,
+    },
     Welcome {
-        player_id: usize,
+        player_id: PlayerId,
         session_id: String,
     },
+    // 回复 Login
+    LoggedIn {
+        name: String,
+    },
+    // 回复 GetStats, 名字没有注册时 stats 为空
+    Stats {
+        name: String,
+        stats: Option<PlayerStats>,
+    },
+    // 以观战者身份进入房间
+    Spectating {
+        god_view: bool,
+    },
     SharedState {
-        players_cards_count: Vec<(String, usize)>, // (name, cards_count)
+        players_cards_count: Vec<(PlayerId, String, usize)>, // 按座位顺序的 (id, name, cards_count)
         top_card: Option<UnoCard>,
-        current_player: usize,
+        current_player: PlayerId,
         clockwise: bool,
+        rules: RuleSet,
     },
     PlayerState {
-        player_id: usize,
+        player_id: PlayerId,
         hand: Vec<UnoCard>,
     },
+    // 回合倒计时, deadline_ms 为剩余毫秒数, 超时服务器会代为摸牌并跳过
+    TurnDeadline {
+        player_id: PlayerId,
+        deadline_ms: u64,
+    },
     
-    Events(Vec<GameEvent>),
+    // 回复 ListRooms
+    RoomList {
+        rooms: Vec<RoomInfo>,
+    },
+    // 不认识的事件单独跳过, 同一批里的其他事件照常处理
+    Events(#[serde(deserialize_with = "known_events")] Vec<GameEvent>),
+    // ts 为服务器时间戳(毫秒)
+    ChatMessage {
+        from: String,
+        text: String,
+        ts: u64,
+    },
     ServerError {
+        code: ErrorCode,
         message: String,
     },
 
-}
\ No newline at end of file
+}
+
+// 大厅里看到的房间概况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomInfo {
+    pub room_id: String,
+    pub players: usize,
+    pub max_players: usize,
+    pub started: bool,
+    pub rules: RuleSet,
+    pub has_password: bool,
+    pub locked: bool,
+}
+
+fn known_events<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<GameEvent>, D::Error> {
+    let mut events = Vec::new();
+    for v in Vec::<Value>::deserialize(d)? {
+        // 事件是外部标签: {"名字": 内容}, 没有内容的只有名字
+        let tag = match &v {
+            Value::Object(m) if m.len() == 1 => m.keys().next().cloned(),
+            Value::String(s) => Some(s.clone()),
+            _ => None,
+        };
+        match serde_json::from_value(v) {
+            Ok(e) => events.push(e),
+            Err(_) if tag.is_some_and(|t| !known_tag::<GameEvent>(Value::String(t))) => {}
+            Err(e) => return Err(D::Error::custom(e)),
+        }
+    }
+    Ok(events)
+}
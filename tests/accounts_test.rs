@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use uno::game::cards::{Color, UnoCard, WildType};
+use uno::game::events::GameEvent as GE;
+use uno::game::PlayerId;
+use uno::ports::accounts::{Account, AccountError, AccountStore};
+use uno::ports::stats::{PlayerStats, StatsStore};
+
+fn temp_file(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("uno-{}-{}.json", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[cfg(test)]
+mod accounts_tests {
+    use super::*;
+
+    #[test]
+    fn test_register_and_reload() {
+        assert!(matches!(Account::new("  ", "secret1"), Err(AccountError::InvalidName)));
+        assert!(matches!(Account::new("Alice", "123"), Err(AccountError::WeakPassword)));
+
+        let path = temp_file("accounts");
+        let mut store = AccountStore::open(&path).unwrap();
+        let account = Account::new(" Alice ", "secret1").unwrap();
+        assert_eq!(account.name, "Alice");
+        store.insert(account).unwrap();
+        assert_eq!(store.insert(Account::new("Alice", "other12").unwrap()), Err(AccountError::NameTaken));
+
+        // 重新打开后密码仍然能校验
+        let store = AccountStore::open(&path).unwrap();
+        let alice = store.get("Alice").unwrap();
+        assert!(alice.verify("secret1"));
+        assert!(!alice.verify("secret2"));
+        assert!(!store.is_registered("Bob"));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_record_stats() {
+        let path = temp_file("stats");
+        let mut store = StatsStore::open(&path).unwrap();
+        let accounts = HashMap::from([(PlayerId(0), "Alice".to_string()), (PlayerId(2), "Carol".to_string())]);
+        let draw_four = UnoCard::WildCard(Some(Color::RED), WildType::DRAWFOUR);
+        let events = vec![
+            GE::CardPlayed { player_id: PlayerId(0), card: draw_four, card_index: 0 },
+            GE::CardPlayed { player_id: PlayerId(1), card: draw_four, card_index: 0 },
+            GE::UnoPenalty { player_id: PlayerId(2) },
+            GE::GameOver {
+                winner: PlayerId(0),
+                scores: vec![("Alice".into(), 0), ("Bob".into(), 20), ("Carol".into(), 45)],
+            },
+        ];
+        store.record(&events, &accounts).unwrap();
+        let round2 = vec![GE::GameOver { winner: PlayerId(1), scores: vec![("Alice".into(), 11), ("Carol".into(), 5)] }];
+        store.record(&round2, &accounts).unwrap();
+        store.flush();
+
+        // 只记录账号玩家, Bob 是游客
+        let store = StatsStore::open(&path).unwrap();
+        assert_eq!(
+            store.get("Alice"),
+            Some(&PlayerStats { games_played: 2, wins: 1, penalty_points: 11, uno_penalties: 0, draw_fours_played: 1 })
+        );
+        let carol = store.get("Carol").unwrap();
+        assert_eq!((carol.games_played, carol.wins, carol.uno_penalties), (2, 0, 1));
+        assert_eq!(carol.average_penalty(), 25.0);
+        assert!(store.get("Bob").is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+}
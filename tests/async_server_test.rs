@@ -0,0 +1,123 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{connect, recv, send, start_server};
+use uno::bot::BotDifficulty;
+use uno::game::RuleSet;
+use uno::protocol::{Client2Server, ErrorCode, Server2Client};
+
+#[cfg(test)]
+mod async_server_tests {
+    use super::*;
+
+    // 电脑思考时房间照常处理聊天和大厅查询
+    #[tokio::test]
+    async fn test_room_responsive_while_bots_play() {
+        let (_server, addr) = start_server(env!("CARGO_BIN_EXE_async_server"), &[]);
+        let (mut lines, mut w) = connect(&addr).await;
+        // 开启回合限时, 用 TurnDeadline 判断轮到自己
+        let rules = RuleSet { random_seats: false, first_card_effects: false, turn_timeout_secs: 30, ..RuleSet::default() };
+        send(&mut w, &Client2Server::CreateRoom {
+            room_id: "电脑".into(),
+            name: "Alice".into(),
+            max_players: 6,
+            password: None,
+            rules: Some(rules),
+        })
+        .await;
+        let Server2Client::Welcome { player_id, .. } = recv(&mut lines).await else {
+            panic!("expected Welcome");
+        };
+        for _ in 0..5 {
+            send(&mut w, &Client2Server::AddBot { difficulty: BotDifficulty::Easy }).await;
+        }
+        send(&mut w, &Client2Server::StartGame { player_id }).await;
+        while !matches!(recv(&mut lines).await, Server2Client::TurnDeadline { player_id: p, .. } if p == player_id) {}
+
+        // 摸一张后跳过, 接下来五个电脑依次行动
+        send(&mut w, &Client2Server::DrawCard { player_id, count: 1 }).await;
+        send(&mut w, &Client2Server::PassTurn { player_id }).await;
+        let sent = Instant::now();
+        send(&mut w, &Client2Server::Chat { text: "hi".into() }).await;
+        while !matches!(recv(&mut lines).await, Server2Client::ChatMessage { .. }) {}
+        assert!(sent.elapsed() < Duration::from_secs(1));
+
+        let (mut lobby, mut lw) = connect(&addr).await;
+        let sent = Instant::now();
+        send(&mut lw, &Client2Server::ListRooms).await;
+        let Server2Client::RoomList { rooms } = recv(&mut lobby).await else {
+            panic!("expected RoomList");
+        };
+        assert!(sent.elapsed() < Duration::from_secs(1));
+        assert_eq!(rooms.len(), 1);
+    }
+
+    // 上帝视角只给登录后开房观战的房主或裁判, 同一账号入座后收回
+    #[tokio::test]
+    async fn test_god_view_needs_watching_host() {
+        let (_server, addr) = start_server(env!("CARGO_BIN_EXE_async_server"), &[]);
+        let spectate = |name: &str| Client2Server::Spectate {
+            room_id: "上帝".into(),
+            name: name.into(),
+            god_view: true,
+            referee_key: None,
+            password: None,
+        };
+        let login = |register: bool| Client2Server::Login { name: "Host".into(), password: "secret1".into(), register };
+
+        // 不存在的房间不能观战, 也不会因此建房
+        let (mut guest, mut gw) = connect(&addr).await;
+        send(&mut gw, &spectate("Eve")).await;
+        assert!(matches!(recv(&mut guest).await, Server2Client::ServerError { code: ErrorCode::RoomNotFound, .. }));
+
+        // 房主建房后离座, 回到大厅再观战
+        let (mut host, mut hw) = connect(&addr).await;
+        send(&mut hw, &login(true)).await;
+        assert!(matches!(recv(&mut host).await, Server2Client::LoggedIn { .. }));
+        send(&mut hw, &Client2Server::CreateRoom {
+            room_id: "上帝".into(),
+            name: "Host".into(),
+            max_players: 4,
+            password: None,
+            rules: None,
+        })
+        .await;
+        let Server2Client::Welcome { player_id, .. } = recv(&mut host).await else {
+            panic!("expected Welcome");
+        };
+        send(&mut hw, &Client2Server::LeaveGame { player_id }).await;
+
+        send(&mut gw, &spectate("Eve")).await;
+        assert!(matches!(recv(&mut guest).await, Server2Client::Spectating { god_view: false }));
+        send(&mut hw, &spectate("Host")).await;
+        loop {
+            if let Server2Client::Spectating { god_view } = recv(&mut host).await {
+                assert!(god_view);
+                break;
+            }
+        }
+
+        // 同一账号从另一个连接入座
+        let (mut seat, mut sw) = connect(&addr).await;
+        send(&mut sw, &login(false)).await;
+        assert!(matches!(recv(&mut seat).await, Server2Client::LoggedIn { .. }));
+        send(&mut sw, &Client2Server::JoinGame {
+            room_id: "上帝".into(),
+            name: "Host".into(),
+            seed: None,
+            rules: None,
+            password: None,
+        })
+        .await;
+        assert!(matches!(recv(&mut seat).await, Server2Client::Welcome { .. }));
+        while !matches!(recv(&mut host).await, Server2Client::Spectating { god_view: false }) {}
+
+        // 入座后再观战也拿不到上帝视角
+        let (mut again, mut aw) = connect(&addr).await;
+        send(&mut aw, &login(false)).await;
+        assert!(matches!(recv(&mut again).await, Server2Client::LoggedIn { .. }));
+        send(&mut aw, &spectate("Host")).await;
+        assert!(matches!(recv(&mut again).await, Server2Client::Spectating { god_view: false }));
+    }
+}
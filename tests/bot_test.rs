@@ -0,0 +1,124 @@
+use uno::bot::*;
+use uno::game::events::GameEvent as GE;
+use uno::game::*;
+
+// 所有座位都交给机器人, 打完一整局
+fn play_bot_game(seed: u64, rules: RuleSet, mut bots: Vec<Box<dyn Strategy>>) -> Vec<GE> {
+    let mut game = UnoGame::with_seed(seed, rules);
+    let names = (0..bots.len()).map(|i| format!("Bot-{}", i)).collect();
+    let mut ev = game.init_game(names).unwrap();
+    for _ in 0..5000 {
+        if !game.started {
+            return ev;
+        }
+        if let Some((challenger, challenged)) = game.challenge_window() {
+            let view = BotView::from_game(&game, challenger);
+            if bots[challenger.index()].challenge_draw_four(&view) {
+                ev.extend(game.challenge(challenger, challenged).unwrap());
+            } else {
+                ev.extend(game.accept_draw_four(challenger).unwrap());
+            }
+            continue;
+        }
+        let pid = game.current_player();
+        let view = BotView::from_game(&game, pid);
+        if game.color_choice() == Some(pid) {
+            ev.extend(game.choose_color(pid, bots[pid.index()].choose_color(&view)).unwrap());
+            continue;
+        }
+        // 机器人不应该做出被规则拒绝的动作
+        let res = match bots[pid.index()].decide(&view) {
+            BotAction::Play { card_index, color, call_uno } => {
+                game.play_card(pid, card_index, call_uno, Some(color))
+            }
+            BotAction::Draw => game.draw_card(pid),
+            BotAction::Pass => game.player_pass(pid),
+        };
+        ev.extend(res.unwrap());
+    }
+    panic!("bot game did not finish");
+}
+
+#[cfg(test)]
+mod bot_tests {
+    use super::*;
+
+    #[test]
+    fn test_bots_finish_game() {
+        for seed in 0..10 {
+            let bots: Vec<Box<dyn Strategy>> = vec![
+                Box::new(RandomBot::with_seed(seed)),
+                Box::new(HeuristicBot),
+                Box::new(RandomBot::with_seed(seed + 100)),
+                Box::new(HeuristicBot),
+            ];
+            let ev = play_bot_game(seed, RuleSet::default(), bots);
+            assert!(!ev.iter().any(|e| matches!(e, GE::GameError { .. })));
+            assert!(ev.iter().any(|e| matches!(e, GE::GameOver { .. })));
+        }
+    }
+
+    #[test]
+    fn test_bots_finish_game_with_house_rules() {
+        let rules = RuleSet {
+            stack_draws: true,
+            seven_zero: true,
+            draw_until_playable: true,
+            forced_play: true,
+            ..RuleSet::default()
+        };
+        let mut stacked = false;
+        let mut swapped = false;
+        for seed in 0..10 {
+            let bots: Vec<Box<dyn Strategy>> = vec![
+                Box::new(RandomBot::with_seed(seed)),
+                Box::new(HeuristicBot),
+                Box::new(RandomBot::with_seed(seed + 100)),
+            ];
+            let ev = play_bot_game(seed, rules.clone(), bots);
+            assert!(!ev.iter().any(|e| matches!(e, GE::GameError { .. })));
+            assert!(ev.iter().any(|e| matches!(e, GE::GameOver { .. })));
+            stacked |= ev.iter().any(|e| matches!(e, GE::DrawStacked { .. }));
+            swapped |= ev.iter().any(|e| matches!(e, GE::HandsSwapped { .. } | GE::HandsRotated { .. }));
+        }
+        assert!(stacked && swapped);
+    }
+
+    #[test]
+    fn test_heuristic_bot_choice() {
+        let view = BotView {
+            hand: vec![
+                UnoCard::WildCard(None, WildType::DRAWFOUR),
+                UnoCard::NumberCard(Color::RED, Number::TWO),
+                UnoCard::NumberCard(Color::RED, Number::NINE),
+                UnoCard::NumberCard(Color::BLUE, Number::ONE),
+            ],
+            top_card: Some(UnoCard::NumberCard(Color::RED, Number::FIVE)),
+            opponents_cards_count: vec![5],
+            drawn_card: None,
+            stacked_draws: 0,
+        };
+        // 留着万能牌, 先出高分牌
+        assert_eq!(
+            HeuristicBot.decide(&view),
+            BotAction::Play { card_index: 2, color: Color::RED, call_uno: false }
+        );
+
+        let view = BotView {
+            hand: vec![
+                UnoCard::WildCard(None, WildType::WILD),
+                UnoCard::NumberCard(Color::BLUE, Number::ONE),
+                UnoCard::NumberCard(Color::BLUE, Number::THREE),
+            ],
+            top_card: Some(UnoCard::NumberCard(Color::RED, Number::FIVE)),
+            opponents_cards_count: vec![5],
+            drawn_card: None,
+            stacked_draws: 0,
+        };
+        // 只能出万能牌时, 选手里最多的颜色
+        assert_eq!(
+            HeuristicBot.decide(&view),
+            BotAction::Play { card_index: 0, color: Color::BLUE, call_uno: false }
+        );
+    }
+}
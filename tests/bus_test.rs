@@ -1,4 +1,4 @@
-use uno::game::UnoGame;
+use uno::game::{RuleSet, UnoGame};
 use uno::ports::bus::{EventBus, ConsolerLogger};
 
 #[cfg(test)]
@@ -9,8 +9,8 @@ mod bus_test {
         let mut bus = EventBus::new();
         bus.register_handler(Box::new(ConsolerLogger));
 
-        let mut game = UnoGame::new();
-        let events = game.init_game(vec!["Alice".into(), "Bob".into()]);
+        let mut game = UnoGame::new(RuleSet::default());
+        let events = game.init_game(vec!["Alice".into(), "Bob".into()]).unwrap();
         bus.publish(events);
 
     // 后续每次调用 play_card / draw_card 后，同样把返回的 Vec<GameEvent> 发布出去
@@ -0,0 +1,23 @@
+use uno::protocol::chat::{sanitize_chat, MAX_CHAT_LEN};
+use uno::protocol::{Client2Server, Emote};
+
+#[cfg(test)]
+mod chat_tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_chat() {
+        assert_eq!(sanitize_chat("  hi\u{7}  ").unwrap(), "hi");
+        assert!(sanitize_chat(" \n ").is_err());
+        assert!(sanitize_chat(&"好".repeat(MAX_CHAT_LEN)).is_ok());
+        assert!(sanitize_chat(&"好".repeat(MAX_CHAT_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_emote_message_roundtrip() {
+        let json = r#"{"type":"SendEmote","data":{"emote":"GoodGame"}}"#;
+        let msg: Client2Server = serde_json::from_str(json).unwrap();
+        assert!(matches!(msg, Client2Server::SendEmote { emote: Emote::GoodGame }));
+        assert_eq!(serde_json::to_string(&msg).unwrap(), json);
+    }
+}
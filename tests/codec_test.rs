@@ -0,0 +1,62 @@
+use uno::game::{Color, Number, PlayerId, UnoCard};
+use uno::protocol::codec::{read_frame, JsonLines, MsgPack};
+use uno::protocol::{Client2Server, Codec, Frame, Server2Client, WireFormat};
+
+fn player_state() -> Server2Client {
+    let hand = (0..7).map(|_| UnoCard::NumberCard(Color::RED, Number::FIVE)).collect();
+    Server2Client::PlayerState { player_id: PlayerId(1), hand }
+}
+
+#[cfg(test)]
+mod codec_tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_survive_partial_reads() {
+        for format in [WireFormat::Json, WireFormat::MsgPack] {
+            let mut wire = Vec::new();
+            format.encode(&player_state(), &mut wire).unwrap();
+            format.encode(&Client2Server::ListRooms, &mut wire).unwrap();
+
+            // 一个字节一个字节地喂进去
+            let mut buf = Vec::new();
+            let mut got = Vec::new();
+            for b in wire {
+                buf.push(b);
+                if let Some(frame) = format.split_frame(&mut buf).unwrap() {
+                    got.push(frame);
+                }
+            }
+            assert_eq!(got.len(), 2);
+            assert!(buf.is_empty());
+            assert!(matches!(
+                format.decode_payload::<Server2Client>(&got[0]),
+                Frame::Msg(Server2Client::PlayerState { player_id: PlayerId(1), hand }) if hand.len() == 7
+            ));
+            assert!(matches!(format.decode_payload(&got[1]), Frame::Msg(Client2Server::ListRooms)));
+        }
+    }
+
+    #[test]
+    fn test_msgpack_is_smaller() {
+        let json = JsonLines.encode_payload(&player_state()).unwrap();
+        let bin = MsgPack.encode_payload(&player_state()).unwrap();
+        assert!(bin.len() < json.len());
+    }
+
+    #[test]
+    fn test_unknown_and_invalid_frames() {
+        let mut buf = b"{\"type\":\"Teleport\",\"data\":{}}\nnot json\r\n".to_vec();
+        assert!(matches!(JsonLines.decode::<Client2Server>(&mut buf), Ok(Some(Frame::Unknown))));
+        assert!(matches!(JsonLines.decode::<Client2Server>(&mut buf), Ok(Some(Frame::Invalid(_)))));
+        assert!(matches!(JsonLines.decode::<Client2Server>(&mut buf), Ok(None)));
+
+        // 超长的长度前缀直接报错, 不会等着读完
+        let mut buf = u32::MAX.to_be_bytes().to_vec();
+        assert!(MsgPack.split_frame(&mut buf).is_err());
+
+        // 对方关闭连接
+        let mut wire: &[u8] = &[];
+        assert!(read_frame::<Server2Client>(&MsgPack, &mut wire, &mut Vec::new()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,69 @@
+// 集成测试共用的服务器进程和 TCP 客户端辅助函数, 每个测试文件只用到其中一部分
+#![allow(dead_code)]
+
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use uno::protocol::{decode, Client2Server, Server2Client};
+
+pub type Reader = Lines<BufReader<OwnedReadHalf>>;
+
+// 测试结束时关掉服务器进程
+pub struct Server(pub Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+pub fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+pub fn local_addr() -> String {
+    format!("127.0.0.1:{}", free_port())
+}
+
+// 用随机端口启动 bin 指定的服务器, 返回 TCP 地址; envs 是额外的环境变量
+// 不继承外面的数据目录和 WebSocket 地址, 需要的测试自己传
+pub fn start_server(bin: &str, envs: &[(&str, String)]) -> (Server, String) {
+    let addr = local_addr();
+    let child = Command::new(bin)
+        .env("UNO_ADDR", &addr)
+        .env_remove("UNO_DATA_DIR")
+        .env_remove("UNO_WS_ADDR")
+        .envs(envs.iter().map(|(k, v)| (k, v)))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    (Server(child), addr)
+}
+
+// 服务器进程刚启动时端口可能还没监听, 重试几次
+pub async fn connect(addr: &str) -> (Reader, OwnedWriteHalf) {
+    for _ in 0..50 {
+        if let Ok(conn) = TcpStream::connect(addr).await {
+            let (r, w) = conn.into_split();
+            return (BufReader::new(r).lines(), w);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("server did not start");
+}
+
+pub async fn send(w: &mut OwnedWriteHalf, msg: &Client2Server) {
+    let line = format!("{}\n", serde_json::to_string(msg).unwrap());
+    w.write_all(line.as_bytes()).await.unwrap();
+}
+
+pub async fn recv(lines: &mut Reader) -> Server2Client {
+    let line = timeout(Duration::from_secs(10), lines.next_line()).await.unwrap().unwrap().unwrap();
+    decode(&line).unwrap().unwrap()
+}
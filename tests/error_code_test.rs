@@ -0,0 +1,28 @@
+use uno::game::events::GameEvent as GE;
+use uno::game::RuleError;
+use uno::protocol::{ErrorCode, Server2Client};
+
+#[cfg(test)]
+mod error_code_tests {
+    use super::*;
+
+    #[test]
+    fn test_server_error_carries_code() {
+        let e = RuleError::CannotPlayCard;
+        let msg = Server2Client::ServerError { code: e.into(), message: e.to_string() };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"ServerError","data":{"code":"IllegalCard","message":"Cannot play this card"}}"#
+        );
+        let back: Server2Client = serde_json::from_str(&json).unwrap();
+        assert!(matches!(back, Server2Client::ServerError { code: ErrorCode::IllegalCard, .. }));
+    }
+
+    #[test]
+    fn test_game_error_event() {
+        let json = serde_json::to_string(&GE::GameError { error: RuleError::DeckEmpty }).unwrap();
+        assert_eq!(json, r#"{"GameError":{"error":"DeckEmpty"}}"#);
+        assert_eq!(ErrorCode::from(RuleError::NotYourTurn), ErrorCode::NotYourTurn);
+    }
+}
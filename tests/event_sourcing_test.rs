@@ -0,0 +1,98 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use uno::game::events::GameEvent as GE;
+use uno::game::{Color, PlayerId, RuleSet, UnoGame};
+
+const COLORS: [Color; 4] = [Color::RED, Color::GREEN, Color::BLUE, Color::YELLOW];
+
+fn random_rules(rng: &mut StdRng) -> RuleSet {
+    RuleSet {
+        stack_draws: rng.gen(),
+        seven_zero: rng.gen(),
+        jump_in: rng.gen(),
+        draw_until_playable: rng.gen(),
+        forced_play: rng.gen(),
+        first_card_effects: rng.gen(),
+        random_seats: rng.gen(),
+        ..RuleSet::default()
+    }
+}
+
+// 随机挑一个玩家做随机操作, 大部分会被规则拒绝, 被拒绝的操作不能改动牌局
+fn random_action(game: &mut UnoGame, rng: &mut StdRng, n: usize) -> Option<Vec<GE>> {
+    let pid = if rng.gen_bool(0.7) { game.current_player() } else { PlayerId(rng.gen_range(0..n)) };
+    let color = COLORS[rng.gen_range(0..4)];
+    let res = match rng.gen_range(0..100) {
+        0..=49 => {
+            let len = game.get_player_hand(pid).len();
+            game.play_card_with_target(
+                pid,
+                rng.gen_range(0..len + 1),
+                rng.gen_bool(0.3),
+                rng.gen_bool(0.9).then_some(color),
+                rng.gen_bool(0.5).then(|| PlayerId(rng.gen_range(0..n))),
+            )
+        }
+        50..=74 => game.draw_card(pid),
+        75..=84 => game.player_pass(pid),
+        85..=89 => game.choose_color(pid, color),
+        90..=94 => match game.challenge_window() {
+            Some((challenger, challenged)) if rng.gen() => game.challenge(challenger, challenged),
+            Some((challenger, _)) => game.accept_draw_four(challenger),
+            None => game.challenge(pid, PlayerId(rng.gen_range(0..n))),
+        },
+        95..=97 => game.accept_draw_four(pid),
+        _ => game.remove_player(pid),
+    };
+    res.ok()
+}
+
+#[cfg(test)]
+mod event_sourcing_tests {
+    use super::*;
+
+    // 随机牌局的每一步, 从完整事件流重建的牌局都与实际牌局完全一致
+    #[test]
+    fn test_from_events_reproduces_random_games() {
+        let mut rng = StdRng::seed_from_u64(2024);
+        for _ in 0..200 {
+            let n = rng.gen_range(2..=6);
+            let mut game = UnoGame::with_seed(rng.gen(), random_rules(&mut rng));
+            let names = (0..n).map(|i| format!("P{}", i)).collect();
+            let mut events = game.init_game(names).unwrap();
+            assert!(matches!(events.first(), Some(GE::GameCreated { .. })));
+            assert!(UnoGame::from_events(&events) == game);
+
+            for _ in 0..3000 {
+                if !game.started {
+                    break;
+                }
+                let before = game.clone();
+                match random_action(&mut game, &mut rng, n) {
+                    Some(ev) => {
+                        events.extend(ev);
+                        assert!(UnoGame::from_events(&events) == game);
+                    }
+                    None => assert!(game == before),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_card_played_carries_hand_index() {
+        let mut game = UnoGame::with_seed(5, RuleSet { first_card_effects: false, ..RuleSet::default() });
+        game.init_game(vec!["A".into(), "B".into()]).unwrap();
+        let pid = game.current_player();
+        let hand = game.get_player_hand(pid);
+        let ev = (0..hand.len())
+            .find_map(|i| game.play_card(pid, i, false, Some(Color::RED)).ok())
+            .unwrap_or_else(|| game.draw_card(pid).unwrap());
+        for e in &ev {
+            if let GE::CardPlayed { card_index, card, .. } = e {
+                assert!(hand[*card_index].without_chosen_color() == card.without_chosen_color());
+            }
+        }
+    }
+}
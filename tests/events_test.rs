@@ -0,0 +1,45 @@
+use uno::game::events::{redact_events, GameEvent as GE};
+use uno::game::*;
+
+#[cfg(test)]
+mod events_tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_events() {
+        let card = UnoCard::NumberCard(Color::RED, Number::FIVE);
+        let events = vec![
+            GE::CardDraw { player_id: PlayerId(0), card },
+            GE::CardDraw { player_id: PlayerId(0), card },
+            GE::CardDraw { player_id: PlayerId(1), card },
+            GE::PlayerTurn { player_id: PlayerId(1) },
+        ];
+
+        let view = redact_events(&events, Some(PlayerId(1)));
+        assert_eq!(view.len(), 3);
+        assert!(matches!(view[0], GE::CardsDrawn { player_id: PlayerId(0), count: 2 }));
+        assert!(matches!(view[1], GE::CardDraw { player_id: PlayerId(1), .. }));
+        assert!(matches!(view[2], GE::PlayerTurn { player_id: PlayerId(1) }));
+
+        let view = redact_events(&events, None);
+        assert!(!view.iter().any(|e| matches!(e, GE::CardDraw { .. })));
+
+        // 种子决定整副牌的顺序, 玩家和观众都拿不到
+        let started = [GE::GameStarted { game_id: 0, seed: Some(42) }];
+        for viewer in [Some(PlayerId(0)), None] {
+            assert!(matches!(redact_events(&started, viewer)[0], GE::GameStarted { seed: None, .. }));
+        }
+    }
+
+    #[test]
+    fn test_redacted_opening_has_no_seed() {
+        let mut m = Match::with_seed(42, RuleSet::default());
+        let events = m.start_round(vec![(PlayerId(0), "A".into()), (PlayerId(1), "B".into())]).unwrap();
+        assert!(events.iter().any(|e| matches!(e, GE::GameCreated { .. })));
+
+        for viewer in [Some(PlayerId(1)), None] {
+            let view = redact_events(&events, viewer);
+            assert!(!view.iter().any(|e| matches!(e, GE::GameCreated { .. } | GE::GameStarted { seed: Some(_), .. })));
+        }
+    }
+}
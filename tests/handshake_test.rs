@@ -0,0 +1,73 @@
+use uno::game::events::GameEvent as GE;
+use uno::game::PlayerId;
+use uno::protocol::{decode, Capability, Client2Server, Codec, ErrorCode, Frame, Server2Client, WireFormat, PROTOCOL_VERSION};
+
+#[cfg(test)]
+mod handshake_tests {
+    use super::*;
+
+    #[test]
+    fn test_hello_with_unknown_capability() {
+        let json = r#"{"type":"Hello","data":{"protocol_version":1,"client_name":"cli","capabilities":["Chat","Teleport"]}}"#;
+        let msg = decode::<Client2Server>(json).unwrap();
+        let Some(Client2Server::Hello { protocol_version, capabilities, .. }) = msg else {
+            panic!("expected Hello");
+        };
+        assert_eq!(protocol_version, PROTOCOL_VERSION);
+        assert_eq!(capabilities, vec![Capability::Chat, Capability::Unknown]);
+
+        // 老客户端可以不带 capabilities
+        let json = r#"{"type":"Hello","data":{"protocol_version":1,"client_name":"old"}}"#;
+        assert!(matches!(
+            decode::<Client2Server>(json),
+            Ok(Some(Client2Server::Hello { capabilities, .. })) if capabilities.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_unknown_messages_are_skipped() {
+        let json = r#"{"type":"Teleport","data":{"x":1}}"#;
+        assert!(matches!(decode::<Client2Server>(json), Ok(None)));
+        assert!(matches!(decode::<Server2Client>(r#"{"type":"Fireworks"}"#), Ok(None)));
+
+        // 认识的消息缺字段仍然是错误
+        assert!(decode::<Client2Server>(r#"{"type":"JoinGame","data":{"name":"A"}}"#).is_err());
+        assert!(decode::<Client2Server>("not json").is_err());
+
+        // 不认识的错误码不会让整条消息失败
+        let json = r#"{"type":"ServerError","data":{"code":"Brand new","message":"hi"}}"#;
+        assert!(matches!(
+            decode::<Server2Client>(json),
+            Ok(Some(Server2Client::ServerError { code: ErrorCode::Unknown, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_bad_value_in_known_message_is_invalid() {
+        // 消息类型认识, 颜色不认识: 报错而不是当作新消息跳过
+        let json = r#"{"type":"PlayCard","data":{"player_id":0,"card_index":0,"color":"PURPLE","call_uno":false}}"#;
+        assert!(decode::<Client2Server>(json).is_err());
+        assert!(matches!(WireFormat::Json.decode_payload::<Client2Server>(json.as_bytes()), Frame::Invalid(_)));
+
+        // MessagePack 也一样区分
+        let msg: serde_json::Value = serde_json::from_str(json).unwrap();
+        let payload = WireFormat::MsgPack.encode_payload(&msg).unwrap();
+        assert!(matches!(WireFormat::MsgPack.decode_payload::<Client2Server>(&payload), Frame::Invalid(_)));
+        let unknown = serde_json::json!({"type": "Teleport", "data": {"x": 1}});
+        let payload = WireFormat::MsgPack.encode_payload(&unknown).unwrap();
+        assert!(matches!(WireFormat::MsgPack.decode_payload::<Client2Server>(&payload), Frame::Unknown));
+    }
+
+    #[test]
+    fn test_unknown_event_in_batch_is_skipped() {
+        let json = r#"{"type":"Events","data":[{"Fireworks":{"x":1}},"Confetti",{"PlayerTurn":{"player_id":1}}]}"#;
+        let Ok(Some(Server2Client::Events(events))) = decode::<Server2Client>(json) else {
+            panic!("expected Events");
+        };
+        assert!(matches!(&events[..], [GE::PlayerTurn { player_id: PlayerId(1) }]));
+
+        // 认识的事件内容不对仍然整条报错
+        let json = r#"{"type":"Events","data":[{"PlayerTurn":{"player_id":"one"}}]}"#;
+        assert!(decode::<Server2Client>(json).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use common::{connect, recv, send, start_server, Server};
+use uno::bot::BotDifficulty;
+use uno::game::{RuleSet, UnoCard};
+use uno::protocol::{Client2Server, ErrorCode, Server2Client};
+
+// 用随机端口和指定的数据目录启动 async_server
+fn start(data_dir: &Path) -> (Server, String) {
+    start_server(env!("CARGO_BIN_EXE_async_server"), &[("UNO_DATA_DIR", data_dir.display().to_string())])
+}
+
+// 发信号让服务器正常退出: INT 相当于 Ctrl-C, TERM 是 systemd、docker stop 发的
+fn stop(server: &mut Server, signal: &str) {
+    let status = Command::new("kill").arg(format!("-{}", signal)).arg(server.0.id().to_string()).status().unwrap();
+    assert!(status.success());
+    for _ in 0..50 {
+        if let Some(status) = server.0.try_wait().unwrap() {
+            assert!(status.success());
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    panic!("server did not shut down");
+}
+
+fn data_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("uno-data-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[cfg(test)]
+mod persistence_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resume_game_after_restart() {
+        let dir = data_dir("restart");
+        let (mut server, addr) = start(&dir);
+        let (mut lines, mut w) = connect(&addr).await;
+        send(&mut w, &Client2Server::CreateRoom {
+            room_id: "持久化".into(),
+            name: "Alice".into(),
+            max_players: 4,
+            password: None,
+            // 开启回合限时, 用 TurnDeadline 判断轮到自己
+            rules: Some(RuleSet { turn_timeout_secs: 30, ..RuleSet::default() }),
+        })
+        .await;
+        let Server2Client::Welcome { player_id, session_id } = recv(&mut lines).await else {
+            panic!("expected Welcome");
+        };
+        send(&mut w, &Client2Server::AddBot { difficulty: BotDifficulty::Hard }).await;
+        send(&mut w, &Client2Server::StartGame { player_id }).await;
+
+        // 电脑打完轮到自己时手牌不会再变, 记下最新的手牌
+        let mut hand: Vec<UnoCard> = Vec::new();
+        loop {
+            match recv(&mut lines).await {
+                Server2Client::PlayerState { hand: h, .. } => hand = h,
+                Server2Client::TurnDeadline { player_id: p, .. } if p == player_id => break,
+                _ => {}
+            }
+        }
+        assert!(!hand.is_empty());
+        drop(w);
+        stop(&mut server, "INT");
+        let files: Vec<_> = std::fs::read_dir(dir.join("rooms")).unwrap().collect();
+        assert_eq!(files.len(), 1);
+
+        // 重启后凭原来的 session_id 回到座位, 手牌不变
+        let (_server, addr) = start(&dir);
+        let (mut lines, mut w) = connect(&addr).await;
+        send(&mut w, &Client2Server::Resume { session_id: session_id.clone() }).await;
+        assert!(matches!(
+            recv(&mut lines).await,
+            Server2Client::Welcome { player_id: p, session_id: s } if p == player_id && s == session_id
+        ));
+        loop {
+            if let Server2Client::PlayerState { player_id: p, hand: h } = recv(&mut lines).await {
+                assert_eq!(p, player_id);
+                assert!(h == hand);
+                break;
+            }
+        }
+
+        // 房间还在大厅里, 牌局仍在进行
+        send(&mut w, &Client2Server::ListRooms).await;
+        loop {
+            if let Server2Client::RoomList { rooms } = recv(&mut lines).await {
+                assert_eq!(rooms.len(), 1);
+                assert_eq!(rooms[0].room_id, "持久化");
+                assert!(rooms[0].started);
+                break;
+            }
+        }
+
+        // 未知的 session 仍然被拒绝
+        let (mut lines, mut w) = connect(&addr).await;
+        send(&mut w, &Client2Server::Resume { session_id: "nope".into() }).await;
+        assert!(matches!(recv(&mut lines).await, Server2Client::ServerError { .. }));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    // SIGTERM 也会写快照; 快照里只有密码摘要, 重启后房间仍然要密码
+    #[tokio::test]
+    async fn test_password_room_survives_sigterm() {
+        let dir = data_dir("sigterm");
+        let (mut server, addr) = start(&dir);
+        let (mut lines, mut w) = connect(&addr).await;
+        send(&mut w, &Client2Server::CreateRoom {
+            room_id: "locked".into(),
+            name: "Alice".into(),
+            max_players: 4,
+            password: Some("hunter2".into()),
+            rules: None,
+        })
+        .await;
+        assert!(matches!(recv(&mut lines).await, Server2Client::Welcome { .. }));
+        drop(w);
+        stop(&mut server, "TERM");
+        let files: Vec<PathBuf> = std::fs::read_dir(dir.join("rooms")).unwrap().map(|e| e.unwrap().path()).collect();
+        assert_eq!(files.len(), 1);
+        assert!(!std::fs::read_to_string(&files[0]).unwrap().contains("hunter2"));
+
+        let (_server, addr) = start(&dir);
+        let join = |password: &str| Client2Server::JoinGame {
+            room_id: "locked".into(),
+            name: "Bob".into(),
+            seed: None,
+            rules: None,
+            password: Some(password.into()),
+        };
+        let (mut lines, mut w) = connect(&addr).await;
+        send(&mut w, &join("wrong")).await;
+        assert!(matches!(recv(&mut lines).await, Server2Client::ServerError { code: ErrorCode::WrongPassword, .. }));
+        send(&mut w, &join("hunter2")).await;
+        assert!(matches!(recv(&mut lines).await, Server2Client::Welcome { .. }));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    // 账号存在数据目录里, 重启后还能登录; 游客不能冒用账号名
+    #[tokio::test]
+    async fn test_accounts_survive_restart() {
+        let dir = data_dir("accounts");
+        let (mut server, addr) = start(&dir);
+        let (mut lines, mut w) = connect(&addr).await;
+        let login = |register: bool, password: &str| Client2Server::Login {
+            name: "Alice".into(),
+            password: password.into(),
+            register,
+        };
+        send(&mut w, &login(true, "secret1")).await;
+        assert!(matches!(recv(&mut lines).await, Server2Client::LoggedIn { name } if name == "Alice"));
+        send(&mut w, &login(true, "secret1")).await;
+        assert!(matches!(recv(&mut lines).await, Server2Client::ServerError { code: ErrorCode::NameTaken, .. }));
+        send(&mut w, &Client2Server::GetStats { name: "Alice".into() }).await;
+        assert!(matches!(
+            recv(&mut lines).await,
+            Server2Client::Stats { stats: Some(s), .. } if s.games_played == 0
+        ));
+        send(&mut w, &Client2Server::GetStats { name: "Bob".into() }).await;
+        assert!(matches!(recv(&mut lines).await, Server2Client::Stats { stats: None, .. }));
+
+        let (mut guest, mut gw) = connect(&addr).await;
+        send(&mut gw, &Client2Server::JoinGame {
+            room_id: "r".into(),
+            name: "Alice".into(),
+            seed: None,
+            rules: None,
+            password: None,
+        })
+        .await;
+        assert!(matches!(recv(&mut guest).await, Server2Client::ServerError { code: ErrorCode::NameReserved, .. }));
+        drop((w, gw));
+        stop(&mut server, "INT");
+
+        let (_server, addr) = start(&dir);
+        let (mut lines, mut w) = connect(&addr).await;
+        send(&mut w, &login(false, "wrong12")).await;
+        assert!(matches!(recv(&mut lines).await, Server2Client::ServerError { code: ErrorCode::LoginFailed, .. }));
+        send(&mut w, &login(false, "secret1")).await;
+        assert!(matches!(recv(&mut lines).await, Server2Client::LoggedIn { .. }));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
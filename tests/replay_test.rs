@@ -0,0 +1,139 @@
+use std::path::PathBuf;
+
+use uno::bot::*;
+use uno::game::events::GameEvent as GE;
+use uno::game::*;
+use uno::ports::replay::{Replay, ReplayHeader, ReplayRecorder, REPLAY_VERSION};
+
+// 每个测试用自己的临时目录
+fn replay_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("uno-replay-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+// 像服务器那样把被接受的操作和事件交给记录器
+fn record(rec: &ReplayRecorder, res: Result<Accepted, RuleError>) {
+    let accepted = res.unwrap();
+    rec.record(&accepted.command, &accepted.events);
+}
+
+// 开局并写入回放头
+fn start(m: &mut Match, rec: &ReplayRecorder, names: Vec<(PlayerId, String)>) {
+    let ev = m.start_round(names).unwrap();
+    rec.begin(ReplayHeader::from_events(m.round(), &ev).unwrap(), &ev);
+}
+
+// 机器人替所有座位行动, 打完当前这一局, 中途有一个玩家离开
+fn play_round(m: &mut Match, rec: &ReplayRecorder, bots: &mut [Box<dyn Strategy>]) {
+    for turn in 0..5000 {
+        let game = m.game();
+        if !game.started {
+            return;
+        }
+        if turn == 20 {
+            record(rec, m.remove_player(PlayerId(3)));
+            continue;
+        }
+        if let Some((challenger, challenged)) = game.challenge_window() {
+            record(rec, m.challenge(challenger, challenged));
+            continue;
+        }
+        let pid = game.current_player();
+        let view = BotView::from_game(game, pid);
+        if game.color_choice() == Some(pid) {
+            let color = bots[pid.index()].choose_color(&view);
+            record(rec, m.choose_color(pid, color));
+            continue;
+        }
+        let res = match bots[pid.index()].decide(&view) {
+            BotAction::Play { card_index, color, call_uno } => {
+                m.play_card(pid, card_index, call_uno, Some(color), None)
+            }
+            BotAction::Draw => m.draw_card(pid),
+            BotAction::Pass => m.player_pass(pid),
+        };
+        record(rec, res);
+    }
+    panic!("round did not finish");
+}
+
+#[cfg(test)]
+mod replay_tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_reproduces_recorded_game() {
+        let dir = replay_dir("game");
+        let rules = RuleSet { seven_zero: true, draw_until_playable: true, ..RuleSet::default() };
+        let names: Vec<(PlayerId, String)> = (0..4).map(|i| (PlayerId(i), format!("Bot-{}", i))).collect();
+        let mut m = Match::with_seed(7, rules);
+        let rec = ReplayRecorder::new(&dir).unwrap();
+        let mut bots: Vec<Box<dyn Strategy>> = (0..4).map(|i| Box::new(RandomBot::with_seed(i)) as Box<dyn Strategy>).collect();
+        start(&mut m, &rec, names);
+        // 被拒绝的操作不会写进回放
+        let idle = PlayerId((m.game().current_player().index() + 1) % 4);
+        assert!(m.player_pass(idle).is_err());
+        play_round(&mut m, &rec, &mut bots);
+        rec.flush();
+
+        let files: Vec<PathBuf> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path()).collect();
+        assert_eq!(files.len(), 1);
+        let replay = Replay::load(&files[0]).unwrap();
+        assert_eq!(replay.header.version, REPLAY_VERSION);
+        assert_eq!(replay.header.seed, 8); // 第 1 局用 seed + 1
+        assert!(replay.header.rules.seven_zero);
+        assert!(replay.steps.iter().any(|s| s.command == Command::Leave { player_id: PlayerId(3) }));
+        assert!(replay.opening.iter().any(|e| matches!(e, GE::GameStarted { .. })));
+        let last = replay.events_at(replay.steps.len());
+        assert!(last.iter().any(|e| matches!(e, GE::RoundOver { .. })));
+
+        // 重放到最后一步与实际的牌局一致
+        let game = replay.game_at(replay.steps.len()).unwrap();
+        assert!(!game.started);
+        for pid in m.game().seating() {
+            assert!(game.get_player_hand(pid) == m.game().get_player_hand(pid));
+        }
+        assert!(game.top_card == m.game().top_card);
+
+        // 往回走: 从头重放到任意一步, 与一步步向前执行得到的状态相同
+        let hands = |g: &UnoGame| g.seating().into_iter().map(|p| g.get_player_hand(p)).collect::<Vec<_>>();
+        let mut game = replay.header.start().unwrap();
+        let mut forward = vec![hands(&game)];
+        for s in &replay.steps {
+            s.command.apply(&mut game).unwrap();
+            forward.push(hands(&game));
+        }
+        for step in (0..forward.len()).rev().step_by(5) {
+            assert!(hands(&replay.game_at(step).unwrap()) == forward[step]);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reject_bad_replays() {
+        assert!(Replay::parse(&b""[..]).is_err());
+        let cmd = r#"{"type":"Command","data":{"type":"PassTurn","data":{"player_id":0}}}"#;
+        assert!(Replay::parse(cmd.as_bytes()).is_err());
+
+        // 种子对不上牌堆顺序
+        let dir = replay_dir("bad");
+        let mut m = Match::with_seed(1, RuleSet::default());
+        let rec = ReplayRecorder::new(&dir).unwrap();
+        start(&mut m, &rec, vec![(PlayerId(0), "A".into()), (PlayerId(1), "B".into())]);
+        rec.flush();
+        let path = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(Replay::parse(text.as_bytes()).is_ok());
+        let tampered = text.replacen("\"seed\":2", "\"seed\":3", 1);
+        assert_ne!(tampered, text);
+        assert!(Replay::parse(tampered.as_bytes()).is_err());
+        let future = text.replacen(
+            &format!("\"version\":{}", REPLAY_VERSION),
+            &format!("\"version\":{}", REPLAY_VERSION + 1),
+            1,
+        );
+        assert!(Replay::parse(future.as_bytes()).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use common::start_server;
+use uno::game::events::GameEvent as GE;
+use uno::game::PlayerId;
+use uno::protocol::{decode, Client2Server, ErrorCode, Server2Client};
+
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Client {
+    fn connect(addr: &str) -> Client {
+        for _ in 0..50 {
+            if let Ok(conn) = TcpStream::connect(addr) {
+                conn.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+                return Client { reader: BufReader::new(conn.try_clone().unwrap()), writer: conn };
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        panic!("server did not start");
+    }
+
+    fn send(&mut self, msg: &Client2Server) {
+        writeln!(self.writer, "{}", serde_json::to_string(msg).unwrap()).unwrap();
+    }
+
+    fn recv(&mut self) -> Server2Client {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        decode(&line).unwrap().unwrap()
+    }
+
+    fn join(&mut self, name: &str) -> (PlayerId, String) {
+        self.send(&Client2Server::JoinGame {
+            room_id: "r".into(),
+            name: name.into(),
+            seed: None,
+            rules: None,
+            password: None,
+        });
+        loop {
+            if let Server2Client::Welcome { player_id, session_id } = self.recv() {
+                return (player_id, session_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod server_tests {
+    use super::*;
+
+    // 断线重连后, 广播时清理掉旧连接不能让新连接认错玩家
+    #[test]
+    fn test_resumed_connection_keeps_its_player() {
+        let (_server, addr) = start_server(env!("CARGO_BIN_EXE_server"), &[]);
+        let mut alice = Client::connect(&addr);
+        let (alice_id, session_id) = alice.join("Alice");
+        let mut bob = Client::connect(&addr);
+        let (bob_id, _) = bob.join("Bob");
+        drop(alice);
+
+        let mut alice = Client::connect(&addr);
+        alice.send(&Client2Server::Resume { session_id });
+        assert!(matches!(alice.recv(), Server2Client::Welcome { player_id, .. } if player_id == alice_id));
+
+        // 开局的广播会清理断开的旧连接
+        bob.send(&Client2Server::StartGame { player_id: bob_id });
+        alice.send(&Client2Server::LeaveGame { player_id: alice_id });
+        loop {
+            match alice.recv() {
+                Server2Client::ServerError { code: ErrorCode::PlayerMismatch, .. } => {
+                    panic!("resumed connection lost its player")
+                }
+                Server2Client::Events(ev) if ev.iter().any(|e| matches!(e, GE::PlayerLeft { .. })) => break,
+                _ => {}
+            }
+        }
+    }
+
+    // 断线超过宽限期的玩家被移出牌局, 会话随之失效
+    #[test]
+    fn test_reconnect_grace_expires() {
+        let (_server, addr) = start_server(env!("CARGO_BIN_EXE_server"), &[("UNO_RECONNECT_GRACE_SECS", "1".into())]);
+        let mut alice = Client::connect(&addr);
+        let (alice_id, session_id) = alice.join("Alice");
+        let mut bob = Client::connect(&addr);
+        let (bob_id, _) = bob.join("Bob");
+        bob.send(&Client2Server::StartGame { player_id: bob_id });
+        drop(alice);
+
+        loop {
+            match bob.recv() {
+                Server2Client::Events(ev)
+                    if ev.iter().any(|e| matches!(e, GE::PlayerLeft { player_id } if *player_id == alice_id)) =>
+                {
+                    break
+                }
+                _ => {}
+            }
+        }
+        let mut alice = Client::connect(&addr);
+        alice.send(&Client2Server::Resume { session_id });
+        assert!(matches!(alice.recv(), Server2Client::ServerError { code: ErrorCode::SessionExpired, .. }));
+    }
+}
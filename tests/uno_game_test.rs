@@ -0,0 +1,562 @@
+use uno::game::events::GameEvent as GE;
+use uno::game::{valid_card, Action, Color, Number, PlayerId, RuleError, RuleSet, UnoCard, UnoGame, WildType};
+
+// 简单的自动出牌: 能出就出第一张合法牌, 否则摸牌, 摸到能出的也直接跳过
+fn take_turn(game: &mut UnoGame) -> Vec<GE> {
+    let pid = game.current_player();
+    if game.color_choice() == Some(pid) {
+        return game.choose_color(pid, Color::RED).unwrap();
+    }
+    let hand = game.get_player_hand(pid);
+    let playable = hand.iter().position(|c| {
+        let c = match c {
+            UnoCard::WildCard(_, wt) => UnoCard::WildCard(Some(Color::RED), *wt),
+            _ => *c,
+        };
+        valid_card(&c, &game.top_card)
+    });
+    let mut ev = match playable {
+        Some(idx) => game.play_card(pid, idx, hand.len() == 2, Some(Color::RED)),
+        None => game.draw_card(pid),
+    }
+    .unwrap();
+    if ev.iter().any(|e| matches!(e, GE::DrawnCardPlayable { .. })) {
+        ev.extend(game.player_pass(pid).unwrap());
+    }
+    if let Some(GE::ChallengeWindow { challenger_id, .. }) = ev
+        .iter()
+        .find(|e| matches!(e, GE::ChallengeWindow { .. }))
+        .cloned()
+    {
+        ev.extend(game.accept_draw_four(challenger_id).unwrap());
+    }
+    ev
+}
+
+// 找一局 0 号手里有 +4 的牌局, bluff 表示 0 号是否还有与顶牌同色的牌
+fn draw_four_game(bluff: bool) -> (UnoGame, usize) {
+    let names: Vec<String> = (0..3).map(|i| format!("P{}", i)).collect();
+    let rules = RuleSet { first_card_effects: false, ..RuleSet::default() };
+    (0..1000)
+        .find_map(|seed| {
+            let mut game = UnoGame::with_seed(seed, rules.clone());
+            game.init_game(names.clone()).unwrap();
+            let hand = game.get_player_hand(PlayerId(0));
+            let idx = hand.iter().position(|c| matches!(c, UnoCard::WildCard(_, WildType::DRAWFOUR)))?;
+            let top_color = game.top_card?.get_color().ok().copied();
+            let has_color = hand.iter().any(|c| c.get_color().ok().copied() == top_color);
+            (has_color == bluff).then_some((game, idx))
+        })
+        .unwrap()
+}
+
+fn play_draw_four(game: &mut UnoGame, idx: usize) {
+    let ev = game.play_card(PlayerId(0), idx, false, Some(Color::GREEN)).unwrap();
+    assert!(ev.iter().any(|e| matches!(
+        e,
+        GE::ChallengeWindow { challenger_id: PlayerId(1), challenged_id: PlayerId(0) }
+    )));
+    assert_eq!(game.challenge_window(), Some((PlayerId(1), PlayerId(0))));
+}
+
+// 在前 5000 个种子里找第一局满足 pick 的牌局, 首张顶牌不生效, 0 号先出
+fn find_game<T>(players: usize, rules: RuleSet, pick: impl Fn(&UnoGame) -> Option<T>) -> (UnoGame, T) {
+    let names: Vec<String> = (0..players).map(|i| format!("P{}", i)).collect();
+    let rules = RuleSet { first_card_effects: false, ..rules };
+    (0..5000)
+        .find_map(|seed| {
+            let mut game = UnoGame::with_seed(seed, rules.clone());
+            game.init_game(names.clone()).unwrap();
+            let found = pick(&game)?;
+            Some((game, found))
+        })
+        .unwrap()
+}
+
+fn find_card(game: &UnoGame, pid: PlayerId, want: impl Fn(&UnoCard) -> bool) -> Option<usize> {
+    game.get_player_hand(pid).iter().position(want)
+}
+
+#[cfg(test)]
+mod uno_game_tests {
+    use super::*;
+
+    #[test]
+    fn test_challenge_without_draw_four() {
+        let rules = RuleSet { first_card_effects: false, ..RuleSet::default() };
+        let mut game = UnoGame::new(rules);
+        game.init_game(vec!["Alice".into(), "Bob".into()]).unwrap();
+
+        let err = game.challenge(PlayerId(0), PlayerId(1));
+        assert!(matches!(err, Err(RuleError::NoDrawFourToChallenge)));
+        let err = game.accept_draw_four(PlayerId(0));
+        assert!(matches!(err, Err(RuleError::NoDrawFourToAccept)));
+        assert_eq!(game.get_player_hand(PlayerId(0)).len(), 7);
+    }
+
+    // 0 号手里还有顶牌颜色时打 +4 算诈唬: 0 号摸 4 张, 1 号照常出牌
+    #[test]
+    fn test_challenge_success() {
+        let (mut game, idx) = draw_four_game(true);
+        play_draw_four(&mut game, idx);
+        let ev = game.challenge(PlayerId(1), PlayerId(0)).unwrap();
+        assert!(ev.iter().any(|e| matches!(e, GE::ChallengedSuccess { .. })));
+        assert!(matches!(ev.last(), Some(GE::PlayerTurn { player_id: PlayerId(1) })));
+        assert_eq!(game.get_player_hand(PlayerId(0)).len(), 10);
+        assert_eq!(game.get_player_hand(PlayerId(1)).len(), 7);
+        assert_eq!(game.current_player(), PlayerId(1));
+        assert_eq!(game.challenge_window(), None);
+    }
+
+    // 没有诈唬时质疑失败: 1 号摸 6 张并失去回合
+    #[test]
+    fn test_challenge_failure() {
+        let (mut game, idx) = draw_four_game(false);
+        play_draw_four(&mut game, idx);
+        let ev = game.challenge(PlayerId(1), PlayerId(0)).unwrap();
+        assert!(ev.iter().any(|e| matches!(e, GE::ChallengedFailed { .. })));
+        assert!(matches!(ev.last(), Some(GE::PlayerTurn { player_id: PlayerId(2) })));
+        assert_eq!(game.get_player_hand(PlayerId(0)).len(), 6);
+        assert_eq!(game.get_player_hand(PlayerId(1)).len(), 13);
+        assert_eq!(game.current_player(), PlayerId(2));
+    }
+
+    // 不质疑: 1 号摸 4 张并失去回合
+    #[test]
+    fn test_accept_draw_four() {
+        let (mut game, idx) = draw_four_game(true);
+        play_draw_four(&mut game, idx);
+        let err = game.accept_draw_four(PlayerId(2));
+        assert!(matches!(err, Err(RuleError::NotYourTurn)));
+        let ev = game.accept_draw_four(PlayerId(1)).unwrap();
+        assert!(matches!(ev.last(), Some(GE::PlayerTurn { player_id: PlayerId(2) })));
+        assert_eq!(game.get_player_hand(PlayerId(1)).len(), 11);
+        assert_eq!(game.current_player(), PlayerId(2));
+    }
+
+    #[test]
+    fn test_starting_hand_size() {
+        // 关掉第一张牌的效果, 免得首位玩家被 +2
+        let rules = RuleSet { starting_hand_size: 5, first_card_effects: false, ..RuleSet::default() };
+        let mut game = UnoGame::new(rules);
+        game.init_game(vec!["Alice".into(), "Bob".into()]).unwrap();
+        assert_eq!(game.get_player_hand(PlayerId(0)).len(), 5);
+        assert_eq!(game.get_player_hand(PlayerId(1)).len(), 5);
+    }
+
+    // 一副牌发不完起手牌时拒绝开局, 牌局保持原样
+    #[test]
+    fn test_starting_hand_size_too_large() {
+        let rules = RuleSet { starting_hand_size: 60, ..RuleSet::default() };
+        assert!(rules.check(2).is_err());
+        assert!(RuleSet { starting_hand_size: 0, ..RuleSet::default() }.check(2).is_err());
+        assert!(RuleSet::default().check(10).is_ok());
+        let mut game = UnoGame::with_seed(1, rules);
+        let before = game.clone();
+        assert!(matches!(game.init_game(vec!["Alice".into(), "Bob".into()]), Err(RuleError::DeckEmpty)));
+        assert!(game == before);
+    }
+
+    #[test]
+    fn test_first_card_effects() {
+        let names: Vec<String> = (0..3).map(|i| format!("P{}", i)).collect();
+        let mut seen = [false; 4];
+        for seed in 0..300 {
+            let mut game = UnoGame::with_seed(seed, RuleSet::default());
+            let ev = game.init_game(names.clone()).unwrap();
+            match game.top_card {
+                Some(UnoCard::WildCard(_, WildType::DRAWFOUR)) => panic!("+4 must be redrawn"),
+                Some(UnoCard::ActionCard(_, Action::SKIP)) => {
+                    assert_eq!(game.current_player(), PlayerId(1));
+                    seen[0] = true;
+                }
+                Some(UnoCard::ActionCard(_, Action::REVERSE)) => {
+                    // 反向后庄家(2 号)先出
+                    assert!(!game.direction);
+                    assert_eq!(game.current_player(), PlayerId(2));
+                    seen[1] = true;
+                }
+                Some(UnoCard::ActionCard(_, Action::DRAWTWO)) => {
+                    assert_eq!(game.get_player_hand(PlayerId(0)).len(), 9);
+                    assert_eq!(game.current_player(), PlayerId(1));
+                    seen[2] = true;
+                }
+                Some(UnoCard::WildCard(_, WildType::WILD)) => {
+                    assert_eq!(game.color_choice(), Some(PlayerId(0)));
+                    assert!(ev.iter().any(|e| matches!(e, GE::ColorChoiceRequired { player_id: PlayerId(0) })));
+                    let err = game.draw_card(PlayerId(0));
+                    assert!(matches!(err, Err(RuleError::ColorChoicePending)));
+                    game.choose_color(PlayerId(0), Color::BLUE).unwrap();
+                    assert!(game.top_card == Some(UnoCard::WildCard(Some(Color::BLUE), WildType::WILD)));
+                    assert_eq!(game.color_choice(), None);
+                    seen[3] = true;
+                }
+                _ => assert_eq!(game.current_player(), PlayerId(0)),
+            }
+        }
+        assert_eq!(seen, [true; 4]);
+
+        // 关闭首牌效果时只会翻到数字牌
+        let rules = RuleSet { first_card_effects: false, ..RuleSet::default() };
+        for seed in 0..50 {
+            let mut game = UnoGame::with_seed(seed, rules.clone());
+            game.init_game(names.clone()).unwrap();
+            assert!(matches!(game.top_card, Some(UnoCard::NumberCard(..))));
+        }
+    }
+
+    #[test]
+    fn test_jump_in() {
+        let names: Vec<String> = (0..4).map(|i| format!("P{}", i)).collect();
+        let setup = |seed: u64, jump_in: bool| {
+            let mut game = UnoGame::with_seed(seed, RuleSet { jump_in, ..RuleSet::default() });
+            game.init_game(names.clone()).unwrap();
+            // 非当前玩家手里有和顶牌完全相同的牌
+            let top = game.top_card;
+            let target = (0..4).map(PlayerId).filter(|&p| p != game.current_player()).find_map(|p| {
+                let idx = game.get_player_hand(p).iter().position(|c| Some(*c) == top)?;
+                Some((p, idx))
+            });
+            (game, target)
+        };
+        let seed = (0..500).find(|&s| setup(s, true).1.is_some()).unwrap();
+
+        let (mut game, Some((pid, idx))) = setup(seed, false) else { unreachable!() };
+        let err = game.play_card(pid, idx, false, Some(Color::RED));
+        assert!(matches!(err, Err(RuleError::NotYourTurn)));
+
+        let (mut game, Some((pid, idx))) = setup(seed, true) else { unreachable!() };
+        let ev = game.play_card(pid, idx, false, Some(Color::RED)).unwrap();
+        assert!(matches!(ev.first(), Some(GE::JumpedIn { player_id }) if *player_id == pid));
+        assert_eq!(game.get_player_hand(pid).len(), 6);
+    }
+
+    #[test]
+    fn test_deck_reshuffle() {
+        let names: Vec<String> = (0..8).map(|i| format!("P{}", i)).collect();
+        let mut reshuffled = false;
+        for seed in 0..20 {
+            let mut game = UnoGame::with_seed(seed, RuleSet::default());
+            game.init_game(names.clone()).unwrap();
+            for _ in 0..2000 {
+                let ev = take_turn(&mut game);
+                assert!(!ev.iter().any(|e| matches!(e, GE::GameError { .. })));
+                if ev.iter().any(|e| matches!(e, GE::DeckReshuffled { .. })) {
+                    reshuffled = true;
+                }
+                if !game.started {
+                    break;
+                }
+            }
+            if reshuffled {
+                break;
+            }
+        }
+        assert!(reshuffled);
+    }
+
+    #[test]
+    fn test_seeded_game_is_reproducible() {
+        let play = |seed: u64| {
+            let mut game = UnoGame::with_seed(seed, RuleSet::default());
+            let mut ev = game.init_game(vec!["Alice".into(), "Bob".into(), "Carol".into()]).unwrap();
+            for _ in 0..300 {
+                if !game.started {
+                    break;
+                }
+                ev.extend(take_turn(&mut game));
+            }
+            serde_json::to_string(&ev).unwrap()
+        };
+        assert_eq!(play(42), play(42));
+        assert_ne!(play(42), play(43));
+        assert!(play(7).contains(r#""seed":7"#));
+    }
+
+    #[test]
+    fn test_remove_player() {
+        for seed in 0..20 {
+            let mut game = UnoGame::with_seed(seed, RuleSet::default());
+            game.init_game(vec!["Alice".into(), "Bob".into(), "Carol".into(), "Dave".into()]).unwrap();
+            take_turn(&mut game);
+            // 轮到谁谁离开, 其他人的 id 不变, 下一位接着出
+            let leaver = game.current_player();
+            let ev = game.remove_player(leaver).unwrap();
+            assert!(matches!(ev[0], GE::PlayerLeft { player_id } if player_id == leaver));
+            assert!(matches!(ev.last(), Some(GE::PlayerTurn { player_id }) if *player_id != leaver));
+            assert!(game.get_player_hand(leaver).is_empty());
+            assert_eq!(game.get_players_cards_count().len(), 4);
+            assert!(matches!(game.remove_player(leaver), Err(RuleError::InvalidPlayer)));
+
+            // 离开的人不再轮到, 也不能出牌
+            for _ in 0..30 {
+                if !game.started {
+                    break;
+                }
+                assert_ne!(game.current_player(), leaver);
+                assert!(game.challenge_window().is_none_or(|(c, _)| c != leaver));
+                take_turn(&mut game);
+            }
+            assert!(game.play_card(leaver, 0, false, Some(Color::RED)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_remove_player_ends_game() {
+        let mut game = UnoGame::with_seed(7, RuleSet::default());
+        game.init_game(vec!["Alice".into(), "Bob".into(), "Carol".into()]).unwrap();
+        game.remove_player(PlayerId(0)).unwrap();
+        assert!(game.started);
+        let ev = game.remove_player(PlayerId(2)).unwrap();
+        assert!(!game.started);
+        let Some(GE::GameOver { winner, scores }) = ev.last() else {
+            panic!("missing GameOver");
+        };
+        assert_eq!(*winner, PlayerId(1));
+        assert_eq!(scores.len(), 1);
+    }
+
+    #[test]
+    fn test_random_seats() {
+        let names: Vec<String> = (0..4).map(|i| format!("P{}", i)).collect();
+        let ids: Vec<PlayerId> = (0..4).map(PlayerId).collect();
+        let rules = RuleSet { random_seats: true, first_card_effects: false, ..RuleSet::default() };
+        let mut shuffled = false;
+        for seed in 0..20 {
+            let mut game = UnoGame::with_seed(seed, rules.clone());
+            let ev = game.init_game(names.clone()).unwrap();
+            // id 跟着名字走, 座位打乱
+            let seating = game.seating();
+            let mut sorted = seating.clone();
+            sorted.sort();
+            assert_eq!(sorted, ids);
+            shuffled |= seating != ids;
+            assert_eq!(game.current_player(), seating[0]);
+            for e in &ev {
+                if let GE::PlayerJoined { player_id, name } = e {
+                    assert_eq!(name, &format!("P{}", player_id));
+                }
+            }
+            assert!(ids.iter().all(|&p| game.get_player_hand(p).len() == 7));
+        }
+        assert!(shuffled);
+
+        let mut game = UnoGame::with_seed(1, RuleSet::default());
+        game.init_game(names).unwrap();
+        assert_eq!(game.seating(), ids);
+    }
+
+    #[test]
+    fn test_rejected_actions() {
+        let rules = RuleSet { first_card_effects: false, ..RuleSet::default() };
+        let names: Vec<String> = vec!["Alice".into(), "Bob".into()];
+        let mut game = UnoGame::new(rules.clone());
+        assert!(matches!(game.draw_card(PlayerId(0)), Err(RuleError::GameNotStarted)));
+
+        game.init_game(names.clone()).unwrap();
+        let pid = game.current_player();
+        let other = PlayerId(1 - pid.index());
+        // 没摸牌不能跳过, 不是自己的回合不能摸牌
+        assert!(matches!(game.player_pass(pid), Err(RuleError::MustDrawBeforePass)));
+        assert!(matches!(game.draw_card(other), Err(RuleError::NotYourTurn)));
+        assert!(matches!(game.play_card(pid, 99, false, None), Err(RuleError::InvalidCardIndex)));
+        assert_eq!(game.current_player(), pid);
+        assert_eq!(game.get_player_hand(pid).len(), 7);
+
+        // 万能牌必须带颜色, 被拒绝后手牌不变
+        let mut wild_seen = false;
+        let mut drawn_seen = false;
+        for seed in 0..200 {
+            let mut game = UnoGame::with_seed(seed, rules.clone());
+            game.init_game(names.clone()).unwrap();
+            let pid = game.current_player();
+            let hand = game.get_player_hand(pid);
+            if let Some(idx) = hand.iter().position(|c| matches!(c, UnoCard::WildCard(..))) {
+                assert!(matches!(game.play_card(pid, idx, false, None), Err(RuleError::ColorRequired)));
+                assert!(game.get_player_hand(pid) == hand);
+                wild_seen = true;
+            }
+
+            // 摸到能出的牌之后只能出这张, 也不能再摸
+            game.draw_card(pid).unwrap();
+            if let Some(drawn) = game.drawn_card_playable() {
+                let idx = if drawn == 0 { 1 } else { 0 };
+                let err = game.play_card(pid, idx, false, Some(Color::RED));
+                assert!(matches!(err, Err(RuleError::MustPlayDrawnCard)));
+                assert!(matches!(game.draw_card(pid), Err(RuleError::AlreadyDrawn)));
+                game.player_pass(pid).unwrap();
+                assert_ne!(game.current_player(), pid);
+                drawn_seen = true;
+            }
+        }
+        assert!(wild_seen && drawn_seen);
+    }
+
+    // 快照里连随机源的状态也保存下来, 恢复后接着打和原来完全一样
+    #[test]
+    fn test_snapshot_roundtrip_keeps_playing_the_same() {
+        let mut game = UnoGame::with_seed(11, RuleSet::default());
+        game.init_game(vec!["A".into(), "B".into(), "C".into()]).unwrap();
+        for _ in 0..30 {
+            take_turn(&mut game);
+        }
+        let json = serde_json::to_string(&game).unwrap();
+        let mut restored: UnoGame = serde_json::from_str(&json).unwrap();
+        assert!(restored == game);
+        while game.started {
+            let ev = take_turn(&mut game);
+            let ev2 = take_turn(&mut restored);
+            assert_eq!(ev.len(), ev2.len());
+            assert!(restored == game);
+        }
+    }
+    #[test]
+    fn test_stacked_draws_taken_on_draw() {
+        let rules = RuleSet { stack_draws: true, ..RuleSet::default() };
+        let (mut game, (two, four)) = find_game(3, rules, |g| {
+            let two = find_card(g, PlayerId(0), |c| {
+                matches!(c, UnoCard::ActionCard(_, Action::DRAWTWO)) && valid_card(c, &g.top_card)
+            })?;
+            let four = find_card(g, PlayerId(1), |c| matches!(c, UnoCard::WildCard(_, WildType::DRAWFOUR)))?;
+            Some((two, four))
+        });
+        let ev = game.play_card(PlayerId(0), two, false, None).unwrap();
+        assert!(ev.iter().any(|e| matches!(e, GE::DrawStacked { target_player_id: PlayerId(1), total: 2 })));
+        let ev = game.play_card(PlayerId(1), four, false, Some(Color::BLUE)).unwrap();
+        assert!(ev.iter().any(|e| matches!(e, GE::DrawStacked { target_player_id: PlayerId(2), total: 6 })));
+        assert_eq!(game.stacked_draws(), 6);
+
+        // 接不住时不能直接跳过, 摸牌一次摸完累计的张数
+        assert!(matches!(game.player_pass(PlayerId(2)), Err(RuleError::MustStackOrTake)));
+        let ev = game.draw_card(PlayerId(2)).unwrap();
+        assert!(ev.iter().any(|e| matches!(e, GE::DrawStackTaken { player_id: PlayerId(2), count: 6 })));
+        assert!(matches!(ev.last(), Some(GE::PlayerTurn { player_id: PlayerId(0) })));
+        assert_eq!(game.get_player_hand(PlayerId(2)).len(), 13);
+        assert_eq!(game.stacked_draws(), 0);
+    }
+
+    #[test]
+    fn test_draw_two_does_not_stack_on_draw_four() {
+        let rules = RuleSet { stack_draws: true, ..RuleSet::default() };
+        let (mut game, (four, two)) = find_game(3, rules, |g| {
+            let four = find_card(g, PlayerId(0), |c| matches!(c, UnoCard::WildCard(_, WildType::DRAWFOUR)))?;
+            let two = find_card(g, PlayerId(1), |c| matches!(c, UnoCard::ActionCard(_, Action::DRAWTWO)))?;
+            Some((four, two))
+        });
+        // +4 选 +2 的颜色, 颜色相同也不能叠
+        let color = *game.get_player_hand(PlayerId(1))[two].get_color().unwrap();
+        game.play_card(PlayerId(0), four, false, Some(color)).unwrap();
+        let before = game.clone();
+        assert!(matches!(game.play_card(PlayerId(1), two, false, None), Err(RuleError::MustStackOrTake)));
+        assert!(game == before);
+        assert_eq!(game.stacked_draws(), 4);
+    }
+
+    #[test]
+    fn test_seven_swaps_with_chosen_player() {
+        let rules = RuleSet { seven_zero: true, ..RuleSet::default() };
+        let (mut game, seven) = find_game(4, rules, |g| {
+            find_card(g, PlayerId(0), |c| {
+                matches!(c, UnoCard::NumberCard(_, Number::SEVEN)) && valid_card(c, &g.top_card)
+            })
+        });
+        let mut mine = game.get_player_hand(PlayerId(0));
+        mine.remove(seven);
+        let theirs = game.get_player_hand(PlayerId(2));
+        let next = game.get_player_hand(PlayerId(1));
+
+        let ev = game.play_card_with_target(PlayerId(0), seven, false, None, Some(PlayerId(2))).unwrap();
+        assert!(ev.iter().any(|e| matches!(e, GE::HandsSwapped { player_id: PlayerId(0), other_id: PlayerId(2) })));
+        assert!(game.get_player_hand(PlayerId(0)) == theirs);
+        assert!(game.get_player_hand(PlayerId(2)) == mine);
+        assert!(game.get_player_hand(PlayerId(1)) == next);
+        assert!(matches!(ev.last(), Some(GE::PlayerTurn { player_id: PlayerId(1) })));
+    }
+
+    #[test]
+    fn test_zero_rotates_hands_along_direction() {
+        let rules = RuleSet { seven_zero: true, ..RuleSet::default() };
+        let is_zero = |c: &UnoCard| matches!(c, UnoCard::NumberCard(_, Number::ZERO));
+
+        // 顺时针: 每人的手牌交给下家
+        let (mut game, zero) = find_game(3, rules.clone(), |g| {
+            find_card(g, PlayerId(0), |c| is_zero(c) && valid_card(c, &g.top_card))
+        });
+        let mut hands: Vec<Vec<UnoCard>> = (0..3).map(|i| game.get_player_hand(PlayerId(i))).collect();
+        hands[0].remove(zero);
+        let ev = game.play_card(PlayerId(0), zero, false, None).unwrap();
+        assert!(ev.iter().any(|e| matches!(e, GE::HandsRotated { clockwise: true })));
+        assert!(game.get_player_hand(PlayerId(1)) == hands[0]);
+        assert!(game.get_player_hand(PlayerId(2)) == hands[1]);
+        assert!(game.get_player_hand(PlayerId(0)) == hands[2]);
+
+        // 倒转后: 0 号出反转, 2 号出同色 0, 每人的手牌交给逆时针方向的下家
+        let (mut game, (reverse, zero)) = find_game(3, rules, |g| {
+            let reverse = find_card(g, PlayerId(0), |c| {
+                matches!(c, UnoCard::ActionCard(_, Action::REVERSE)) && valid_card(c, &g.top_card)
+            })?;
+            let color = g.get_player_hand(PlayerId(0))[reverse].get_color().ok().copied();
+            let zero = find_card(g, PlayerId(2), |c| is_zero(c) && c.get_color().ok().copied() == color)?;
+            Some((reverse, zero))
+        });
+        game.play_card(PlayerId(0), reverse, false, None).unwrap();
+        assert!(!game.direction);
+        let mut hands: Vec<Vec<UnoCard>> = (0..3).map(|i| game.get_player_hand(PlayerId(i))).collect();
+        hands[2].remove(zero);
+        let ev = game.play_card(PlayerId(2), zero, false, None).unwrap();
+        assert!(ev.iter().any(|e| matches!(e, GE::HandsRotated { clockwise: false })));
+        assert!(game.get_player_hand(PlayerId(1)) == hands[2]);
+        assert!(game.get_player_hand(PlayerId(0)) == hands[1]);
+        assert!(game.get_player_hand(PlayerId(2)) == hands[0]);
+        assert!(matches!(ev.last(), Some(GE::PlayerTurn { player_id: PlayerId(1) })));
+    }
+
+    #[test]
+    fn test_forced_play_rejects_pass() {
+        let rules = RuleSet { forced_play: true, ..RuleSet::default() };
+        let (mut game, ()) = find_game(3, rules, |g| {
+            let mut g = g.clone();
+            let ev = g.draw_card(PlayerId(0)).ok()?;
+            ev.iter().any(|e| matches!(e, GE::DrawnCardPlayable { .. })).then_some(())
+        });
+        game.draw_card(PlayerId(0)).unwrap();
+        let idx = game.drawn_card_playable().unwrap();
+        assert!(matches!(game.player_pass(PlayerId(0)), Err(RuleError::ForcedPlay)));
+        let ev = game.play_card(PlayerId(0), idx, false, Some(Color::RED)).unwrap();
+        assert!(ev.iter().any(|e| matches!(e, GE::CardPlayed { player_id: PlayerId(0), .. })));
+        assert_ne!(game.current_player(), PlayerId(0));
+    }
+
+    #[test]
+    fn test_draw_until_playable() {
+        let rules = RuleSet { draw_until_playable: true, ..RuleSet::default() };
+        // 第一张摸到的牌不能出, 要继续摸
+        let (mut game, ()) = find_game(3, rules.clone(), |g| {
+            let mut g = g.clone();
+            let ev = g.draw_card(PlayerId(0)).ok()?;
+            (ev.iter().filter(|e| matches!(e, GE::CardDraw { .. })).count() >= 2).then_some(())
+        });
+        let top = game.top_card;
+        let ev = game.draw_card(PlayerId(0)).unwrap();
+        let drawn: Vec<UnoCard> = ev
+            .iter()
+            .filter_map(|e| match e {
+                GE::CardDraw { card, .. } => Some(*card),
+                _ => None,
+            })
+            .collect();
+        let (last, rest) = drawn.split_last().unwrap();
+        assert!(rest.iter().all(|c| !valid_card(c, &top)));
+        assert!(valid_card(last, &top));
+        assert!(matches!(ev.last(), Some(GE::DrawnCardPlayable { player_id: PlayerId(0) })));
+        assert_eq!(game.get_player_hand(PlayerId(0)).len(), 7 + drawn.len());
+        assert_eq!(game.current_player(), PlayerId(0));
+
+        // 关闭房规时同一局只摸一张, 不能出就轮到下家
+        let mut game = UnoGame::with_seed(game.seed(), RuleSet { draw_until_playable: false, ..rules });
+        game.init_game((0..3).map(|i| format!("P{}", i)).collect()).unwrap();
+        let ev = game.draw_card(PlayerId(0)).unwrap();
+        assert_eq!(ev.iter().filter(|e| matches!(e, GE::CardDraw { .. })).count(), 1);
+        assert!(matches!(ev.last(), Some(GE::PlayerTurn { player_id: PlayerId(1) })));
+    }
+}
@@ -0,0 +1,157 @@
+use uno::bot::*;
+use uno::game::events::GameEvent as GE;
+use uno::game::*;
+
+// 机器人替所有座位行动, 打完当前这一局
+fn play_round(m: &mut Match, bots: &mut [Box<dyn Strategy>]) -> Vec<GE> {
+    let mut ev = Vec::new();
+    for _ in 0..5000 {
+        let game = m.game();
+        if !game.started {
+            return ev;
+        }
+        if let Some((challenger, _)) = game.challenge_window() {
+            ev.extend(m.accept_draw_four(challenger).unwrap().events);
+            continue;
+        }
+        let pid = game.current_player();
+        let view = BotView::from_game(game, pid);
+        if game.color_choice() == Some(pid) {
+            let color = bots[pid.index()].choose_color(&view);
+            ev.extend(m.choose_color(pid, color).unwrap().events);
+            continue;
+        }
+        let res = match bots[pid.index()].decide(&view) {
+            BotAction::Play { card_index, color, call_uno } => {
+                m.play_card(pid, card_index, call_uno, Some(color), None)
+            }
+            BotAction::Draw => m.draw_card(pid),
+            BotAction::Pass => m.player_pass(pid),
+        };
+        ev.extend(res.unwrap().events);
+    }
+    panic!("round did not finish");
+}
+
+#[cfg(test)]
+mod uno_match_tests {
+    use super::*;
+
+    #[test]
+    fn test_match_to_target_score() {
+        let names: Vec<(PlayerId, String)> = (0..3).map(|i| (PlayerId(i), format!("Bot-{}", i))).collect();
+        let rules = RuleSet { target_score: 200, ..RuleSet::default() };
+        let mut m = Match::with_seed(1, rules);
+        let mut bots: Vec<Box<dyn Strategy>> = (0..3).map(|_| Box::new(HeuristicBot) as Box<dyn Strategy>).collect();
+        let mut expected = [0i32; 3];
+        for round in 1..100u32 {
+            m.start_round(names.clone()).unwrap();
+            assert_eq!(m.round(), round);
+            // 庄家轮换
+            assert_eq!(m.dealer(), PlayerId((round as usize + 1) % 3));
+
+            let ev = play_round(&mut m, &mut bots);
+            let Some(GE::RoundOver { round: r, winner, points, totals }) =
+                ev.iter().find(|e| matches!(e, GE::RoundOver { .. })).cloned()
+            else {
+                panic!("missing RoundOver");
+            };
+            assert_eq!(r, round);
+            let left: i32 = (0..3)
+                .map(PlayerId)
+                .filter(|&p| p != winner)
+                .flat_map(|p| m.game().get_player_hand(p))
+                .map(|c| c.get_value())
+                .sum();
+            assert_eq!(points, left);
+            expected[winner.index()] += points;
+            assert_eq!(totals.iter().map(|t| t.1).collect::<Vec<_>>(), expected);
+
+            if let Some(GE::MatchOver { winner: w, .. }) =
+                ev.iter().find(|e| matches!(e, GE::MatchOver { .. }))
+            {
+                assert_eq!(*w, winner);
+                assert!(expected[winner.index()] >= 200);
+                assert!(!m.in_progress());
+                // 比赛结束后再开局就是新比赛
+                m.start_round(names.clone()).unwrap();
+                assert_eq!(m.round(), 1);
+                assert!(m.totals().iter().all(|t| t.1 == 0));
+                return;
+            }
+            assert!(m.in_progress());
+        }
+        panic!("match did not finish");
+    }
+
+    #[test]
+    fn test_set_rules_between_matches() {
+        let mut m = Match::with_seed(3, RuleSet::default());
+        let rules = RuleSet { stack_draws: true, ..RuleSet::default() };
+        let ev = m.set_rules(rules.clone()).unwrap();
+        assert!(matches!(&ev[..], [GE::RulesChanged { .. }]));
+        assert_eq!(m.rules(), &rules);
+        assert_eq!(m.game().rules(), &rules);
+
+        // 比赛进行中(包括两局之间)不能改房规
+        m.start_round(vec![(PlayerId(0), "A".to_string()), (PlayerId(1), "B".to_string())]).unwrap();
+        assert!(matches!(m.set_rules(RuleSet::default()), Err(RuleError::RulesLocked)));
+        assert_eq!(m.rules(), &rules);
+    }
+
+    // 开局失败时比赛没有开始, 还能改房规后重新开局
+    #[test]
+    fn test_failed_start_keeps_match_open() {
+        let mut m = Match::with_seed(3, RuleSet { starting_hand_size: 60, ..RuleSet::default() });
+        let players = vec![(PlayerId(0), "A".to_string()), (PlayerId(1), "B".to_string())];
+        assert!(matches!(m.start_round(players.clone()), Err(RuleError::DeckEmpty)));
+        assert_eq!(m.round(), 0);
+        m.set_rules(RuleSet::default()).unwrap();
+        m.start_round(players).unwrap();
+        assert_eq!(m.round(), 1);
+        assert!(m.game().started);
+    }
+
+    #[test]
+    fn test_roster_keeps_player_ids() {
+        // 中间的人离开后, 其他人沿用原来的 id
+        let roster = vec![(PlayerId(0), "A".to_string()), (PlayerId(2), "C".to_string())];
+        let mut m = Match::with_seed(5, RuleSet::default());
+        m.start_round(roster).unwrap();
+        assert_eq!(m.game().seating(), vec![PlayerId(0), PlayerId(2)]);
+        assert_eq!(m.game().get_player_hand(PlayerId(2)).len(), 7);
+        assert!(m.game().get_player_hand(PlayerId(1)).is_empty());
+    }
+
+    #[test]
+    fn test_roster_change_keeps_match_scores() {
+        let player = |i: usize| (PlayerId(i), format!("Bot-{}", i));
+        let rules = RuleSet { target_score: 10_000, ..RuleSet::default() };
+        let mut m = Match::with_seed(7, rules);
+        let mut bots: Vec<Box<dyn Strategy>> = (0..4).map(|_| Box::new(HeuristicBot) as Box<dyn Strategy>).collect();
+        m.start_round(vec![player(0), player(1), player(2)]).unwrap();
+        play_round(&mut m, &mut bots);
+        let before = m.totals();
+        assert!(before.iter().any(|t| t.1 > 0));
+
+        // 1 号离开, 3 号加入: 比赛继续, 其余人的分数保留
+        m.start_round(vec![player(0), player(2), player(3)]).unwrap();
+        assert_eq!(m.round(), 2);
+        assert!(m.in_progress());
+        assert_eq!(m.game().seating(), vec![PlayerId(0), PlayerId(2), PlayerId(3)]);
+        // 第一局庄家是 2 号, 庄家顺延到 0 号
+        assert_eq!(m.dealer(), PlayerId(0));
+        let mut expected = vec![before[0].clone(), before[2].clone(), ("Bot-3".to_string(), 0)];
+        assert_eq!(m.totals(), expected);
+
+        let ev = play_round(&mut m, &mut bots);
+        let Some(GE::RoundOver { winner, points, totals, .. }) =
+            ev.iter().find(|e| matches!(e, GE::RoundOver { .. })).cloned()
+        else {
+            panic!("missing RoundOver");
+        };
+        let slot = [PlayerId(0), PlayerId(2), PlayerId(3)].iter().position(|&p| p == winner).unwrap();
+        expected[slot].1 += points;
+        assert_eq!(totals, expected);
+    }
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{connect, local_addr, recv, send, start_server, Server};
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use uno::game::events::GameEvent as GE;
+use uno::game::PlayerId;
+use uno::protocol::codec::{read_frame, MsgPack};
+use uno::protocol::{decode, Capability, Client2Server, Codec, ErrorCode, Frame, Server2Client, WireFormat, PROTOCOL_VERSION};
+
+type Ws = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// 用随机端口启动 async_server 并打开 WebSocket 网关, 返回 (进程, TCP 地址, WebSocket 地址)
+fn start_gateway() -> (Server, String, String) {
+    let ws_addr = local_addr();
+    let (server, addr) = start_server(env!("CARGO_BIN_EXE_async_server"), &[("UNO_WS_ADDR", ws_addr.clone())]);
+    (server, addr, ws_addr)
+}
+
+// 服务器进程刚启动时端口可能还没监听, 重试几次
+async fn connect_ws(ws_addr: &str) -> Ws {
+    for _ in 0..50 {
+        if let Ok((conn, _)) = tokio_tungstenite::connect_async(format!("ws://{}", ws_addr)).await {
+            return conn;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("websocket gateway did not start");
+}
+
+async fn ws_send(ws: &mut Ws, msg: &Client2Server) {
+    ws.send(Message::text(serde_json::to_string(msg).unwrap())).await.unwrap();
+}
+
+async fn ws_recv(ws: &mut Ws) -> Server2Client {
+    loop {
+        let frame = timeout(Duration::from_secs(5), ws.next()).await.unwrap().unwrap().unwrap();
+        if let Message::Text(text) = frame {
+            return decode(&text).unwrap().unwrap();
+        }
+    }
+}
+
+fn join(name: &str) -> Client2Server {
+    Client2Server::JoinGame {
+        room_id: "mixed".into(),
+        name: name.into(),
+        seed: None,
+        rules: None,
+        password: None,
+    }
+}
+
+#[cfg(test)]
+mod ws_gateway_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_browser_and_tcp_share_a_table() {
+        let (_server, addr, ws_addr) = start_gateway();
+        let mut ws = connect_ws(&ws_addr).await;
+        let (mut lines, mut w) = connect(&addr).await;
+
+        let hello = Client2Server::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            client_name: "browser".into(),
+            capabilities: vec![Capability::Chat],
+            formats: Vec::new(),
+        };
+        ws_send(&mut ws, &hello).await;
+        assert!(matches!(ws_recv(&mut ws).await, Server2Client::HelloAck { .. }));
+
+        // 房间还不存在时加入失败; TCP 玩家建房当房主, 浏览器玩家坐到同一桌
+        ws_send(&mut ws, &join("web")).await;
+        assert!(matches!(ws_recv(&mut ws).await, Server2Client::ServerError { code: ErrorCode::RoomNotFound, .. }));
+        send(&mut w, &Client2Server::CreateRoom {
+            room_id: "mixed".into(),
+            name: "tcp".into(),
+            max_players: 4,
+            password: None,
+            rules: None,
+        })
+        .await;
+        assert!(matches!(recv(&mut lines).await, Server2Client::Welcome { player_id: PlayerId(0), .. }));
+        ws_send(&mut ws, &join("web")).await;
+        assert!(matches!(ws_recv(&mut ws).await, Server2Client::Welcome { player_id: PlayerId(1), .. }));
+        loop {
+            if let Server2Client::Events(ev) = recv(&mut lines).await {
+                if ev.iter().any(|e| matches!(e, GE::PlayerJoined { name, .. } if name == "web")) {
+                    break;
+                }
+            }
+        }
+
+        // 房主开局, 浏览器玩家拿到自己的手牌
+        send(&mut w, &Client2Server::StartGame { player_id: PlayerId(0) }).await;
+        loop {
+            if let Server2Client::PlayerState { player_id, hand } = ws_recv(&mut ws).await {
+                if !hand.is_empty() {
+                    assert_eq!(player_id, PlayerId(1));
+                    break;
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_msgpack() {
+        let (_server, addr, ws_addr) = start_gateway();
+        let hello = Client2Server::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            client_name: "compact".into(),
+            capabilities: Vec::new(),
+            formats: vec![WireFormat::Unknown, WireFormat::MsgPack],
+        };
+        let mut ws = connect_ws(&ws_addr).await;
+
+        // WebSocket: HelloAck 还是文本帧, 之后用二进制帧
+        ws_send(&mut ws, &hello).await;
+        assert!(matches!(ws_recv(&mut ws).await, Server2Client::HelloAck { format: WireFormat::MsgPack, .. }));
+        let payload = MsgPack.encode_payload(&Client2Server::ListRooms).unwrap();
+        ws.send(Message::binary(payload)).await.unwrap();
+        let frame = timeout(Duration::from_secs(5), ws.next()).await.unwrap().unwrap().unwrap();
+        let Message::Binary(data) = frame else { panic!("expected binary frame") };
+        assert!(matches!(MsgPack.decode_payload(&data), Frame::Msg(Server2Client::RoomList { .. })));
+
+        // TCP: HelloAck 是一行 JSON, 之后换成长度前缀的帧
+        let addr_cl = addr.clone();
+        let handle = tokio::task::spawn_blocking(move || {
+            let mut stream = std::net::TcpStream::connect(addr_cl).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let mut out = Vec::new();
+            WireFormat::Json.encode(&hello, &mut out).unwrap();
+            std::io::Write::write_all(&mut stream, &out).unwrap();
+            let mut buf = Vec::new();
+            let ack = read_frame::<Server2Client>(&WireFormat::Json, &mut stream, &mut buf).unwrap();
+            assert!(matches!(ack, Some(Frame::Msg(Server2Client::HelloAck { format: WireFormat::MsgPack, .. }))));
+
+            let mut out = Vec::new();
+            MsgPack.encode(&Client2Server::ListRooms, &mut out).unwrap();
+            std::io::Write::write_all(&mut stream, &out).unwrap();
+            let reply = read_frame::<Server2Client>(&MsgPack, &mut stream, &mut buf).unwrap();
+            assert!(matches!(reply, Some(Frame::Msg(Server2Client::RoomList { .. }))));
+        });
+        timeout(Duration::from_secs(10), handle).await.unwrap().unwrap();
+    }
+
+    // WebSocket 端口被占用时服务器照常接受 TCP 玩家
+    #[tokio::test]
+    async fn test_busy_ws_port_keeps_tcp() {
+        let busy = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let ws_addr = busy.local_addr().unwrap().to_string();
+        let (_server, addr) = start_server(env!("CARGO_BIN_EXE_async_server"), &[("UNO_WS_ADDR", ws_addr)]);
+        let (mut lines, mut w) = connect(&addr).await;
+        send(&mut w, &Client2Server::ListRooms).await;
+        assert!(matches!(recv(&mut lines).await, Server2Client::RoomList { rooms } if rooms.is_empty()));
+    }
+}